@@ -0,0 +1,190 @@
+//! Times lexing, parsing and evaluating each workload in `benches/workloads`.
+//!
+//! Results are printed and written as CSV to `target/bench/workloads.csv`, or to
+//! the path given with `--save <path>`. Passing `--baseline <path>` with a file
+//! saved by an earlier run prints the change against it:
+//!
+//! ```sh
+//! cargo bench --bench workloads -- --save before.csv
+//! cargo bench --bench workloads -- --baseline before.csv
+//! ```
+use rost_interpreter::{
+    evaluator::Eval, lexer::Lexer, object::Environment, parser::Parser, token::TokenType,
+};
+use std::{
+    collections::HashMap,
+    env, fs,
+    hint::black_box,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+const WORKLOADS: [(&str, &str); 5] = [
+    ("fib", include_str!("workloads/fib.mky")),
+    ("strings", include_str!("workloads/strings.mky")),
+    ("arrays", include_str!("workloads/arrays.mky")),
+    ("closures", include_str!("workloads/closures.mky")),
+    ("calls", include_str!("workloads/calls.mky")),
+];
+
+const DEFAULT_OUTPUT: &str = "target/bench/workloads.csv";
+const HEADER: &str = "workload,phase,iterations,mean_ns,min_ns,bytes_per_sec";
+
+/// Each measurement runs for at least this long and at least `MIN_ITERATIONS` times.
+const MEASUREMENT_TIME: Duration = Duration::from_millis(500);
+const MIN_ITERATIONS: u32 = 10;
+
+struct Measurement {
+    workload: &'static str,
+    phase: &'static str,
+    iterations: u32,
+    mean: Duration,
+    min: Duration,
+    bytes: usize,
+}
+
+impl Measurement {
+    /// Source throughput, for the phases that scale with the size of the source.
+    fn bytes_per_sec(&self) -> Option<f64> {
+        (self.bytes > 0).then(|| self.bytes as f64 / self.mean.as_secs_f64())
+    }
+
+    fn to_csv(&self) -> String {
+        format!(
+            "{},{},{},{},{},{}",
+            self.workload,
+            self.phase,
+            self.iterations,
+            self.mean.as_nanos(),
+            self.min.as_nanos(),
+            self.bytes_per_sec()
+                .map(|b| format!("{:.0}", b))
+                .unwrap_or_default()
+        )
+    }
+}
+
+fn measure(
+    workload: &'static str,
+    phase: &'static str,
+    bytes: usize,
+    mut run: impl FnMut(),
+) -> Measurement {
+    run();
+    let mut iterations = 0;
+    let mut min = Duration::MAX;
+    let started = Instant::now();
+    while iterations < MIN_ITERATIONS || started.elapsed() < MEASUREMENT_TIME {
+        let start = Instant::now();
+        run();
+        min = min.min(start.elapsed());
+        iterations += 1;
+    }
+    Measurement {
+        workload,
+        phase,
+        iterations,
+        mean: started.elapsed() / iterations,
+        min,
+        bytes,
+    }
+}
+
+fn lex(source: &str) -> usize {
+    let mut lexer = Lexer::new(source);
+    let mut tokens = 0;
+    while lexer.next_token().r#type != TokenType::EOF {
+        tokens += 1;
+    }
+    tokens
+}
+
+fn bench_workload(name: &'static str, source: &'static str) -> Vec<Measurement> {
+    let program = Parser::new(Lexer::new(source))
+        .parse_program()
+        .unwrap_or_else(|e| panic!("{} doesn't parse: {}", name, e));
+    vec![
+        measure(name, "lex", source.len(), || {
+            black_box(lex(black_box(source)));
+        }),
+        measure(name, "parse", source.len(), || {
+            let program = Parser::new(Lexer::new(black_box(source))).parse_program();
+            black_box(program.unwrap());
+        }),
+        measure(name, "eval", 0, || {
+            let mut env = Environment::new(None);
+            let result = program.on_eval(&mut env);
+            black_box(result.unwrap_or_else(|e| panic!("{} failed: {}", name, e)));
+        }),
+    ]
+}
+
+/// Mean times by (workload, phase) from a file written by an earlier run.
+fn read_baseline(path: &str) -> HashMap<(String, String), u128> {
+    let contents =
+        fs::read_to_string(path).unwrap_or_else(|e| panic!("can't read {}: {}", path, e));
+    contents
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(',').collect();
+            let mean = fields.get(3)?.parse().ok()?;
+            Some(((fields[0].to_string(), fields[1].to_string()), mean))
+        })
+        .collect()
+}
+
+fn main() {
+    let mut output = String::from(DEFAULT_OUTPUT);
+    let mut baseline = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--save" => output = args.next().expect("--save needs a path"),
+            "--baseline" => {
+                baseline = Some(read_baseline(
+                    &args.next().expect("--baseline needs a path"),
+                ))
+            }
+            // Flags such as `--bench` that cargo passes to every bench target.
+            _ => {}
+        }
+    }
+
+    let mut csv = vec![String::from(HEADER)];
+    for (name, source) in WORKLOADS {
+        for measurement in bench_workload(name, source) {
+            let change = baseline
+                .as_ref()
+                .and_then(|b| b.get(&(name.to_string(), measurement.phase.to_string())))
+                .map(|&before| {
+                    let after = measurement.mean.as_nanos() as f64;
+                    format!(
+                        " ({:+.1}%)",
+                        (after - before as f64) / before as f64 * 100.0
+                    )
+                })
+                .unwrap_or_default();
+            println!(
+                "{:<9} {:<6} {:>12.3?} mean {:>12.3?} min {}{}",
+                measurement.workload,
+                measurement.phase,
+                measurement.mean,
+                measurement.min,
+                measurement
+                    .bytes_per_sec()
+                    .map(|b| format!("{:>8.1} MB/s", b / 1e6))
+                    .unwrap_or_default(),
+                change
+            );
+            csv.push(measurement.to_csv());
+        }
+    }
+
+    if let Some(parent) = Path::new(&output).parent() {
+        fs::create_dir_all(parent).expect("can't create the output directory");
+    }
+    fs::write(&output, csv.join("\n") + "\n")
+        .unwrap_or_else(|e| panic!("can't write {}: {}", output, e));
+    println!("saved {}", output);
+}
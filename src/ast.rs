@@ -1,5 +1,12 @@
-use crate::token::Token;
-use std::fmt::{Debug, Display};
+use crate::{
+    resolver::{Layout, Resolution},
+    token::{Position, Token},
+};
+use std::{
+    cell::Cell,
+    fmt::{Debug, Display},
+    rc::Rc,
+};
 
 pub trait NodeTrait {
     fn token_literal(&self) -> String;
@@ -9,6 +16,7 @@ pub trait StatementTrait: NodeTrait {
     fn statement_node();
 }
 
+#[allow(dead_code)]
 trait ExpressionTrait: NodeTrait {
     fn expression_node(&self);
 }
@@ -41,6 +49,7 @@ pub enum Statement {
     Let(LetStatement),
     Return(ReturnStatement),
     Expression(ExpressionStatement),
+    Throw(ThrowStatement),
 }
 
 impl NodeTrait for Statement {
@@ -49,6 +58,19 @@ impl NodeTrait for Statement {
             Statement::Let(ls) => ls.token_literal(),
             Statement::Return(rs) => rs.token_literal(),
             Statement::Expression(es) => es.token_literal(),
+            Statement::Throw(ts) => ts.token_literal(),
+        }
+    }
+}
+
+impl Statement {
+    /// Where the statement starts in the source.
+    pub fn position(&self) -> Position {
+        match self {
+            Statement::Let(ls) => ls.token.position,
+            Statement::Return(rs) => rs.token.position,
+            Statement::Expression(es) => es.token.position,
+            Statement::Throw(ts) => ts.token.position,
         }
     }
 }
@@ -59,6 +81,7 @@ impl Display for Statement {
             Statement::Let(ls) => write!(f, "{}", ls),
             Statement::Return(rs) => write!(f, "{}", rs),
             Statement::Expression(es) => write!(f, "{}", es),
+            Statement::Throw(ts) => write!(f, "{}", ts),
         }
     }
 }
@@ -77,9 +100,34 @@ pub enum Expression {
     StringLiteral(StringLiteral),
     ArrayLiteral(ArrayLiteral),
     IndexExpression(IndexExpression),
+    TryExpression(Box<TryExpression>),
+    MacroLiteral(MacroLiteral),
     Default,
 }
 
+impl Expression {
+    /// Where the expression starts in the source.
+    pub fn position(&self) -> Position {
+        match self {
+            Expression::Identifier(i) => i.token.position,
+            Expression::IntegerLiteral(i) => i.token.position,
+            Expression::PrefixExpression(p) => p.token.position,
+            Expression::InfixExpression(i) => i.left.position(),
+            Expression::Boolean(b) => b.token.position,
+            Expression::IfExpression(i) => i.token.position,
+            Expression::BlockStatement(b) => b.token.position,
+            Expression::FunctionLiteral(fl) => fl.token.position,
+            Expression::CallExpression(c) => c.function.position(),
+            Expression::StringLiteral(s) => s.token.position,
+            Expression::ArrayLiteral(al) => al.token.position,
+            Expression::IndexExpression(ie) => ie.left.position(),
+            Expression::TryExpression(te) => te.token.position,
+            Expression::MacroLiteral(ml) => ml.token.position,
+            Expression::Default => Position::default(),
+        }
+    }
+}
+
 impl Display for Expression {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -93,6 +141,8 @@ impl Display for Expression {
             Expression::CallExpression(c) => write!(f, "{}", c),
             Expression::ArrayLiteral(al) => write!(f, "{}", al),
             Expression::IndexExpression(ie) => write!(f, "{}", ie),
+            Expression::TryExpression(te) => write!(f, "{}", te),
+            Expression::MacroLiteral(ml) => write!(f, "{}", ml),
             _ => write!(f, "Default"),
         }
     }
@@ -149,6 +199,28 @@ impl Display for ReturnStatement {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThrowStatement {
+    pub token: Token,
+    pub value: Expression,
+}
+
+impl NodeTrait for ThrowStatement {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+}
+
+impl StatementTrait for ThrowStatement {
+    fn statement_node() {}
+}
+
+impl Display for ThrowStatement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {};", self.token_literal(), self.value)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ExpressionStatement {
     pub token: Token,
@@ -175,6 +247,8 @@ impl Display for ExpressionStatement {
 pub struct Identifier {
     pub token: Token,
     pub value: String,
+    /// Filled in by the resolver; unresolved identifiers are looked up by name.
+    pub resolution: Cell<Option<Resolution>>,
 }
 
 impl NodeTrait for Identifier {
@@ -343,8 +417,10 @@ impl Display for BlockStatement {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct FunctionLiteral {
     pub token: Token,
-    pub parameters: Vec<Identifier>,
-    pub body: BlockStatement,
+    /// Shared with the functions the literal evaluates to.
+    pub parameters: Rc<[Identifier]>,
+    pub body: Rc<BlockStatement>,
+    pub layout: Rc<Layout>,
 }
 
 impl NodeTrait for FunctionLiteral {
@@ -476,3 +552,667 @@ impl Display for IndexExpression {
         write!(f, "({}[{}])", self.left, self.index)
     }
 }
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CatchClause {
+    pub parameter: Identifier,
+    pub body: BlockStatement,
+    /// The catch body is a scope of its own, so the parameter doesn't
+    /// overwrite a variable of the same name outside it.
+    pub layout: Rc<Layout>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TryExpression {
+    pub token: Token,
+    pub block: BlockStatement,
+    pub catch: Option<CatchClause>,
+    pub finally: Option<BlockStatement>,
+}
+
+impl NodeTrait for TryExpression {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+}
+
+impl ExpressionTrait for TryExpression {
+    fn expression_node(&self) {}
+}
+
+impl Display for TryExpression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "try {}", self.block)?;
+        if let Some(catch) = &self.catch {
+            write!(f, " catch ({}) {}", catch.parameter, catch.body)?;
+        }
+        if let Some(finally) = &self.finally {
+            write!(f, " finally {}", finally)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MacroLiteral {
+    pub token: Token,
+    pub parameters: Vec<Identifier>,
+    pub body: BlockStatement,
+}
+
+impl NodeTrait for MacroLiteral {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+}
+
+impl ExpressionTrait for MacroLiteral {
+    fn expression_node(&self) {}
+}
+
+impl Display for MacroLiteral {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let parameters = self
+            .parameters
+            .iter()
+            .map(|p| p.value.to_string())
+            .collect::<Vec<String>>()
+            .join(", ");
+        write!(f, "{}({}) {}", self.token_literal(), parameters, self.body)
+    }
+}
+
+/// Read-only traversal of the AST. Every method defaults to the free function of
+/// the same name, which visits the node's children; overrides call it to keep walking.
+pub trait Visitor {
+    fn visit_program(&mut self, program: &Program) {
+        visit_program(self, program)
+    }
+
+    fn visit_statement(&mut self, statement: &Statement) {
+        visit_statement(self, statement)
+    }
+
+    fn visit_let_statement(&mut self, statement: &LetStatement) {
+        visit_let_statement(self, statement)
+    }
+
+    fn visit_return_statement(&mut self, statement: &ReturnStatement) {
+        visit_return_statement(self, statement)
+    }
+
+    fn visit_expression_statement(&mut self, statement: &ExpressionStatement) {
+        visit_expression_statement(self, statement)
+    }
+
+    fn visit_throw_statement(&mut self, statement: &ThrowStatement) {
+        visit_throw_statement(self, statement)
+    }
+
+    fn visit_block_statement(&mut self, block: &BlockStatement) {
+        visit_block_statement(self, block)
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        visit_expression(self, expression)
+    }
+
+    fn visit_identifier(&mut self, _identifier: &Identifier) {}
+
+    fn visit_integer_literal(&mut self, _integer: &IntegerLiteral) {}
+
+    fn visit_boolean(&mut self, _boolean: &Boolean) {}
+
+    fn visit_string_literal(&mut self, _string: &StringLiteral) {}
+
+    fn visit_prefix_expression(&mut self, expression: &PrefixExpression) {
+        visit_prefix_expression(self, expression)
+    }
+
+    fn visit_infix_expression(&mut self, expression: &InfixExpression) {
+        visit_infix_expression(self, expression)
+    }
+
+    fn visit_if_expression(&mut self, expression: &IfExpression) {
+        visit_if_expression(self, expression)
+    }
+
+    fn visit_function_literal(&mut self, function: &FunctionLiteral) {
+        visit_function_literal(self, function)
+    }
+
+    fn visit_call_expression(&mut self, call: &CallExpression) {
+        visit_call_expression(self, call)
+    }
+
+    fn visit_array_literal(&mut self, array: &ArrayLiteral) {
+        visit_array_literal(self, array)
+    }
+
+    fn visit_index_expression(&mut self, expression: &IndexExpression) {
+        visit_index_expression(self, expression)
+    }
+
+    fn visit_try_expression(&mut self, expression: &TryExpression) {
+        visit_try_expression(self, expression)
+    }
+
+    fn visit_catch_clause(&mut self, catch: &CatchClause) {
+        visit_catch_clause(self, catch)
+    }
+
+    fn visit_macro_literal(&mut self, r#macro: &MacroLiteral) {
+        visit_macro_literal(self, r#macro)
+    }
+}
+
+pub fn visit_program<V: Visitor + ?Sized>(visitor: &mut V, program: &Program) {
+    for statement in &program.statements {
+        visitor.visit_statement(statement);
+    }
+}
+
+pub fn visit_statement<V: Visitor + ?Sized>(visitor: &mut V, statement: &Statement) {
+    match statement {
+        Statement::Let(ls) => visitor.visit_let_statement(ls),
+        Statement::Return(rs) => visitor.visit_return_statement(rs),
+        Statement::Expression(es) => visitor.visit_expression_statement(es),
+        Statement::Throw(ts) => visitor.visit_throw_statement(ts),
+    }
+}
+
+pub fn visit_let_statement<V: Visitor + ?Sized>(visitor: &mut V, statement: &LetStatement) {
+    visitor.visit_identifier(&statement.name);
+    visitor.visit_expression(&statement.value);
+}
+
+pub fn visit_return_statement<V: Visitor + ?Sized>(visitor: &mut V, statement: &ReturnStatement) {
+    visitor.visit_expression(&statement.return_value);
+}
+
+pub fn visit_expression_statement<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    statement: &ExpressionStatement,
+) {
+    visitor.visit_expression(&statement.expression);
+}
+
+pub fn visit_throw_statement<V: Visitor + ?Sized>(visitor: &mut V, statement: &ThrowStatement) {
+    visitor.visit_expression(&statement.value);
+}
+
+pub fn visit_block_statement<V: Visitor + ?Sized>(visitor: &mut V, block: &BlockStatement) {
+    for statement in &block.statements {
+        visitor.visit_statement(statement);
+    }
+}
+
+pub fn visit_expression<V: Visitor + ?Sized>(visitor: &mut V, expression: &Expression) {
+    match expression {
+        Expression::Identifier(i) => visitor.visit_identifier(i),
+        Expression::IntegerLiteral(i) => visitor.visit_integer_literal(i),
+        Expression::PrefixExpression(p) => visitor.visit_prefix_expression(p),
+        Expression::InfixExpression(i) => visitor.visit_infix_expression(i),
+        Expression::Boolean(b) => visitor.visit_boolean(b),
+        Expression::IfExpression(i) => visitor.visit_if_expression(i),
+        Expression::BlockStatement(b) => visitor.visit_block_statement(b),
+        Expression::FunctionLiteral(fl) => visitor.visit_function_literal(fl),
+        Expression::CallExpression(c) => visitor.visit_call_expression(c),
+        Expression::StringLiteral(s) => visitor.visit_string_literal(s),
+        Expression::ArrayLiteral(al) => visitor.visit_array_literal(al),
+        Expression::IndexExpression(ie) => visitor.visit_index_expression(ie),
+        Expression::TryExpression(te) => visitor.visit_try_expression(te),
+        Expression::MacroLiteral(ml) => visitor.visit_macro_literal(ml),
+        Expression::Default => {}
+    }
+}
+
+pub fn visit_prefix_expression<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    expression: &PrefixExpression,
+) {
+    visitor.visit_expression(&expression.right);
+}
+
+pub fn visit_infix_expression<V: Visitor + ?Sized>(visitor: &mut V, expression: &InfixExpression) {
+    visitor.visit_expression(&expression.left);
+    visitor.visit_expression(&expression.right);
+}
+
+pub fn visit_if_expression<V: Visitor + ?Sized>(visitor: &mut V, expression: &IfExpression) {
+    visitor.visit_expression(&expression.condition);
+    visitor.visit_block_statement(&expression.consequence);
+    if let Some(alternative) = &expression.alternative {
+        visitor.visit_block_statement(alternative);
+    }
+}
+
+pub fn visit_function_literal<V: Visitor + ?Sized>(visitor: &mut V, function: &FunctionLiteral) {
+    for parameter in function.parameters.iter() {
+        visitor.visit_identifier(parameter);
+    }
+    visitor.visit_block_statement(&function.body);
+}
+
+pub fn visit_call_expression<V: Visitor + ?Sized>(visitor: &mut V, call: &CallExpression) {
+    visitor.visit_expression(&call.function);
+    for argument in &call.arguments {
+        visitor.visit_expression(argument);
+    }
+}
+
+pub fn visit_array_literal<V: Visitor + ?Sized>(visitor: &mut V, array: &ArrayLiteral) {
+    for element in &array.elements {
+        visitor.visit_expression(element);
+    }
+}
+
+pub fn visit_index_expression<V: Visitor + ?Sized>(visitor: &mut V, expression: &IndexExpression) {
+    visitor.visit_expression(&expression.left);
+    visitor.visit_expression(&expression.index);
+}
+
+pub fn visit_try_expression<V: Visitor + ?Sized>(visitor: &mut V, expression: &TryExpression) {
+    visitor.visit_block_statement(&expression.block);
+    if let Some(catch) = &expression.catch {
+        visitor.visit_catch_clause(catch);
+    }
+    if let Some(finally) = &expression.finally {
+        visitor.visit_block_statement(finally);
+    }
+}
+
+pub fn visit_catch_clause<V: Visitor + ?Sized>(visitor: &mut V, catch: &CatchClause) {
+    visitor.visit_identifier(&catch.parameter);
+    visitor.visit_block_statement(&catch.body);
+}
+
+pub fn visit_macro_literal<V: Visitor + ?Sized>(visitor: &mut V, r#macro: &MacroLiteral) {
+    for parameter in &r#macro.parameters {
+        visitor.visit_identifier(parameter);
+    }
+    visitor.visit_block_statement(&r#macro.body);
+}
+
+/// Rebuilds the AST by value. Every method defaults to the free function of the
+/// same name, which folds the node's children and puts the node back together.
+pub trait Fold {
+    fn fold_program(&mut self, program: Program) -> Program {
+        fold_program(self, program)
+    }
+
+    fn fold_statement(&mut self, statement: Statement) -> Statement {
+        fold_statement(self, statement)
+    }
+
+    fn fold_let_statement(&mut self, statement: LetStatement) -> LetStatement {
+        fold_let_statement(self, statement)
+    }
+
+    fn fold_return_statement(&mut self, statement: ReturnStatement) -> ReturnStatement {
+        fold_return_statement(self, statement)
+    }
+
+    fn fold_expression_statement(&mut self, statement: ExpressionStatement) -> ExpressionStatement {
+        fold_expression_statement(self, statement)
+    }
+
+    fn fold_throw_statement(&mut self, statement: ThrowStatement) -> ThrowStatement {
+        fold_throw_statement(self, statement)
+    }
+
+    fn fold_block_statement(&mut self, block: BlockStatement) -> BlockStatement {
+        fold_block_statement(self, block)
+    }
+
+    fn fold_expression(&mut self, expression: Expression) -> Expression {
+        fold_expression(self, expression)
+    }
+
+    fn fold_identifier(&mut self, identifier: Identifier) -> Identifier {
+        identifier
+    }
+
+    fn fold_integer_literal(&mut self, integer: IntegerLiteral) -> IntegerLiteral {
+        integer
+    }
+
+    fn fold_boolean(&mut self, boolean: Boolean) -> Boolean {
+        boolean
+    }
+
+    fn fold_string_literal(&mut self, string: StringLiteral) -> StringLiteral {
+        string
+    }
+
+    fn fold_prefix_expression(&mut self, expression: PrefixExpression) -> PrefixExpression {
+        fold_prefix_expression(self, expression)
+    }
+
+    fn fold_infix_expression(&mut self, expression: InfixExpression) -> InfixExpression {
+        fold_infix_expression(self, expression)
+    }
+
+    fn fold_if_expression(&mut self, expression: IfExpression) -> IfExpression {
+        fold_if_expression(self, expression)
+    }
+
+    fn fold_function_literal(&mut self, function: FunctionLiteral) -> FunctionLiteral {
+        fold_function_literal(self, function)
+    }
+
+    fn fold_call_expression(&mut self, call: CallExpression) -> CallExpression {
+        fold_call_expression(self, call)
+    }
+
+    fn fold_array_literal(&mut self, array: ArrayLiteral) -> ArrayLiteral {
+        fold_array_literal(self, array)
+    }
+
+    fn fold_index_expression(&mut self, expression: IndexExpression) -> IndexExpression {
+        fold_index_expression(self, expression)
+    }
+
+    fn fold_try_expression(&mut self, expression: TryExpression) -> TryExpression {
+        fold_try_expression(self, expression)
+    }
+
+    fn fold_catch_clause(&mut self, catch: CatchClause) -> CatchClause {
+        fold_catch_clause(self, catch)
+    }
+
+    fn fold_macro_literal(&mut self, r#macro: MacroLiteral) -> MacroLiteral {
+        fold_macro_literal(self, r#macro)
+    }
+}
+
+pub fn fold_program<F: Fold + ?Sized>(folder: &mut F, program: Program) -> Program {
+    Program {
+        statements: program
+            .statements
+            .into_iter()
+            .map(|s| folder.fold_statement(s))
+            .collect(),
+    }
+}
+
+pub fn fold_statement<F: Fold + ?Sized>(folder: &mut F, statement: Statement) -> Statement {
+    match statement {
+        Statement::Let(ls) => Statement::Let(folder.fold_let_statement(ls)),
+        Statement::Return(rs) => Statement::Return(folder.fold_return_statement(rs)),
+        Statement::Expression(es) => Statement::Expression(folder.fold_expression_statement(es)),
+        Statement::Throw(ts) => Statement::Throw(folder.fold_throw_statement(ts)),
+    }
+}
+
+pub fn fold_let_statement<F: Fold + ?Sized>(
+    folder: &mut F,
+    statement: LetStatement,
+) -> LetStatement {
+    LetStatement {
+        token: statement.token,
+        name: folder.fold_identifier(statement.name),
+        value: folder.fold_expression(statement.value),
+    }
+}
+
+pub fn fold_return_statement<F: Fold + ?Sized>(
+    folder: &mut F,
+    statement: ReturnStatement,
+) -> ReturnStatement {
+    ReturnStatement {
+        token: statement.token,
+        return_value: folder.fold_expression(statement.return_value),
+    }
+}
+
+pub fn fold_expression_statement<F: Fold + ?Sized>(
+    folder: &mut F,
+    statement: ExpressionStatement,
+) -> ExpressionStatement {
+    ExpressionStatement {
+        token: statement.token,
+        expression: folder.fold_expression(statement.expression),
+    }
+}
+
+pub fn fold_throw_statement<F: Fold + ?Sized>(
+    folder: &mut F,
+    statement: ThrowStatement,
+) -> ThrowStatement {
+    ThrowStatement {
+        token: statement.token,
+        value: folder.fold_expression(statement.value),
+    }
+}
+
+pub fn fold_block_statement<F: Fold + ?Sized>(
+    folder: &mut F,
+    block: BlockStatement,
+) -> BlockStatement {
+    BlockStatement {
+        token: block.token,
+        statements: block
+            .statements
+            .into_iter()
+            .map(|s| folder.fold_statement(s))
+            .collect(),
+    }
+}
+
+pub fn fold_expression<F: Fold + ?Sized>(folder: &mut F, expression: Expression) -> Expression {
+    match expression {
+        Expression::Identifier(i) => Expression::Identifier(folder.fold_identifier(i)),
+        Expression::IntegerLiteral(i) => Expression::IntegerLiteral(folder.fold_integer_literal(i)),
+        Expression::PrefixExpression(p) => {
+            Expression::PrefixExpression(folder.fold_prefix_expression(p))
+        }
+        Expression::InfixExpression(i) => {
+            Expression::InfixExpression(folder.fold_infix_expression(i))
+        }
+        Expression::Boolean(b) => Expression::Boolean(folder.fold_boolean(b)),
+        Expression::IfExpression(i) => Expression::IfExpression(folder.fold_if_expression(i)),
+        Expression::BlockStatement(b) => Expression::BlockStatement(folder.fold_block_statement(b)),
+        Expression::FunctionLiteral(fl) => {
+            Expression::FunctionLiteral(folder.fold_function_literal(fl))
+        }
+        Expression::CallExpression(c) => Expression::CallExpression(folder.fold_call_expression(c)),
+        Expression::StringLiteral(s) => Expression::StringLiteral(folder.fold_string_literal(s)),
+        Expression::ArrayLiteral(al) => Expression::ArrayLiteral(folder.fold_array_literal(al)),
+        Expression::IndexExpression(ie) => {
+            Expression::IndexExpression(folder.fold_index_expression(ie))
+        }
+        Expression::TryExpression(te) => {
+            Expression::TryExpression(Box::new(folder.fold_try_expression(*te)))
+        }
+        Expression::MacroLiteral(ml) => Expression::MacroLiteral(folder.fold_macro_literal(ml)),
+        Expression::Default => Expression::Default,
+    }
+}
+
+pub fn fold_prefix_expression<F: Fold + ?Sized>(
+    folder: &mut F,
+    expression: PrefixExpression,
+) -> PrefixExpression {
+    PrefixExpression {
+        token: expression.token,
+        operator: expression.operator,
+        right: Box::new(folder.fold_expression(*expression.right)),
+    }
+}
+
+pub fn fold_infix_expression<F: Fold + ?Sized>(
+    folder: &mut F,
+    expression: InfixExpression,
+) -> InfixExpression {
+    InfixExpression {
+        token: expression.token,
+        left: Box::new(folder.fold_expression(*expression.left)),
+        operator: expression.operator,
+        right: Box::new(folder.fold_expression(*expression.right)),
+    }
+}
+
+pub fn fold_if_expression<F: Fold + ?Sized>(
+    folder: &mut F,
+    expression: IfExpression,
+) -> IfExpression {
+    IfExpression {
+        token: expression.token,
+        condition: Box::new(folder.fold_expression(*expression.condition)),
+        consequence: folder.fold_block_statement(expression.consequence),
+        alternative: expression
+            .alternative
+            .map(|alternative| folder.fold_block_statement(alternative)),
+    }
+}
+
+pub fn fold_function_literal<F: Fold + ?Sized>(
+    folder: &mut F,
+    function: FunctionLiteral,
+) -> FunctionLiteral {
+    FunctionLiteral {
+        token: function.token,
+        parameters: function
+            .parameters
+            .iter()
+            .map(|p| folder.fold_identifier(p.clone()))
+            .collect(),
+        body: Rc::new(folder.fold_block_statement(Rc::unwrap_or_clone(function.body))),
+        layout: function.layout,
+    }
+}
+
+pub fn fold_call_expression<F: Fold + ?Sized>(
+    folder: &mut F,
+    call: CallExpression,
+) -> CallExpression {
+    CallExpression {
+        token: call.token,
+        function: Box::new(folder.fold_expression(*call.function)),
+        arguments: call
+            .arguments
+            .into_iter()
+            .map(|a| folder.fold_expression(a))
+            .collect(),
+    }
+}
+
+pub fn fold_array_literal<F: Fold + ?Sized>(folder: &mut F, array: ArrayLiteral) -> ArrayLiteral {
+    ArrayLiteral {
+        token: array.token,
+        elements: array
+            .elements
+            .into_iter()
+            .map(|e| folder.fold_expression(e))
+            .collect(),
+    }
+}
+
+pub fn fold_index_expression<F: Fold + ?Sized>(
+    folder: &mut F,
+    expression: IndexExpression,
+) -> IndexExpression {
+    IndexExpression {
+        token: expression.token,
+        left: Box::new(folder.fold_expression(*expression.left)),
+        index: Box::new(folder.fold_expression(*expression.index)),
+    }
+}
+
+pub fn fold_try_expression<F: Fold + ?Sized>(
+    folder: &mut F,
+    expression: TryExpression,
+) -> TryExpression {
+    TryExpression {
+        token: expression.token,
+        block: folder.fold_block_statement(expression.block),
+        catch: expression
+            .catch
+            .map(|catch| folder.fold_catch_clause(catch)),
+        finally: expression
+            .finally
+            .map(|finally| folder.fold_block_statement(finally)),
+    }
+}
+
+pub fn fold_catch_clause<F: Fold + ?Sized>(folder: &mut F, catch: CatchClause) -> CatchClause {
+    CatchClause {
+        parameter: folder.fold_identifier(catch.parameter),
+        body: folder.fold_block_statement(catch.body),
+        layout: catch.layout,
+    }
+}
+
+pub fn fold_macro_literal<F: Fold + ?Sized>(folder: &mut F, r#macro: MacroLiteral) -> MacroLiteral {
+    MacroLiteral {
+        token: r#macro.token,
+        parameters: r#macro
+            .parameters
+            .into_iter()
+            .map(|p| folder.fold_identifier(p))
+            .collect(),
+        body: folder.fold_block_statement(r#macro.body),
+    }
+}
+
+/// A fold that passes every expression to a fallible closure after its children,
+/// stopping at the first error.
+struct Modifier<'a, F, E> {
+    modifier: &'a mut F,
+    error: Option<E>,
+}
+
+impl<F, E> Fold for Modifier<'_, F, E>
+where
+    F: FnMut(Expression) -> Result<Expression, E>,
+{
+    fn fold_expression(&mut self, expression: Expression) -> Expression {
+        if self.error.is_some() {
+            return expression;
+        }
+        let expression = fold_expression(self, expression);
+        if self.error.is_some() {
+            return expression;
+        }
+        match (self.modifier)(expression) {
+            Ok(expression) => expression,
+            Err(e) => {
+                self.error = Some(e);
+                Expression::Default
+            }
+        }
+    }
+}
+
+/// Rebuilds `program` bottom-up, passing every expression to `modifier` after
+/// its children have been modified.
+pub fn modify<F, E>(program: Program, modifier: &mut F) -> Result<Program, E>
+where
+    F: FnMut(Expression) -> Result<Expression, E>,
+{
+    let mut folder = Modifier {
+        modifier,
+        error: None,
+    };
+    let program = folder.fold_program(program);
+    match folder.error {
+        Some(e) => Err(e),
+        None => Ok(program),
+    }
+}
+
+pub fn modify_expression<F, E>(expression: Expression, modifier: &mut F) -> Result<Expression, E>
+where
+    F: FnMut(Expression) -> Result<Expression, E>,
+{
+    let mut folder = Modifier {
+        modifier,
+        error: None,
+    };
+    let expression = folder.fold_expression(expression);
+    match folder.error {
+        Some(e) => Err(e),
+        None => Ok(expression),
+    }
+}
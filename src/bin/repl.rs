@@ -1,8 +1,5 @@
-use rost_interpreter::{evaluator, lexer::Lexer, object::Environment, parser::Parser};
-use std::{
-    cell::RefCell,
-    io::{self, Write},
-};
+use rost_interpreter::interpreter::Interpreter;
+use std::io::{self, Write};
 
 const PROMPT: &str = ">> ";
 
@@ -21,7 +18,7 @@ const MONKEY_FACE: &str = r#"            __,__
 
 fn main() {
     println!("Hello! Welcome to the Monkey programming language REPL!");
-    let env = RefCell::new(Environment::new(None));
+    let mut interpreter = Interpreter::new();
     loop {
         print!("{}", PROMPT);
         io::stdout().flush().expect("Failed to flush stdout");
@@ -37,21 +34,9 @@ fn main() {
             break;
         }
 
-        let lexer = Lexer::new(input);
-        let mut parser = Parser::new(lexer);
-        let program = parser.parse_program();
-        match program {
-            Ok(prog) => {
-                let evaluated = evaluator::eval(prog, &mut env.borrow_mut());
-                match evaluated {
-                    Ok(evaled) => {
-                        println!("{}", evaled);
-                    }
-                    Err(e) => {
-                        eprintln!("{}", MONKEY_FACE);
-                        eprintln!("{}", e);
-                    }
-                }
+        match interpreter.eval_str(input) {
+            Ok(evaled) => {
+                println!("{}", evaled);
             }
             Err(e) => {
                 eprintln!("{}", MONKEY_FACE);
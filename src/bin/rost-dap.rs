@@ -0,0 +1,28 @@
+//! A debug adapter for Monkey, speaking DAP over stdin and stdout.
+use rost_interpreter::{dap, protocol::read_message};
+use std::{io, process, sync::mpsc, thread};
+
+fn main() {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut input = io::stdin().lock();
+        loop {
+            match read_message(&mut input) {
+                Ok(Some(message)) => {
+                    if sender.send(message).is_err() {
+                        break;
+                    }
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    eprintln!("rost-dap: {}", e);
+                    process::exit(1);
+                }
+            }
+        }
+    });
+    if let Err(e) = dap::serve(receiver, io::stdout()) {
+        eprintln!("rost-dap: {}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,31 @@
+//! A language server for Monkey, speaking LSP over stdin and stdout.
+use rost_interpreter::{
+    lsp::Server,
+    protocol::{read_message, write_message},
+};
+use std::{io, process};
+
+fn main() {
+    let mut input = io::stdin().lock();
+    let mut output = io::stdout().lock();
+    let mut server = Server::new();
+    loop {
+        let message = match read_message(&mut input) {
+            Ok(Some(message)) => message,
+            Ok(None) => break,
+            Err(e) => {
+                eprintln!("rost-lsp: {}", e);
+                process::exit(1);
+            }
+        };
+        if message["method"] == "exit" {
+            process::exit(if server.is_shut_down() { 0 } else { 1 });
+        }
+        for reply in server.handle(&message) {
+            if let Err(e) = write_message(&mut output, &reply) {
+                eprintln!("rost-lsp: {}", e);
+                process::exit(1);
+            }
+        }
+    }
+}
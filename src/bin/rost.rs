@@ -0,0 +1,169 @@
+use rost_interpreter::{
+    formatter::format_source,
+    interpreter::Interpreter,
+    linter::{lint_source, LintConfig, Rule, Severity},
+    profiler::Profiler,
+    tracer::{CallTracer, JsonTracer},
+};
+use std::{env, fs, io, process, rc::Rc};
+
+const USAGE: &str =
+    "Usage: rost run [--trace | --trace-json | --profile [--profile-stacks <out>]] <file>
+       rost fmt [--check] <file>...
+       rost lint [--allow <rule>] [--warn <rule>] [--deny <rule>] <file>...";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(|a| a.as_str()) {
+        Some("run") => run(&args[1..]),
+        Some("fmt") => fmt(&args[1..]),
+        Some("lint") => lint(&args[1..]),
+        _ => usage(),
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
+
+/// Runs a script, with `--trace` printing its calls to stderr or
+/// `--trace-json` writing them there as JSON lines. `--profile` prints a
+/// table of the time and memory each function used, and `--profile-stacks`
+/// also writes its call stacks for flamegraph tools.
+fn run(args: &[String]) {
+    let mut interpreter = Interpreter::new();
+    let mut path = None;
+    let mut profile = false;
+    let mut stacks = None;
+    let mut tracing = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--trace" if !tracing => {
+                tracing = true;
+                interpreter.set_tracer(Some(Rc::new(CallTracer::new(io::stderr()))));
+            }
+            "--trace-json" if !tracing => {
+                tracing = true;
+                interpreter.set_tracer(Some(Rc::new(JsonTracer::new(io::stderr()))));
+            }
+            "--profile" => profile = true,
+            "--profile-stacks" => match args.next() {
+                Some(out) => stacks = Some(out),
+                None => usage(),
+            },
+            _ if path.is_none() && !arg.starts_with("--") => path = Some(arg),
+            _ => usage(),
+        }
+    }
+    let Some(path) = path else { usage() };
+    let profiler = Rc::new(Profiler::new());
+    if profile || stacks.is_some() {
+        if tracing {
+            usage();
+        }
+        interpreter.set_tracer(Some(profiler.clone()));
+    }
+    let result = interpreter.eval_file(path);
+    if profile {
+        eprint!("{}", profiler.report());
+    }
+    if let Some(out) = stacks {
+        if let Err(e) = fs::File::create(out).and_then(|f| profiler.write_collapsed_stacks(f)) {
+            eprintln!("{}: {}", out, e);
+            process::exit(1);
+        }
+    }
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+/// Rewrites each file in place, or with `--check` lists the files that would change.
+fn fmt(args: &[String]) {
+    let check = args.iter().any(|a| a == "--check");
+    let paths: Vec<&String> = args.iter().filter(|a| *a != "--check").collect();
+    if paths.is_empty() {
+        usage();
+    }
+    let mut failed = false;
+    for path in paths {
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("{}: {}", path, e);
+                failed = true;
+                continue;
+            }
+        };
+        let formatted = match format_source(&source) {
+            Ok(formatted) => formatted,
+            Err(e) => {
+                eprintln!("{}: {}", path, e);
+                failed = true;
+                continue;
+            }
+        };
+        if formatted == source {
+            continue;
+        }
+        if check {
+            println!("{}", path);
+            failed = true;
+        } else if let Err(e) = fs::write(path, formatted) {
+            eprintln!("{}: {}", path, e);
+            failed = true;
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+/// Prints every diagnostic and fails if any of them is an error.
+fn lint(args: &[String]) {
+    let mut config = LintConfig::new();
+    let mut paths = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let severity = match arg.as_str() {
+            "--allow" => Severity::Allow,
+            "--warn" => Severity::Warning,
+            "--deny" => Severity::Error,
+            _ => {
+                paths.push(arg);
+                continue;
+            }
+        };
+        match args.next().and_then(|name| Rule::from_name(name)) {
+            Some(rule) => config.set_severity(rule, severity),
+            None => usage(),
+        }
+    }
+    if paths.is_empty() {
+        usage();
+    }
+    let mut failed = false;
+    for path in paths {
+        let diagnostics = match fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|source| lint_source(&source, &config).map_err(|e| e.to_string()))
+        {
+            Ok(diagnostics) => diagnostics,
+            Err(e) => {
+                eprintln!("{}: {}", path, e);
+                failed = true;
+                continue;
+            }
+        };
+        for diagnostic in diagnostics {
+            println!("{}:{}", path, diagnostic);
+            failed |= diagnostic.severity == Severity::Error;
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
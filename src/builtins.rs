@@ -1,43 +1,245 @@
-use crate::ast::Identifier;
+use crate::convert::{FromObject, IntoObject};
 use crate::evaluator::EvaluationError;
-use crate::object::{BuiltIn, Integer, Object, ObjectTrait};
+use crate::object::{BuiltIn, ErrorObj, Integer, Object, ObjectTrait};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt::{Debug, Display},
+    io::{self, Write},
+    rc::Rc,
+};
+
+pub type BuiltInFunction = Rc<dyn Fn(&[Object]) -> Result<Object, EvaluationError>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arity {
+    Exact(usize),
+    AtLeast(usize),
+    Any,
+}
+
+impl Arity {
+    pub fn accepts(&self, count: usize) -> bool {
+        match self {
+            Arity::Exact(n) => count == *n,
+            Arity::AtLeast(n) => count >= *n,
+            Arity::Any => true,
+        }
+    }
+}
+
+impl Display for Arity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Arity::Exact(n) => write!(f, "{}", n),
+            Arity::AtLeast(n) => write!(f, "at least {}", n),
+            Arity::Any => write!(f, "any"),
+        }
+    }
+}
+
+/// Where `puts` writes: standard output unless redirected. Clones write to
+/// the same place.
+#[derive(Clone)]
+struct Output(Rc<RefCell<Box<dyn Write>>>);
+
+impl Default for Output {
+    fn default() -> Output {
+        Output(Rc::new(RefCell::new(Box::new(io::stdout()))))
+    }
+}
+
+impl Debug for Output {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Output")
+    }
+}
+
+impl PartialEq for Output {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Output {}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub enum BuiltInFunction {
-    LenFunction,
+pub struct Builtins {
+    functions: HashMap<String, BuiltIn>,
+    output: Output,
 }
 
-pub fn is_builtin(name: Identifier) -> Result<Object, EvaluationError> {
-    match name.value.as_str() {
-        "len" => Ok(Object::BuiltIn(BuiltIn {
-            func: BuiltInFunction::LenFunction,
-        })),
-        &_ => Err(EvaluationError::IdentError(String::from(
-            "Not a builtin function",
-        ))),
+impl Default for Builtins {
+    fn default() -> Builtins {
+        let mut builtins = Builtins::empty();
+        builtins.register("len", Arity::Exact(1), len);
+        let output = builtins.output.clone();
+        builtins.register("puts", Arity::Any, move |args| puts(&output, args));
+        builtins.register_fn("first", |array: Vec<Object>| array.first().cloned());
+        builtins.register_fn("last", |array: Vec<Object>| array.last().cloned());
+        builtins.register_fn("rest", |array: Vec<Object>| {
+            array.split_first().map(|(_, rest)| rest.to_vec())
+        });
+        builtins.register_fn("push", |mut array: Vec<Object>, element: Object| {
+            array.push(element);
+            array
+        });
+        builtins.register_fn("error", |kind: String, message: String| {
+            Object::Error(ErrorObj::new(&kind, &message))
+        });
+        builtins
+    }
+}
+
+impl Builtins {
+    pub fn new() -> Builtins {
+        Builtins::default()
+    }
+
+    pub fn empty() -> Builtins {
+        Builtins {
+            functions: HashMap::new(),
+            output: Output::default(),
+        }
+    }
+
+    /// Registers `func` under `name`, returning the builtin it replaced, if any.
+    pub fn register<F>(&mut self, name: &str, arity: Arity, func: F) -> Option<BuiltIn>
+    where
+        F: Fn(&[Object]) -> Result<Object, EvaluationError> + 'static,
+    {
+        let builtin = BuiltIn {
+            name: String::from(name),
+            arity,
+            func: Rc::new(func),
+        };
+        self.functions.insert(String::from(name), builtin)
+    }
+
+    /// Registers an ordinary Rust function, converting its arguments with
+    /// [`FromObject`] and its return value with [`IntoCallResult`].
+    pub fn register_fn<F, Args>(&mut self, name: &str, func: F) -> Option<BuiltIn>
+    where
+        F: NativeFunction<Args> + 'static,
+    {
+        let arity = func.arity();
+        self.register(name, arity, move |args| func.call(args))
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<BuiltIn> {
+        self.functions.remove(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<BuiltIn> {
+        self.functions.get(name).cloned()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.functions.contains_key(name)
+    }
+
+    /// Redirects everything `puts` prints.
+    pub fn set_output<W: Write + 'static>(&mut self, output: W) {
+        *self.output.0.borrow_mut() = Box::new(output);
+    }
+
+    pub fn flush_output(&self) -> io::Result<()> {
+        self.output.0.borrow_mut().flush()
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.functions.keys().map(|n| n.as_str()).collect();
+        names.sort_unstable();
+        names
     }
 }
 
 impl BuiltIn {
     pub fn run_builtin(&self, args: &[Object]) -> Result<Object, EvaluationError> {
-        match self.func {
-            BuiltInFunction::LenFunction => {
-                if args.len() != 1 {
-                    return Err(EvaluationError::BuiltInError(format!(
-                        "Wrong number of arguments.\nExpected: 1\nGot: {}",
-                        args.len()
-                    )));
-                }
-                match &args[0] {
-                    Object::String(s) => Ok(Object::Integer(Integer {
-                        value: s.value.len() as i64,
-                    })),
-                    e => Err(EvaluationError::BuiltInError(format!(
-                        "Wrong kind of argument.\nExpected: String\nGot: {}",
-                        e.r#type()
-                    ))),
-                }
+        if !self.arity.accepts(args.len()) {
+            return Err(EvaluationError::BuiltInError(format!(
+                "Wrong number of arguments.\nExpected: {}\nGot: {}",
+                self.arity,
+                args.len()
+            )));
+        }
+        (self.func)(args)
+    }
+}
+
+pub trait IntoCallResult {
+    fn into_call_result(self) -> Result<Object, EvaluationError>;
+}
+
+impl<T: IntoObject> IntoCallResult for T {
+    fn into_call_result(self) -> Result<Object, EvaluationError> {
+        Ok(self.into_object())
+    }
+}
+
+/// Host errors only need to be displayable; they surface as a `BuiltInError`.
+impl<T: IntoObject, E: Display> IntoCallResult for Result<T, E> {
+    fn into_call_result(self) -> Result<Object, EvaluationError> {
+        self.map(IntoObject::into_object)
+            .map_err(|e| EvaluationError::BuiltInError(e.to_string()))
+    }
+}
+
+pub trait NativeFunction<Args> {
+    fn arity(&self) -> Arity;
+    fn call(&self, args: &[Object]) -> Result<Object, EvaluationError>;
+}
+
+macro_rules! impl_native_function {
+    ($($arg:ident),*) => {
+        impl<F, R, $($arg),*> NativeFunction<($($arg,)*)> for F
+        where
+            F: Fn($($arg),*) -> R,
+            R: IntoCallResult,
+            $($arg: FromObject,)*
+        {
+            fn arity(&self) -> Arity {
+                let args: &[&str] = &[$(stringify!($arg)),*];
+                Arity::Exact(args.len())
+            }
+
+            #[allow(unused_variables, unused_mut)]
+            fn call(&self, args: &[Object]) -> Result<Object, EvaluationError> {
+                let mut args = args.iter();
+                (self)($($arg::from_object(args.next().expect("arity is checked before calling"))?),*)
+                    .into_call_result()
             }
         }
+    };
+}
+
+impl_native_function!();
+impl_native_function!(A);
+impl_native_function!(A, B);
+impl_native_function!(A, B, C);
+impl_native_function!(A, B, C, D);
+impl_native_function!(A, B, C, D, E);
+impl_native_function!(A, B, C, D, E, G);
+
+fn len(args: &[Object]) -> Result<Object, EvaluationError> {
+    match &args[0] {
+        Object::String(s) => Ok(Object::Integer(Integer {
+            value: s.value.len() as i64,
+        })),
+        Object::Array(a) => Ok(Object::Integer(Integer {
+            value: a.elements.len() as i64,
+        })),
+        e => Err(EvaluationError::BuiltInError(format!(
+            "Wrong kind of argument.\nExpected: String or Array\nGot: {}",
+            e.r#type()
+        ))),
+    }
+}
+
+fn puts(output: &Output, args: &[Object]) -> Result<Object, EvaluationError> {
+    let mut output = output.0.borrow_mut();
+    for arg in args {
+        writeln!(output, "{}", arg).map_err(|e| EvaluationError::BuiltInError(e.to_string()))?;
     }
+    Ok(Object::Null)
 }
@@ -0,0 +1,152 @@
+use crate::{
+    evaluator::EvaluationError,
+    object::{Array, Boolean, Hash, Integer, Object, ObjectTrait, StringObj},
+};
+use std::collections::HashMap;
+
+pub trait FromObject: Sized {
+    fn from_object(object: &Object) -> Result<Self, EvaluationError>;
+}
+
+pub trait IntoObject {
+    fn into_object(self) -> Object;
+}
+
+fn wrong_kind(expected: &str, got: &Object) -> EvaluationError {
+    EvaluationError::BuiltInError(format!(
+        "Wrong kind of argument.\nExpected: {}\nGot: {}",
+        expected,
+        got.r#type()
+    ))
+}
+
+impl FromObject for Object {
+    fn from_object(object: &Object) -> Result<Self, EvaluationError> {
+        Ok(object.clone())
+    }
+}
+
+impl IntoObject for Object {
+    fn into_object(self) -> Object {
+        self
+    }
+}
+
+impl FromObject for i64 {
+    fn from_object(object: &Object) -> Result<Self, EvaluationError> {
+        match object {
+            Object::Integer(i) => Ok(i.value),
+            e => Err(wrong_kind("INTEGER", e)),
+        }
+    }
+}
+
+impl IntoObject for i64 {
+    fn into_object(self) -> Object {
+        Object::Integer(Integer { value: self })
+    }
+}
+
+impl FromObject for bool {
+    fn from_object(object: &Object) -> Result<Self, EvaluationError> {
+        match object {
+            Object::Boolean(b) => Ok(b.value),
+            e => Err(wrong_kind("BOOLEAN", e)),
+        }
+    }
+}
+
+impl IntoObject for bool {
+    fn into_object(self) -> Object {
+        Object::Boolean(Boolean { value: self })
+    }
+}
+
+impl FromObject for String {
+    fn from_object(object: &Object) -> Result<Self, EvaluationError> {
+        match object {
+            Object::String(s) => Ok(s.value.clone()),
+            e => Err(wrong_kind("STRING", e)),
+        }
+    }
+}
+
+impl IntoObject for String {
+    fn into_object(self) -> Object {
+        Object::String(StringObj { value: self })
+    }
+}
+
+impl IntoObject for &str {
+    fn into_object(self) -> Object {
+        Object::String(StringObj {
+            value: String::from(self),
+        })
+    }
+}
+
+impl<T: FromObject> FromObject for Vec<T> {
+    fn from_object(object: &Object) -> Result<Self, EvaluationError> {
+        match object {
+            Object::Array(a) => a.elements.iter().map(T::from_object).collect(),
+            e => Err(wrong_kind("ARRAY", e)),
+        }
+    }
+}
+
+impl<T: IntoObject> IntoObject for Vec<T> {
+    fn into_object(self) -> Object {
+        Object::Array(Array {
+            elements: self.into_iter().map(IntoObject::into_object).collect(),
+        })
+    }
+}
+
+impl<T: FromObject> FromObject for HashMap<String, T> {
+    fn from_object(object: &Object) -> Result<Self, EvaluationError> {
+        match object {
+            Object::Hash(h) => h
+                .pairs
+                .iter()
+                .map(|(k, v)| Ok((k.clone(), T::from_object(v)?)))
+                .collect(),
+            e => Err(wrong_kind("HASH", e)),
+        }
+    }
+}
+
+impl<T: IntoObject> IntoObject for HashMap<String, T> {
+    fn into_object(self) -> Object {
+        Object::Hash(Hash {
+            pairs: self
+                .into_iter()
+                .map(|(k, v)| (k, v.into_object()))
+                .collect(),
+        })
+    }
+}
+
+/// `null` converts to `None`; anything else must convert to `T`.
+impl<T: FromObject> FromObject for Option<T> {
+    fn from_object(object: &Object) -> Result<Self, EvaluationError> {
+        match object {
+            Object::Null => Ok(None),
+            o => Ok(Some(T::from_object(o)?)),
+        }
+    }
+}
+
+impl<T: IntoObject> IntoObject for Option<T> {
+    fn into_object(self) -> Object {
+        match self {
+            Some(value) => value.into_object(),
+            None => Object::Null,
+        }
+    }
+}
+
+impl IntoObject for () {
+    fn into_object(self) -> Object {
+        Object::Null
+    }
+}
@@ -0,0 +1,1260 @@
+//! A lossless concrete syntax tree, for tools that rewrite source code.
+//!
+//! [`parse`] keeps every byte of the input, including whitespace, comments
+//! and malformed code, in an immutable tree of [`GreenNode`]s. A
+//! [`SyntaxNode`] is a view of a green node that also knows its parent and
+//! where it starts in the source. Edits build a new green tree that shares
+//! all unchanged nodes with the old one.
+//!
+//! This is the only grammar of the language. [`parse`] doesn't stop at the
+//! first error: tokens it didn't expect are wrapped in `Error` nodes and the
+//! errors are collected. A tree without errors converts to an
+//! [`crate::ast::Program`], which is how [`crate::parser::Parser`] builds one.
+use crate::{
+    ast::{
+        ArrayLiteral, BlockStatement, Boolean, CallExpression, CatchClause, Expression,
+        ExpressionStatement, FunctionLiteral, Identifier, IfExpression, IndexExpression,
+        InfixExpression, IntegerLiteral, LetStatement, MacroLiteral, PrefixExpression, Program,
+        ReturnStatement, Statement, StringLiteral, ThrowStatement, TryExpression,
+    },
+    lexer::{self, Lexer},
+    parser::{Parser, LOWEST, PREFIX},
+    token::{Position, Token, TokenType},
+};
+use std::{cell::Cell, fmt::Display, iter, ops::Range, rc::Rc};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SyntaxKind {
+    /// A token of the language, as the lexer read it. Strings keep their quotes.
+    Token(TokenType),
+    Whitespace,
+    /// A `//` comment, without the line break that ends it.
+    Comment,
+    /// A node around tokens the parser didn't expect, or a token for the
+    /// input after a NUL character, where the lexer stops.
+    Error,
+    Program,
+    LetStatement,
+    ReturnStatement,
+    ThrowStatement,
+    ExpressionStatement,
+    BlockStatement,
+    Identifier,
+    IntegerLiteral,
+    StringLiteral,
+    BooleanLiteral,
+    PrefixExpression,
+    InfixExpression,
+    ParenExpression,
+    IfExpression,
+    FunctionLiteral,
+    MacroLiteral,
+    ParameterList,
+    CallExpression,
+    ArgumentList,
+    ArrayLiteral,
+    IndexExpression,
+    TryExpression,
+    CatchClause,
+    FinallyClause,
+}
+
+impl SyntaxKind {
+    pub fn is_trivia(&self) -> bool {
+        matches!(self, SyntaxKind::Whitespace | SyntaxKind::Comment)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GreenToken {
+    kind: SyntaxKind,
+    text: String,
+}
+
+impl GreenToken {
+    pub fn new(kind: SyntaxKind, text: &str) -> GreenToken {
+        GreenToken {
+            kind,
+            text: String::from(text),
+        }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind.clone()
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GreenElement {
+    Node(Rc<GreenNode>),
+    Token(Rc<GreenToken>),
+}
+
+impl GreenElement {
+    pub fn kind(&self) -> SyntaxKind {
+        match self {
+            GreenElement::Node(node) => node.kind(),
+            GreenElement::Token(token) => token.kind(),
+        }
+    }
+
+    pub fn text_len(&self) -> usize {
+        match self {
+            GreenElement::Node(node) => node.text_len(),
+            GreenElement::Token(token) => token.text.len(),
+        }
+    }
+}
+
+/// A node of the green tree. It knows its length but not its position, so
+/// equal subtrees can be shared.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GreenNode {
+    kind: SyntaxKind,
+    len: usize,
+    children: Vec<GreenElement>,
+}
+
+impl GreenNode {
+    pub fn new(kind: SyntaxKind, children: Vec<GreenElement>) -> GreenNode {
+        GreenNode {
+            kind,
+            len: children.iter().map(GreenElement::text_len).sum(),
+            children,
+        }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind.clone()
+    }
+
+    /// The length of the node's text in bytes.
+    pub fn text_len(&self) -> usize {
+        self.len
+    }
+
+    pub fn children(&self) -> &[GreenElement] {
+        &self.children
+    }
+
+    /// A copy of the node with its child at `index` replaced.
+    pub fn replace_child(&self, index: usize, child: GreenElement) -> GreenNode {
+        let mut children = self.children.clone();
+        children[index] = child;
+        GreenNode::new(self.kind(), children)
+    }
+}
+
+impl Display for GreenNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for child in &self.children {
+            match child {
+                GreenElement::Node(node) => write!(f, "{}", node)?,
+                GreenElement::Token(token) => write!(f, "{}", token.text)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+pub struct SyntaxNode(Rc<NodeData>);
+
+struct NodeData {
+    green: Rc<GreenNode>,
+    parent: Option<SyntaxNode>,
+    /// The node's index among its parent's children.
+    index: usize,
+    offset: usize,
+}
+
+impl SyntaxNode {
+    pub fn new_root(green: Rc<GreenNode>) -> SyntaxNode {
+        SyntaxNode(Rc::new(NodeData {
+            green,
+            parent: None,
+            index: 0,
+            offset: 0,
+        }))
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.0.green.kind()
+    }
+
+    pub fn green(&self) -> &Rc<GreenNode> {
+        &self.0.green
+    }
+
+    /// The byte range of the node in the text of the root.
+    pub fn text_range(&self) -> Range<usize> {
+        self.0.offset..self.0.offset + self.0.green.text_len()
+    }
+
+    pub fn parent(&self) -> Option<SyntaxNode> {
+        self.0.parent.clone()
+    }
+
+    pub fn ancestors(&self) -> impl Iterator<Item = SyntaxNode> {
+        iter::successors(Some(self.clone()), SyntaxNode::parent)
+    }
+
+    pub fn children_with_tokens(&self) -> impl Iterator<Item = SyntaxElement> {
+        let parent = self.clone();
+        let mut offset = self.0.offset;
+        (0..self.0.green.children.len()).map(move |index| {
+            let start = offset;
+            let child = &parent.0.green.children[index];
+            offset += child.text_len();
+            match child {
+                GreenElement::Node(green) => SyntaxElement::Node(SyntaxNode(Rc::new(NodeData {
+                    green: Rc::clone(green),
+                    parent: Some(parent.clone()),
+                    index,
+                    offset: start,
+                }))),
+                GreenElement::Token(green) => SyntaxElement::Token(SyntaxToken {
+                    green: Rc::clone(green),
+                    parent: parent.clone(),
+                    index,
+                    offset: start,
+                }),
+            }
+        })
+    }
+
+    pub fn children(&self) -> impl Iterator<Item = SyntaxNode> {
+        self.children_with_tokens()
+            .filter_map(SyntaxElement::into_node)
+    }
+
+    /// This node and everything below it, in source order.
+    pub fn descendants_with_tokens(&self) -> impl Iterator<Item = SyntaxElement> {
+        let mut stack = vec![SyntaxElement::Node(self.clone())];
+        iter::from_fn(move || {
+            let element = stack.pop()?;
+            if let SyntaxElement::Node(node) = &element {
+                let children: Vec<SyntaxElement> = node.children_with_tokens().collect();
+                stack.extend(children.into_iter().rev());
+            }
+            Some(element)
+        })
+    }
+
+    pub fn descendants(&self) -> impl Iterator<Item = SyntaxNode> {
+        self.descendants_with_tokens()
+            .filter_map(SyntaxElement::into_node)
+    }
+
+    /// The tokens of the node, including trivia, in source order.
+    pub fn tokens(&self) -> impl Iterator<Item = SyntaxToken> {
+        self.descendants_with_tokens()
+            .filter_map(SyntaxElement::into_token)
+    }
+
+    /// The token whose text contains the byte at `offset`.
+    pub fn token_at_offset(&self, offset: usize) -> Option<SyntaxToken> {
+        self.tokens().find(|t| t.text_range().contains(&offset))
+    }
+
+    /// A new root in which this node is replaced by `replacement`. The tree
+    /// this node belongs to is left as it was.
+    pub fn replace_with(&self, replacement: GreenNode) -> Rc<GreenNode> {
+        let replacement = Rc::new(replacement);
+        match &self.0.parent {
+            Some(parent) => parent.replace_with(
+                parent
+                    .green()
+                    .replace_child(self.0.index, GreenElement::Node(replacement)),
+            ),
+            None => replacement,
+        }
+    }
+
+    /// The tree as indented lines of kinds and byte ranges, with the text of tokens.
+    pub fn debug_tree(&self) -> String {
+        let mut out = String::new();
+        self.write_tree(&mut out, 0);
+        out
+    }
+
+    fn write_tree(&self, out: &mut String, depth: usize) {
+        out.push_str(&format!("{}{:?}\n", "  ".repeat(depth), self));
+        for child in self.children_with_tokens() {
+            match child {
+                SyntaxElement::Node(node) => node.write_tree(out, depth + 1),
+                SyntaxElement::Token(token) => out.push_str(&format!(
+                    "{}{:?} {:?}\n",
+                    "  ".repeat(depth + 1),
+                    token,
+                    token.text()
+                )),
+            }
+        }
+    }
+}
+
+impl Display for SyntaxNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.green)
+    }
+}
+
+impl std::fmt::Debug for SyntaxNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}@{:?}", self.kind(), self.text_range())
+    }
+}
+
+#[derive(Clone)]
+pub struct SyntaxToken {
+    green: Rc<GreenToken>,
+    parent: SyntaxNode,
+    index: usize,
+    offset: usize,
+}
+
+impl SyntaxToken {
+    pub fn kind(&self) -> SyntaxKind {
+        self.green.kind()
+    }
+
+    pub fn text(&self) -> &str {
+        self.green.text()
+    }
+
+    pub fn text_range(&self) -> Range<usize> {
+        self.offset..self.offset + self.green.text.len()
+    }
+
+    pub fn parent(&self) -> SyntaxNode {
+        self.parent.clone()
+    }
+
+    /// A new root in which this token is replaced by `replacement`.
+    pub fn replace_with(&self, replacement: GreenToken) -> Rc<GreenNode> {
+        self.parent.replace_with(
+            self.parent
+                .green()
+                .replace_child(self.index, GreenElement::Token(Rc::new(replacement))),
+        )
+    }
+}
+
+impl Display for SyntaxToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.text())
+    }
+}
+
+impl std::fmt::Debug for SyntaxToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}@{:?}", self.kind(), self.text_range())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxElement {
+    pub fn kind(&self) -> SyntaxKind {
+        match self {
+            SyntaxElement::Node(node) => node.kind(),
+            SyntaxElement::Token(token) => token.kind(),
+        }
+    }
+
+    pub fn text_range(&self) -> Range<usize> {
+        match self {
+            SyntaxElement::Node(node) => node.text_range(),
+            SyntaxElement::Token(token) => token.text_range(),
+        }
+    }
+
+    pub fn into_node(self) -> Option<SyntaxNode> {
+        match self {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        }
+    }
+
+    pub fn into_token(self) -> Option<SyntaxToken> {
+        match self {
+            SyntaxElement::Node(_) => None,
+            SyntaxElement::Token(token) => Some(token),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    pub message: String,
+    pub position: Position,
+    /// The bytes of the token the error is about; empty at the end of the input.
+    pub range: Range<usize>,
+    /// The construct being parsed when the error was found, or
+    /// `Token(Illegal)` for input the lexer couldn't read.
+    pub node: SyntaxKind,
+}
+
+impl Display for SyntaxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.position, self.message)
+    }
+}
+
+/// Converts byte offsets to the line and column numbers the lexer gives tokens.
+///
+/// Columns count characters, so finding one means scanning its line. The
+/// index remembers the last position it found and scans on from there, which
+/// keeps lookups made in order linear even when the text is one long line.
+#[derive(Debug, Clone)]
+pub struct LineIndex {
+    text: String,
+    line_starts: Vec<usize>,
+    cursor: Cell<(usize, Position)>,
+}
+
+impl LineIndex {
+    pub fn new(text: &str) -> LineIndex {
+        let line_starts = iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        LineIndex {
+            text: String::from(text),
+            line_starts,
+            cursor: Cell::new((0, Position { line: 1, column: 1 })),
+        }
+    }
+
+    pub fn position(&self, offset: usize) -> Position {
+        let line = self.line_starts.partition_point(|start| *start <= offset);
+        let (start, column) = match self.cursor.get() {
+            (start, cursor) if cursor.line == line && start <= offset => (start, cursor.column),
+            _ => (self.line_starts[line - 1], 1),
+        };
+        let position = Position {
+            line,
+            column: column + self.text[start..offset].chars().count(),
+        };
+        self.cursor.set((offset, position));
+        position
+    }
+
+    /// The byte offset of `position`; the inverse of [`LineIndex::position`].
+    pub fn offset(&self, position: Position) -> usize {
+        let (start, column) = match self.cursor.get() {
+            (start, cursor) if cursor.line == position.line && cursor.column <= position.column => {
+                (start, cursor.column)
+            }
+            _ => match self.line_starts.get(position.line.saturating_sub(1)) {
+                Some(start) => (*start, 1),
+                None => return self.text.len(),
+            },
+        };
+        let offset = self.text[start..]
+            .char_indices()
+            .nth(position.column.saturating_sub(column))
+            .map_or(self.text.len(), |(i, _)| start + i);
+        if offset < self.text.len() {
+            self.cursor.set((offset, position));
+        }
+        offset
+    }
+}
+
+/// The result of [`parse`]: a tree that holds all of the input, and the
+/// errors found in it.
+#[derive(Debug, Clone)]
+pub struct Parse {
+    green: Rc<GreenNode>,
+    errors: Vec<SyntaxError>,
+}
+
+impl Parse {
+    pub fn green(&self) -> &Rc<GreenNode> {
+        &self.green
+    }
+
+    pub fn syntax(&self) -> SyntaxNode {
+        SyntaxNode::new_root(Rc::clone(&self.green))
+    }
+
+    pub fn errors(&self) -> &[SyntaxError] {
+        &self.errors
+    }
+
+    /// The program in the tree, or the first error.
+    pub fn to_program(&self) -> Result<Program, SyntaxError> {
+        if let Some(error) = self.errors.first() {
+            return Err(error.clone());
+        }
+        let lowering = Lowering {
+            lines: LineIndex::new(&self.green.to_string()),
+        };
+        Ok(lowering
+            .program(&self.syntax())
+            .expect("a tree without errors is complete"))
+    }
+}
+
+pub fn parse(input: &str) -> Parse {
+    parse_lexer(&mut Lexer::new(input))
+}
+
+/// Parses all the input of `lexer`, which is left at the end with the
+/// comments it skipped.
+pub(crate) fn parse_lexer(lexer: &mut Lexer<'_>) -> Parse {
+    let (tokens, rest) = lex(lexer);
+    let mut parser = CstParser {
+        tokens,
+        rest,
+        pos: 0,
+        trivia_done: false,
+        builder: Builder::default(),
+        errors: Vec::new(),
+    };
+    parser.program();
+    Parse {
+        green: Rc::new(parser.builder.finish()),
+        errors: parser.errors,
+    }
+}
+
+/// A token with the whitespace and comments before it.
+struct Lexed<'a> {
+    r#type: TokenType,
+    trivia: &'a str,
+    text: &'a str,
+    offset: usize,
+    position: Position,
+    error: Option<String>,
+}
+
+/// The tokens up to and including `EOF`, and any input after a NUL
+/// character, which the lexer takes for the end.
+fn lex<'a>(lexer: &mut Lexer<'a>) -> (Vec<Lexed<'a>>, &'a str) {
+    let input = lexer.input();
+    let mut tokens = Vec::new();
+    let mut end = 0;
+    loop {
+        let errors = lexer.errors().len();
+        let (token, span) = lexer.next_token_spanned();
+        tokens.push(Lexed {
+            r#type: token.r#type.clone(),
+            trivia: &input[end..span.start],
+            text: &input[span.clone()],
+            offset: span.start,
+            position: token.position,
+            error: lexer.errors().get(errors).map(|e| e.message.clone()),
+        });
+        end = span.end;
+        if token.r#type == TokenType::EOF {
+            return (tokens, &input[end..]);
+        }
+    }
+}
+
+/// Splits the text between two tokens into whitespace and comments.
+fn split_trivia(mut trivia: &str) -> impl Iterator<Item = (SyntaxKind, &str)> {
+    iter::from_fn(move || {
+        if trivia.is_empty() {
+            return None;
+        }
+        let (kind, len) = if trivia.starts_with("//") {
+            (
+                SyntaxKind::Comment,
+                trivia.find('\n').unwrap_or(trivia.len()),
+            )
+        } else {
+            let len = trivia.find("//").unwrap_or(trivia.len());
+            (SyntaxKind::Whitespace, len)
+        };
+        let (piece, rest) = trivia.split_at(len);
+        trivia = rest;
+        Some((kind, piece))
+    })
+}
+
+/// Builds a green tree from the top down. Nodes are closed once all their
+/// children are added; a checkpoint lets a node start before children that
+/// were already added, as an infix expression wraps its left operand.
+#[derive(Default)]
+struct Builder {
+    /// The open nodes and the index in `children` of their first child.
+    parents: Vec<(SyntaxKind, usize)>,
+    children: Vec<GreenElement>,
+}
+
+impl Builder {
+    fn start_node(&mut self, kind: SyntaxKind) {
+        self.parents.push((kind, self.children.len()));
+    }
+
+    fn checkpoint(&self) -> usize {
+        self.children.len()
+    }
+
+    fn start_node_at(&mut self, checkpoint: usize, kind: SyntaxKind) {
+        self.parents.push((kind, checkpoint));
+    }
+
+    fn token(&mut self, kind: SyntaxKind, text: &str) {
+        self.children
+            .push(GreenElement::Token(Rc::new(GreenToken::new(kind, text))));
+    }
+
+    fn finish_node(&mut self) {
+        let (kind, first) = self.parents.pop().expect("a node is open");
+        let children = self.children.split_off(first);
+        self.children
+            .push(GreenElement::Node(Rc::new(GreenNode::new(kind, children))));
+    }
+
+    fn finish(mut self) -> GreenNode {
+        match self.children.pop() {
+            Some(GreenElement::Node(root)) if self.children.is_empty() => Rc::unwrap_or_clone(root),
+            _ => panic!("the tree has a single root"),
+        }
+    }
+}
+
+fn starts_expression(token_type: &TokenType) -> bool {
+    matches!(
+        token_type,
+        TokenType::Ident
+            | TokenType::Int
+            | TokenType::String
+            | TokenType::True
+            | TokenType::False
+            | TokenType::Bang
+            | TokenType::Minus
+            | TokenType::LParen
+            | TokenType::LBracket
+            | TokenType::If
+            | TokenType::Function
+            | TokenType::Macro
+            | TokenType::Try
+            | TokenType::Illegal
+    )
+}
+
+fn describe(token_type: &TokenType) -> &'static str {
+    match token_type {
+        TokenType::Ident => "an identifier",
+        TokenType::Assign => "`=`",
+        TokenType::LParen => "`(`",
+        TokenType::RParen => "`)`",
+        TokenType::LBrace => "`{`",
+        TokenType::RBrace => "`}`",
+        TokenType::RBracket => "`]`",
+        _ => "a token",
+    }
+}
+
+/// The grammar of Monkey. Records errors and carries on.
+struct CstParser<'a> {
+    tokens: Vec<Lexed<'a>>,
+    rest: &'a str,
+    pos: usize,
+    /// Whether the trivia before the current token is in the tree yet.
+    trivia_done: bool,
+    builder: Builder,
+    errors: Vec<SyntaxError>,
+}
+
+impl CstParser<'_> {
+    fn current(&self) -> &TokenType {
+        &self.tokens[self.pos].r#type
+    }
+
+    fn at(&self, token_type: &TokenType) -> bool {
+        self.current() == token_type
+    }
+
+    /// Adds the trivia before the current token to the innermost open node,
+    /// so that nodes start with a token.
+    fn flush_trivia(&mut self) {
+        if !self.trivia_done {
+            for (kind, text) in split_trivia(self.tokens[self.pos].trivia) {
+                self.builder.token(kind, text);
+            }
+            self.trivia_done = true;
+        }
+    }
+
+    fn bump(&mut self) {
+        self.flush_trivia();
+        let token = &self.tokens[self.pos];
+        self.builder
+            .token(SyntaxKind::Token(token.r#type.clone()), token.text);
+        self.pos += 1;
+        self.trivia_done = false;
+    }
+
+    fn eat(&mut self, token_type: &TokenType) -> bool {
+        if self.at(token_type) {
+            self.bump();
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, token_type: &TokenType) -> bool {
+        if self.eat(token_type) {
+            return true;
+        }
+        self.error_expected(describe(token_type));
+        false
+    }
+
+    fn start_node(&mut self, kind: SyntaxKind) {
+        self.flush_trivia();
+        self.builder.start_node(kind);
+    }
+
+    fn checkpoint(&mut self) -> usize {
+        self.flush_trivia();
+        self.builder.checkpoint()
+    }
+
+    fn start_node_at(&mut self, checkpoint: usize, kind: SyntaxKind) {
+        self.builder.start_node_at(checkpoint, kind);
+    }
+
+    fn finish_node(&mut self) {
+        self.builder.finish_node();
+    }
+
+    /// The innermost construct being parsed; parameter lists and blocks
+    /// count as part of the function or expression they belong to.
+    fn context(&self) -> SyntaxKind {
+        self.builder
+            .parents
+            .iter()
+            .rev()
+            .map(|(kind, _)| kind)
+            .find(|kind| {
+                !matches!(
+                    kind,
+                    SyntaxKind::ParameterList | SyntaxKind::BlockStatement | SyntaxKind::Error
+                )
+            })
+            .cloned()
+            .unwrap_or(SyntaxKind::Program)
+    }
+
+    fn error(&mut self, message: String) {
+        let node = self.context();
+        self.error_in(node, message);
+    }
+
+    fn error_in(&mut self, node: SyntaxKind, message: String) {
+        let token = &self.tokens[self.pos];
+        self.errors.push(SyntaxError {
+            message,
+            position: token.position,
+            range: token.offset..token.offset + token.text.len(),
+            node,
+        });
+    }
+
+    fn error_expected(&mut self, expected: &str) {
+        let found = match self.current() {
+            TokenType::EOF => String::from("end of input"),
+            _ => format!("`{}`", self.tokens[self.pos].text),
+        };
+        self.error(format!("expected {}, found {}", expected, found));
+    }
+
+    fn bump_error(&mut self) {
+        self.start_node(SyntaxKind::Error);
+        self.bump();
+        self.finish_node();
+    }
+
+    fn program(&mut self) {
+        self.builder.start_node(SyntaxKind::Program);
+        while !self.at(&TokenType::EOF) {
+            self.statement();
+        }
+        self.flush_trivia();
+        if !self.rest.is_empty() {
+            self.error_in(
+                SyntaxKind::Token(TokenType::Illegal),
+                String::from("unexpected NUL character"),
+            );
+            self.builder.token(SyntaxKind::Error, self.rest);
+        }
+        self.finish_node();
+    }
+
+    /// Always consumes at least one token.
+    fn statement(&mut self) {
+        match self.current() {
+            TokenType::Let => self.let_statement(),
+            TokenType::Return => self.keyword_statement(SyntaxKind::ReturnStatement),
+            TokenType::Throw => self.keyword_statement(SyntaxKind::ThrowStatement),
+            token_type if starts_expression(token_type) => {
+                self.start_node(SyntaxKind::ExpressionStatement);
+                self.expression(LOWEST);
+                self.eat(&TokenType::Semicolon);
+                self.finish_node();
+            }
+            _ => {
+                self.error_expected("an expression");
+                self.bump_error();
+            }
+        }
+    }
+
+    fn let_statement(&mut self) {
+        self.start_node(SyntaxKind::LetStatement);
+        self.bump();
+        if self.at(&TokenType::Ident) {
+            self.identifier();
+        } else {
+            self.error_expected(describe(&TokenType::Ident));
+        }
+        self.expect(&TokenType::Assign);
+        self.expression(LOWEST);
+        self.eat(&TokenType::Semicolon);
+        self.finish_node();
+    }
+
+    fn keyword_statement(&mut self, kind: SyntaxKind) {
+        self.start_node(kind);
+        self.bump();
+        self.expression(LOWEST);
+        self.eat(&TokenType::Semicolon);
+        self.finish_node();
+    }
+
+    fn expression(&mut self, precedence: u8) {
+        let checkpoint = self.checkpoint();
+        if !self.prefix() {
+            return;
+        }
+        while !self.at(&TokenType::Semicolon) && precedence < Parser::precedences(self.current()) {
+            match self.current().clone() {
+                TokenType::LParen => {
+                    self.start_node_at(checkpoint, SyntaxKind::CallExpression);
+                    self.list(SyntaxKind::ArgumentList, TokenType::RParen, |p| {
+                        p.expression(LOWEST)
+                    });
+                }
+                TokenType::LBracket => {
+                    self.start_node_at(checkpoint, SyntaxKind::IndexExpression);
+                    self.bump();
+                    self.expression(LOWEST);
+                    self.expect(&TokenType::RBracket);
+                }
+                operator => {
+                    self.start_node_at(checkpoint, SyntaxKind::InfixExpression);
+                    self.bump();
+                    self.expression(Parser::precedences(&operator));
+                }
+            }
+            self.finish_node();
+        }
+    }
+
+    /// Returns false, without consuming anything, if there's no expression
+    /// here. Tokens that can end an expression or start a statement are left
+    /// for the enclosing rule; any other unexpected token becomes an `Error`.
+    fn prefix(&mut self) -> bool {
+        match self.current().clone() {
+            TokenType::Ident => self.identifier(),
+            TokenType::Int => {
+                if let Err(message) = lexer::parse_integer(self.tokens[self.pos].text) {
+                    self.error_in(SyntaxKind::IntegerLiteral, message);
+                }
+                self.leaf(SyntaxKind::IntegerLiteral);
+            }
+            TokenType::String => self.leaf(SyntaxKind::StringLiteral),
+            TokenType::True | TokenType::False => self.leaf(SyntaxKind::BooleanLiteral),
+            TokenType::Bang | TokenType::Minus => {
+                self.start_node(SyntaxKind::PrefixExpression);
+                self.bump();
+                self.expression(PREFIX);
+                self.finish_node();
+            }
+            TokenType::LParen => {
+                self.start_node(SyntaxKind::ParenExpression);
+                self.bump();
+                self.expression(LOWEST);
+                self.expect(&TokenType::RParen);
+                self.finish_node();
+            }
+            TokenType::LBracket => {
+                self.list(SyntaxKind::ArrayLiteral, TokenType::RBracket, |p| {
+                    p.expression(LOWEST)
+                });
+            }
+            TokenType::If => self.if_expression(),
+            TokenType::Function => self.function(SyntaxKind::FunctionLiteral),
+            TokenType::Macro => self.function(SyntaxKind::MacroLiteral),
+            TokenType::Try => self.try_expression(),
+            TokenType::Illegal => {
+                let token = &self.tokens[self.pos];
+                let message = token
+                    .error
+                    .clone()
+                    .unwrap_or_else(|| format!("unexpected `{}`", token.text));
+                self.error_in(SyntaxKind::Token(TokenType::Illegal), message);
+                self.bump_error();
+            }
+            TokenType::Semicolon
+            | TokenType::Comma
+            | TokenType::RParen
+            | TokenType::RBracket
+            | TokenType::RBrace
+            | TokenType::Let
+            | TokenType::Return
+            | TokenType::Throw
+            | TokenType::EOF => {
+                self.error_expected("an expression");
+                return false;
+            }
+            _ => {
+                self.error_expected("an expression");
+                self.bump_error();
+            }
+        }
+        true
+    }
+
+    fn leaf(&mut self, kind: SyntaxKind) {
+        self.start_node(kind);
+        self.bump();
+        self.finish_node();
+    }
+
+    fn identifier(&mut self) {
+        self.leaf(SyntaxKind::Identifier);
+    }
+
+    /// A node of `item`s separated by commas, from the current token up to `end`.
+    fn list(&mut self, kind: SyntaxKind, end: TokenType, item: fn(&mut Self)) {
+        self.start_node(kind);
+        self.bump();
+        if !self.at(&end) {
+            loop {
+                item(self);
+                if !self.eat(&TokenType::Comma) {
+                    break;
+                }
+            }
+        }
+        self.expect(&end);
+        self.finish_node();
+    }
+
+    fn block(&mut self) {
+        if !self.at(&TokenType::LBrace) {
+            self.error_expected(describe(&TokenType::LBrace));
+            return;
+        }
+        self.start_node(SyntaxKind::BlockStatement);
+        self.bump();
+        while !self.at(&TokenType::RBrace) && !self.at(&TokenType::EOF) {
+            self.statement();
+        }
+        self.expect(&TokenType::RBrace);
+        self.finish_node();
+    }
+
+    fn if_expression(&mut self) {
+        self.start_node(SyntaxKind::IfExpression);
+        self.bump();
+        self.expect(&TokenType::LParen);
+        self.expression(LOWEST);
+        self.expect(&TokenType::RParen);
+        self.block();
+        if self.eat(&TokenType::Else) {
+            self.block();
+        }
+        self.finish_node();
+    }
+
+    fn function(&mut self, kind: SyntaxKind) {
+        self.start_node(kind);
+        self.bump();
+        if self.at(&TokenType::LParen) {
+            self.list(SyntaxKind::ParameterList, TokenType::RParen, |p| {
+                p.parameter()
+            });
+        } else {
+            self.error_expected(describe(&TokenType::LParen));
+        }
+        self.block();
+        self.finish_node();
+    }
+
+    fn parameter(&mut self) {
+        match self.current() {
+            TokenType::Ident => self.identifier(),
+            TokenType::Comma | TokenType::RParen | TokenType::LBrace | TokenType::EOF => {
+                self.error_expected("a parameter name")
+            }
+            _ => {
+                self.error_expected("a parameter name");
+                self.bump_error();
+            }
+        }
+    }
+
+    fn try_expression(&mut self) {
+        self.start_node(SyntaxKind::TryExpression);
+        self.bump();
+        self.block();
+        let mut handled = false;
+        if self.at(&TokenType::Catch) {
+            self.start_node(SyntaxKind::CatchClause);
+            self.bump();
+            self.expect(&TokenType::LParen);
+            if self.at(&TokenType::Ident) {
+                self.identifier();
+            } else {
+                self.error_expected(describe(&TokenType::Ident));
+            }
+            self.expect(&TokenType::RParen);
+            self.block();
+            self.finish_node();
+            handled = true;
+        }
+        if self.at(&TokenType::Finally) {
+            self.start_node(SyntaxKind::FinallyClause);
+            self.bump();
+            self.block();
+            self.finish_node();
+            handled = true;
+        }
+        if !handled {
+            self.error_expected("`catch` or `finally`");
+        }
+        self.finish_node();
+    }
+}
+
+/// Builds the AST from a tree without errors, with the tokens [`Parser`]
+/// would have given each node.
+struct Lowering {
+    lines: LineIndex,
+}
+
+/// The node's own tokens, without trivia.
+fn tokens(node: &SyntaxNode) -> impl Iterator<Item = SyntaxToken> {
+    node.children_with_tokens()
+        .filter_map(SyntaxElement::into_token)
+        .filter(|t| !t.kind().is_trivia())
+}
+
+fn last_token(node: &SyntaxNode) -> Option<SyntaxToken> {
+    node.tokens().filter(|t| !t.kind().is_trivia()).last()
+}
+
+impl Lowering {
+    fn token(&self, token: &SyntaxToken) -> Option<Token> {
+        let SyntaxKind::Token(r#type) = token.kind() else {
+            return None;
+        };
+        let mut literal = token.text();
+        if r#type == TokenType::String {
+            literal = literal.strip_prefix('"').unwrap_or(literal);
+            literal = literal.strip_suffix('"').unwrap_or(literal);
+        }
+        Some(Token {
+            r#type,
+            literal: String::from(literal),
+            position: self.lines.position(token.text_range().start),
+        })
+    }
+
+    fn first_token(&self, node: &SyntaxNode) -> Option<Token> {
+        self.token(&tokens(node).next()?)
+    }
+
+    fn program(&self, node: &SyntaxNode) -> Option<Program> {
+        Some(Program {
+            statements: self.statements(node)?,
+        })
+    }
+
+    fn statements(&self, node: &SyntaxNode) -> Option<Vec<Statement>> {
+        node.children().map(|s| self.statement(&s)).collect()
+    }
+
+    fn statement(&self, node: &SyntaxNode) -> Option<Statement> {
+        let mut children = node.children();
+        let statement = match node.kind() {
+            SyntaxKind::LetStatement => Statement::Let(LetStatement {
+                token: self.first_token(node)?,
+                name: self.identifier(&children.next()?)?,
+                value: self.expression(&children.next()?)?,
+            }),
+            SyntaxKind::ReturnStatement => Statement::Return(ReturnStatement {
+                token: self.first_token(node)?,
+                return_value: self.expression(&children.next()?)?,
+            }),
+            SyntaxKind::ThrowStatement => Statement::Throw(ThrowStatement {
+                token: self.first_token(node)?,
+                value: self.expression(&children.next()?)?,
+            }),
+            SyntaxKind::ExpressionStatement => Statement::Expression(ExpressionStatement {
+                token: self.token(&node.tokens().find(|t| !t.kind().is_trivia())?)?,
+                expression: self.expression(&children.next()?)?,
+            }),
+            _ => return None,
+        };
+        Some(statement)
+    }
+
+    fn identifier(&self, node: &SyntaxNode) -> Option<Identifier> {
+        let token = self.first_token(node)?;
+        Some(Identifier {
+            value: token.literal.clone(),
+            token,
+            resolution: Cell::default(),
+        })
+    }
+
+    fn identifiers(&self, node: &SyntaxNode) -> Option<Vec<Identifier>> {
+        node.children().map(|i| self.identifier(&i)).collect()
+    }
+
+    fn block(&self, node: &SyntaxNode) -> Option<BlockStatement> {
+        Some(BlockStatement {
+            token: self.first_token(node)?,
+            statements: self.statements(node)?,
+        })
+    }
+
+    fn expressions(&self, node: &SyntaxNode) -> Option<Vec<Expression>> {
+        node.children().map(|e| self.expression(&e)).collect()
+    }
+
+    fn boxed(&self, node: Option<SyntaxNode>) -> Option<Box<Expression>> {
+        Some(Box::new(self.expression(&node?)?))
+    }
+
+    fn expression(&self, node: &SyntaxNode) -> Option<Expression> {
+        let mut children = node.children();
+        let expression = match node.kind() {
+            SyntaxKind::Identifier => Expression::Identifier(self.identifier(node)?),
+            SyntaxKind::IntegerLiteral => {
+                let token = self.first_token(node)?;
+                Expression::IntegerLiteral(IntegerLiteral {
+                    value: lexer::parse_integer(&token.literal).ok()?,
+                    token,
+                })
+            }
+            SyntaxKind::StringLiteral => {
+                let token = self.first_token(node)?;
+                Expression::StringLiteral(StringLiteral {
+                    value: token.literal.clone(),
+                    token,
+                })
+            }
+            SyntaxKind::BooleanLiteral => {
+                let token = self.first_token(node)?;
+                Expression::Boolean(Boolean {
+                    value: token.r#type == TokenType::True,
+                    token,
+                })
+            }
+            SyntaxKind::PrefixExpression => {
+                let token = self.first_token(node)?;
+                Expression::PrefixExpression(PrefixExpression {
+                    operator: token.literal.clone(),
+                    token,
+                    right: self.boxed(children.next())?,
+                })
+            }
+            SyntaxKind::InfixExpression => {
+                let token = self.first_token(node)?;
+                Expression::InfixExpression(InfixExpression {
+                    operator: token.literal.clone(),
+                    token,
+                    left: self.boxed(children.next())?,
+                    right: self.boxed(children.next())?,
+                })
+            }
+            SyntaxKind::ParenExpression => self.expression(&children.next()?)?,
+            SyntaxKind::IfExpression => Expression::IfExpression(IfExpression {
+                token: self.first_token(node)?,
+                condition: self.boxed(children.next())?,
+                consequence: self.block(&children.next()?)?,
+                alternative: match children.next() {
+                    Some(block) => Some(self.block(&block)?),
+                    None => None,
+                },
+            }),
+            SyntaxKind::FunctionLiteral => Expression::FunctionLiteral(FunctionLiteral {
+                token: self.first_token(node)?,
+                parameters: self.identifiers(&children.next()?)?.into(),
+                body: Rc::new(self.block(&children.next()?)?),
+                layout: Rc::default(),
+            }),
+            SyntaxKind::MacroLiteral => Expression::MacroLiteral(MacroLiteral {
+                token: self.first_token(node)?,
+                parameters: self.identifiers(&children.next()?)?,
+                body: self.block(&children.next()?)?,
+            }),
+            SyntaxKind::CallExpression => {
+                let function = children.next()?;
+                Expression::CallExpression(CallExpression {
+                    token: self.token(&last_token(&function)?)?,
+                    function: Box::new(self.expression(&function)?),
+                    arguments: self.expressions(&children.next()?)?,
+                })
+            }
+            SyntaxKind::ArrayLiteral => Expression::ArrayLiteral(ArrayLiteral {
+                token: self.first_token(node)?,
+                elements: self.expressions(node)?,
+            }),
+            SyntaxKind::IndexExpression => {
+                let left = children.next()?;
+                Expression::IndexExpression(IndexExpression {
+                    token: self.token(&last_token(&left)?)?,
+                    left: Box::new(self.expression(&left)?),
+                    index: self.boxed(children.next())?,
+                })
+            }
+            SyntaxKind::TryExpression => {
+                let mut try_expression = TryExpression {
+                    token: self.first_token(node)?,
+                    block: self.block(&children.next()?)?,
+                    catch: None,
+                    finally: None,
+                };
+                for clause in children {
+                    let mut parts = clause.children();
+                    match clause.kind() {
+                        SyntaxKind::CatchClause => {
+                            try_expression.catch = Some(CatchClause {
+                                parameter: self.identifier(&parts.next()?)?,
+                                body: self.block(&parts.next()?)?,
+                                layout: Rc::default(),
+                            })
+                        }
+                        SyntaxKind::FinallyClause => {
+                            try_expression.finally = Some(self.block(&parts.next()?)?)
+                        }
+                        _ => return None,
+                    }
+                }
+                Expression::TryExpression(Box::new(try_expression))
+            }
+            _ => return None,
+        };
+        Some(expression)
+    }
+}
@@ -0,0 +1,539 @@
+//! A debug adapter for Monkey: lets an editor set breakpoints in a script,
+//! step through it and inspect its calls and variables, speaking the Debug
+//! Adapter Protocol.
+//!
+//! The script runs on the thread that calls [`serve`]; requests arrive over a
+//! channel so they can still be read while the script is running or paused.
+use crate::{
+    evaluator::{inspect, DebugFrame, Debugger, EvaluationError},
+    interpreter::Interpreter,
+    object::{Environment, Object, ObjectTrait},
+    protocol::write_message,
+};
+use serde_json::{json, Value};
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeSet, VecDeque},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::mpsc::Receiver,
+};
+
+/// Scripts are single threaded, so the one thread always has this id.
+const THREAD_ID: i64 = 1;
+
+/// Answers the requests in `messages` until the client disconnects or the
+/// channel closes, writing responses and events to `output`.
+pub fn serve<W: Write + 'static>(messages: Receiver<Value>, output: W) -> io::Result<()> {
+    let adapter = Rc::new(Adapter {
+        client: Rc::new(Client::new(output)),
+        messages,
+        pending: RefCell::default(),
+        state: RefCell::default(),
+    });
+    while let Some(request) = adapter.next_request() {
+        adapter.handle(&request);
+        if adapter.state.borrow().disconnected {
+            break;
+        }
+    }
+    adapter.client.finish()
+}
+
+/// Writes messages to the client, numbering them as the protocol requires.
+struct Client {
+    output: RefCell<Box<dyn Write>>,
+    seq: Cell<i64>,
+    error: RefCell<Option<io::Error>>,
+}
+
+impl Client {
+    fn new<W: Write + 'static>(output: W) -> Client {
+        Client {
+            output: RefCell::new(Box::new(output)),
+            seq: Cell::new(0),
+            error: RefCell::default(),
+        }
+    }
+
+    fn send(&self, mut message: Value) {
+        if self.error.borrow().is_some() {
+            return;
+        }
+        self.seq.set(self.seq.get() + 1);
+        message["seq"] = json!(self.seq.get());
+        if let Err(e) = write_message(&mut *self.output.borrow_mut(), &message) {
+            *self.error.borrow_mut() = Some(e);
+        }
+    }
+
+    fn respond(&self, request: &Value, body: Value) {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": true,
+            "body": body,
+        }));
+    }
+
+    fn fail(&self, request: &Value, message: &str) {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": false,
+            "message": message,
+        }));
+    }
+
+    fn event(&self, event: &str, body: Value) {
+        self.send(json!({"type": "event", "event": event, "body": body}));
+    }
+
+    fn is_closed(&self) -> bool {
+        self.error.borrow().is_some()
+    }
+
+    fn finish(&self) -> io::Result<()> {
+        match self.error.borrow_mut().take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Sends what the script prints to the client as output events, a line at a time.
+struct OutputEvents {
+    client: Rc<Client>,
+    line: Vec<u8>,
+}
+
+impl Write for OutputEvents {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.line.extend_from_slice(buf);
+        if let Some(end) = self.line.iter().rposition(|b| *b == b'\n') {
+            let rest = self.line.split_off(end + 1);
+            let output = std::mem::replace(&mut self.line, rest);
+            self.client.event(
+                "output",
+                json!({"category": "stdout", "output": String::from_utf8_lossy(&output)}),
+            );
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.line.is_empty() {
+            let output = std::mem::take(&mut self.line);
+            self.client.event(
+                "output",
+                json!({"category": "stdout", "output": String::from_utf8_lossy(&output)}),
+            );
+        }
+        Ok(())
+    }
+}
+
+struct Adapter {
+    client: Rc<Client>,
+    messages: Receiver<Value>,
+    /// Requests that arrived while the script was running and can only be
+    /// answered once it pauses.
+    pending: RefCell<VecDeque<Value>>,
+    state: RefCell<State>,
+}
+
+#[derive(Default)]
+struct State {
+    program: Option<PathBuf>,
+    stop_on_entry: bool,
+    configured: bool,
+    running: bool,
+    disconnected: bool,
+    breakpoints: BTreeSet<usize>,
+    mode: Mode,
+    /// The call and line of the last statement, so a breakpoint only stops
+    /// once when several statements share its line, but again in every call
+    /// that reaches it.
+    last_line: Option<(usize, usize)>,
+    /// What each `variablesReference` handed out during the current pause refers to.
+    references: Vec<Reference>,
+}
+
+/// When to pause next, besides at breakpoints.
+#[derive(Debug, Clone, Copy, Default)]
+enum Mode {
+    #[default]
+    Run,
+    /// At the next statement, reporting the given reason.
+    Pause(&'static str),
+    /// At the next statement no deeper than this many frames.
+    StepOver(usize),
+    /// At the next statement in fewer than this many frames.
+    StepOut(usize),
+}
+
+enum Reference {
+    Scope(Environment),
+    Value(Object),
+}
+
+impl Adapter {
+    /// Takes the oldest pending request, waiting for one if there are none.
+    fn next_request(&self) -> Option<Value> {
+        if let Some(request) = self.pending.borrow_mut().pop_front() {
+            return Some(request);
+        }
+        self.messages.recv().ok()
+    }
+
+    /// Handles a request while no script is running.
+    fn handle(self: &Rc<Self>, request: &Value) {
+        match command(request) {
+            "initialize" => {
+                self.client
+                    .respond(request, json!({"supportsConfigurationDoneRequest": true}));
+                self.client.event("initialized", json!({}));
+            }
+            "launch" => {
+                let Some(program) = request["arguments"]["program"].as_str() else {
+                    self.client.fail(request, "launch needs a `program` to run");
+                    return;
+                };
+                let mut state = self.state.borrow_mut();
+                state.program = Some(PathBuf::from(program));
+                state.stop_on_entry = request["arguments"]["stopOnEntry"] == true;
+                drop(state);
+                self.client.respond(request, json!({}));
+                self.start();
+            }
+            "configurationDone" => {
+                self.state.borrow_mut().configured = true;
+                self.client.respond(request, json!({}));
+                self.start();
+            }
+            "disconnect" | "terminate" => self.disconnect(request),
+            "stackTrace" | "scopes" | "variables" | "continue" | "next" | "stepIn" | "stepOut"
+            | "pause" => self.client.fail(request, "the program is not running"),
+            _ => self.handle_any_time(request),
+        }
+    }
+
+    /// Handles the requests that make sense whether or not a script is running.
+    fn handle_any_time(&self, request: &Value) {
+        match command(request) {
+            "setBreakpoints" => {
+                let lines: Vec<usize> = request["arguments"]["breakpoints"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|b| b["line"].as_u64())
+                    .map(|line| line as usize)
+                    .collect();
+                self.state.borrow_mut().breakpoints = lines.iter().copied().collect();
+                let breakpoints: Vec<Value> = lines
+                    .iter()
+                    .map(|line| json!({"verified": true, "line": line}))
+                    .collect();
+                self.client
+                    .respond(request, json!({"breakpoints": breakpoints}));
+            }
+            "threads" => self.client.respond(
+                request,
+                json!({"threads": [{"id": THREAD_ID, "name": "main"}]}),
+            ),
+            command => self
+                .client
+                .fail(request, &format!("unsupported request `{}`", command)),
+        }
+    }
+
+    fn disconnect(&self, request: &Value) {
+        self.state.borrow_mut().disconnected = true;
+        self.client.respond(request, json!({}));
+    }
+
+    /// Runs the program once it has been launched and configured.
+    fn start(self: &Rc<Self>) {
+        let mut state = self.state.borrow_mut();
+        let program = match &state.program {
+            Some(program) if state.configured && !state.running => program.clone(),
+            _ => return,
+        };
+        state.running = true;
+        state.last_line = None;
+        state.mode = if state.stop_on_entry {
+            Mode::Pause("entry")
+        } else {
+            Mode::Run
+        };
+        drop(state);
+
+        let mut interpreter = Interpreter::new();
+        interpreter.set_output(OutputEvents {
+            client: Rc::clone(&self.client),
+            line: Vec::new(),
+        });
+        interpreter.set_debugger(Some(Rc::clone(self) as Rc<dyn Debugger>));
+        let result = interpreter.eval_file(&program);
+        interpreter.set_debugger(None);
+        if self.state.borrow().disconnected {
+            return;
+        }
+        let exit_code = match result {
+            Ok(_) => 0,
+            Err(e) => {
+                self.client.event(
+                    "output",
+                    json!({"category": "stderr", "output": format!("{}\n", e)}),
+                );
+                1
+            }
+        };
+        self.client.event("exited", json!({"exitCode": exit_code}));
+        self.client.event("terminated", json!({}));
+    }
+
+    /// Answers what can be answered while the script runs, and keeps the
+    /// rest for when it pauses. A script whose client has gone away runs to
+    /// the end unless it pauses.
+    fn poll(&self) -> Result<(), EvaluationError> {
+        loop {
+            let request = match self.messages.try_recv() {
+                Ok(request) => request,
+                Err(_) => return Ok(()),
+            };
+            if !self.pending.borrow().is_empty() {
+                self.pending.borrow_mut().push_back(request);
+                continue;
+            }
+            match command(&request) {
+                "pause" => {
+                    self.state.borrow_mut().mode = Mode::Pause("pause");
+                    self.client.respond(&request, json!({}));
+                }
+                "disconnect" | "terminate" => {
+                    self.disconnect(&request);
+                    return Err(EvaluationError::Cancelled);
+                }
+                "setBreakpoints" | "threads" => self.handle_any_time(&request),
+                _ => self.pending.borrow_mut().push_back(request),
+            }
+        }
+    }
+
+    /// Reports the pause and answers requests until one resumes the script.
+    fn pause(&self, reason: &str, frames: &[DebugFrame]) -> Result<(), EvaluationError> {
+        self.state.borrow_mut().references.clear();
+        self.client.event(
+            "stopped",
+            json!({"reason": reason, "threadId": THREAD_ID, "allThreadsStopped": true}),
+        );
+        let depth = frames.len();
+        loop {
+            if self.client.is_closed() {
+                return Err(EvaluationError::Cancelled);
+            }
+            let Some(request) = self.next_request() else {
+                return Err(EvaluationError::Cancelled);
+            };
+            let mode = match command(&request) {
+                "continue" => Mode::Run,
+                "next" => Mode::StepOver(depth),
+                "stepIn" => Mode::Pause("step"),
+                "stepOut" => Mode::StepOut(depth),
+                "stackTrace" => {
+                    self.client
+                        .respond(&request, self.stack_trace(&request, frames));
+                    continue;
+                }
+                "scopes" => {
+                    let frame = request["arguments"]["frameId"]
+                        .as_u64()
+                        .and_then(|id| frames.get(id as usize));
+                    match frame {
+                        Some(frame) => self.client.respond(&request, self.scopes(frame)),
+                        None => self.client.fail(&request, "no such frame"),
+                    }
+                    continue;
+                }
+                "variables" => {
+                    let reference = request["arguments"]["variablesReference"]
+                        .as_u64()
+                        .unwrap_or(0) as usize;
+                    match self.variables(reference) {
+                        Some(body) => self.client.respond(&request, body),
+                        None => self.client.fail(&request, "no such variables reference"),
+                    }
+                    continue;
+                }
+                "pause" => {
+                    self.client.respond(&request, json!({}));
+                    continue;
+                }
+                "disconnect" | "terminate" => {
+                    self.disconnect(&request);
+                    return Err(EvaluationError::Cancelled);
+                }
+                _ => {
+                    self.handle_any_time(&request);
+                    continue;
+                }
+            };
+            self.state.borrow_mut().mode = mode;
+            let body = match mode {
+                Mode::Run => json!({"allThreadsContinued": true}),
+                _ => json!({}),
+            };
+            self.client.respond(&request, body);
+            return Ok(());
+        }
+    }
+
+    fn stack_trace(&self, request: &Value, frames: &[DebugFrame]) -> Value {
+        let start = request["arguments"]["startFrame"].as_u64().unwrap_or(0) as usize;
+        let levels = match request["arguments"]["levels"].as_u64() {
+            Some(levels) if levels > 0 => levels as usize,
+            _ => frames.len(),
+        };
+        let source = self.source();
+        let stack_frames: Vec<Value> = frames
+            .iter()
+            .enumerate()
+            .rev()
+            .skip(start)
+            .take(levels)
+            .map(|(id, frame)| {
+                json!({
+                    "id": id,
+                    "name": frame.function,
+                    "line": frame.position.line,
+                    "column": frame.position.column,
+                    "source": source,
+                })
+            })
+            .collect();
+        json!({"stackFrames": stack_frames, "totalFrames": frames.len()})
+    }
+
+    fn source(&self) -> Value {
+        let state = self.state.borrow();
+        let path = state.program.as_deref().unwrap_or(Path::new(""));
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        json!({"name": name, "path": path.to_string_lossy()})
+    }
+
+    /// One scope for each environment from the frame's out to the globals.
+    fn scopes(&self, frame: &DebugFrame) -> Value {
+        let mut scopes = Vec::new();
+        let mut env = Some(frame.env.clone());
+        while let Some(scope) = env {
+            let outer = scope.outer();
+            let name = match (&outer, scopes.is_empty()) {
+                (None, _) => "Globals",
+                (Some(_), true) => "Locals",
+                (Some(_), false) => "Closure",
+            };
+            let reference = self.reference(Reference::Scope(scope));
+            scopes.push(json!({
+                "name": name,
+                "variablesReference": reference,
+                "expensive": false,
+            }));
+            env = outer;
+        }
+        json!({"scopes": scopes})
+    }
+
+    fn variables(&self, reference: usize) -> Option<Value> {
+        let variables: Vec<(String, Object)> = {
+            let state = self.state.borrow();
+            match state.references.get(reference.checked_sub(1)?)? {
+                Reference::Scope(env) => env.bindings(),
+                Reference::Value(Object::Array(array)) => array
+                    .elements
+                    .iter()
+                    .enumerate()
+                    .map(|(i, element)| (i.to_string(), element.clone()))
+                    .collect(),
+                Reference::Value(Object::Hash(hash)) => hash
+                    .pairs
+                    .iter()
+                    .map(|(key, value)| (format!("{:?}", key), value.clone()))
+                    .collect(),
+                Reference::Value(_) => Vec::new(),
+            }
+        };
+        let variables: Vec<Value> = variables
+            .into_iter()
+            .map(|(name, value)| self.variable(name, value))
+            .collect();
+        Some(json!({"variables": variables}))
+    }
+
+    fn variable(&self, name: String, value: Object) -> Value {
+        let rendered = inspect(&value);
+        let r#type = value.r#type();
+        let expandable = match &value {
+            Object::Array(array) => !array.elements.is_empty(),
+            Object::Hash(hash) => !hash.pairs.is_empty(),
+            _ => false,
+        };
+        let reference = if expandable {
+            self.reference(Reference::Value(value))
+        } else {
+            0
+        };
+        json!({
+            "name": name,
+            "value": rendered,
+            "type": r#type,
+            "variablesReference": reference,
+        })
+    }
+
+    fn reference(&self, reference: Reference) -> usize {
+        let mut state = self.state.borrow_mut();
+        state.references.push(reference);
+        state.references.len()
+    }
+}
+
+impl Debugger for Adapter {
+    fn before_statement(&self, frames: &[DebugFrame]) -> Result<(), EvaluationError> {
+        self.poll()?;
+        let Some(frame) = frames.last() else {
+            return Ok(());
+        };
+        let line = frame.position.line;
+        let depth = frames.len();
+        let mut state = self.state.borrow_mut();
+        let entered = state.last_line != Some((frame.call, line));
+        state.last_line = Some((frame.call, line));
+        let reason = if entered && state.breakpoints.contains(&line) {
+            Some("breakpoint")
+        } else {
+            match state.mode {
+                Mode::Run => None,
+                Mode::Pause(reason) => Some(reason),
+                Mode::StepOver(d) if depth <= d => Some("step"),
+                Mode::StepOut(d) if depth < d => Some("step"),
+                Mode::StepOver(_) | Mode::StepOut(_) => None,
+            }
+        };
+        drop(state);
+        match reason {
+            Some(reason) => self.pause(reason, frames),
+            None => Ok(()),
+        }
+    }
+}
+
+fn command(request: &Value) -> &str {
+    request["command"].as_str().unwrap_or_default()
+}
@@ -1,14 +1,18 @@
 use crate::{
     ast::{
-        BlockStatement, Expression, ExpressionStatement, Identifier, IfExpression, LetStatement,
-        Program, ReturnStatement, Statement,
+        self, ArrayLiteral, BlockStatement, CallExpression, Expression, ExpressionStatement,
+        Identifier, IfExpression, IntegerLiteral, LetStatement, Program, ReturnStatement,
+        Statement, StringLiteral, ThrowStatement, TryExpression,
     },
-    builtins::is_builtin,
     object::{
-        Boolean, Environment, Function, Integer, Object, ObjectTrait, ReturnValue, StringObj,
+        Array, Boolean, Environment, ErrorObj, Function, Integer, Object, ObjectTrait, Quote,
+        ReturnValue, StringObj,
     },
+    resolver::{self, ResolveError},
+    runtime::Runtime,
+    token::{Position, Token, TokenType},
 };
-use std::fmt::Display;
+use std::{fmt::Display, rc::Rc, time::Duration};
 
 const NULL: Object = Object::Null;
 const TRUE: Object = Object::Boolean(Boolean { value: true });
@@ -33,40 +37,90 @@ impl Eval for Program {
 
 impl Eval for ExpressionStatement {
     fn on_eval(&self, env: &mut Environment) -> Result<Object, EvaluationError> {
-        eval(self.expression.clone(), env)
+        self.expression.on_eval(env)
     }
 }
 
 impl Eval for Expression {
     fn on_eval(&self, env: &mut Environment) -> Result<Object, EvaluationError> {
+        env.runtime().step()?;
         match self {
             Expression::IntegerLiteral(i) => Ok(Object::Integer(Integer { value: i.value })),
             Expression::Boolean(b) => Ok(native_bool_to_bool_struct(b.value)),
             Expression::PrefixExpression(p) => {
-                let right = eval(*p.right.clone(), env);
+                let right = p.right.on_eval(env);
                 Ok(eval_prefix_expression(&p.operator, right?)?)
             }
             Expression::InfixExpression(i) => {
-                let left = eval(*i.left.clone(), env);
-                let right = eval(*i.right.clone(), env);
-                Ok(eval_infix_expression(&i.operator, left?, right?)?)
+                let left = i.left.on_eval(env);
+                let right = i.right.on_eval(env);
+                let (left, right) = (left?, right?);
+                if let (Object::String(l), Object::String(r)) = (&left, &right) {
+                    let bytes = size_of::<Object>() + l.value.len() + r.value.len();
+                    env.runtime().reserve(bytes)?;
+                }
+                match eval_infix_expression(&i.operator, left, right)? {
+                    s @ Object::String(_) => track_allocation(s, env),
+                    result => Ok(result),
+                }
             }
             Expression::BlockStatement(b) => Ok(eval_block_statement(b, env)?),
             Expression::IfExpression(i) => Ok(eval_if_expression(i, env)?),
             Expression::Identifier(i) => Ok(eval_identifier(i, env)?),
-            Expression::FunctionLiteral(fl) => Ok(Object::Function(Function {
-                parameters: fl.parameters.clone(),
-                body: fl.body.clone(),
-                env: env.clone(),
-            })),
+            Expression::FunctionLiteral(fl) => {
+                let function = Object::Function(Function {
+                    name: None,
+                    parameters: Rc::clone(&fl.parameters),
+                    body: Rc::clone(&fl.body),
+                    layout: Rc::clone(&fl.layout),
+                    env: env.clone(),
+                });
+                track_allocation(function, env)
+            }
+            Expression::CallExpression(ce) if is_call_to(ce, "quote") => quote(ce, env),
             Expression::CallExpression(ce) => {
-                let function = eval(*ce.function.clone(), env)?;
+                let function = ce.function.on_eval(env)?;
                 let args = eval_expressions(&ce.arguments, env)?;
-                apply_function(function, &args)
+                let is_builtin = matches!(function, Object::BuiltIn(_));
+                let result = match apply_function(&function, &args, env.runtime()) {
+                    Ok(result) => result,
+                    Err(e) => {
+                        env.runtime().record_frame(StackFrame::new(
+                            &function,
+                            &args,
+                            ce.function.position(),
+                        ));
+                        return Err(e);
+                    }
+                };
+                if is_builtin {
+                    return track_allocation(result, env);
+                }
+                Ok(result)
+            }
+            Expression::StringLiteral(s) => {
+                let string = Object::String(StringObj {
+                    value: s.value.clone(),
+                });
+                track_allocation(string, env)
+            }
+            Expression::ArrayLiteral(al) => {
+                let mut elements = Vec::with_capacity(al.elements.len());
+                let mut bytes = size_of::<Object>();
+                for element in &al.elements {
+                    let element = element.on_eval(env)?;
+                    bytes += element.allocation_size();
+                    env.runtime().reserve(bytes)?;
+                    elements.push(element);
+                }
+                track_allocation(Object::Array(Array { elements }), env)
             }
-            Expression::StringLiteral(s) => Ok(Object::String(StringObj {
-                value: s.value.clone(),
-            })),
+            Expression::IndexExpression(ie) => {
+                let left = ie.left.on_eval(env)?;
+                let index = ie.index.on_eval(env)?;
+                eval_index_expression(left, index)
+            }
+            Expression::TryExpression(te) => eval_try_expression(te, env),
             e => Err(EvaluationError::MatchError(format!(
                 "Missing implementation of eval on expression: {}",
                 e
@@ -77,39 +131,72 @@ impl Eval for Expression {
 
 impl Eval for Statement {
     fn on_eval(&self, env: &mut Environment) -> Result<Object, EvaluationError> {
-        match self {
-            Statement::Let(ls) => {
-                let obj = eval(ls.clone(), env)?;
-                Ok(obj)
-            }
-            Statement::Return(rs) => {
-                let obj = eval(rs.clone(), env)?;
-                Ok(obj)
-            }
-            Statement::Expression(es) => {
-                let obj = eval(es.clone(), env)?;
-                Ok(obj)
-            }
+        env.runtime().step()?;
+        env.runtime().before_statement(self.position(), env)?;
+        let Some(tracer) = env.runtime().tracer() else {
+            return eval_statement(self, env);
+        };
+        env.runtime().clear_traced_error();
+        tracer.enter_statement(self);
+        let result = eval_statement(self, env);
+        if let Err(e) = &result {
+            env.runtime()
+                .trace_error(tracer.as_ref(), e, self.position());
         }
+        tracer.leave_statement(self);
+        result
+    }
+}
+
+fn eval_statement(statement: &Statement, env: &mut Environment) -> Result<Object, EvaluationError> {
+    match statement {
+        Statement::Let(ls) => {
+            let obj = ls.on_eval(env)?;
+            Ok(obj)
+        }
+        Statement::Return(rs) => {
+            let obj = rs.on_eval(env)?;
+            Ok(obj)
+        }
+        Statement::Expression(es) => {
+            let obj = es.on_eval(env)?;
+            Ok(obj)
+        }
+        Statement::Throw(ts) => ts.on_eval(env),
     }
 }
 
 impl Eval for LetStatement {
     fn on_eval(&self, env: &mut Environment) -> Result<Object, EvaluationError> {
-        let val = eval(self.value.clone(), env)?;
-        Ok(env.set(&self.name.value, val))
+        let mut val = self.value.on_eval(env)?;
+        if let Object::Function(function) = &mut val {
+            if function.name.is_none() {
+                function.name = Some(self.name.value.clone());
+            }
+        }
+        Ok(bind(&self.name, val, env))
     }
 }
 
 impl Eval for ReturnStatement {
     fn on_eval(&self, env: &mut Environment) -> Result<Object, EvaluationError> {
-        let value = eval(self.return_value.clone(), env)?;
+        let value = self.return_value.on_eval(env)?;
         Ok(Object::ReturnValue(ReturnValue {
             value: Box::new(value),
         }))
     }
 }
 
+impl Eval for ThrowStatement {
+    fn on_eval(&self, env: &mut Environment) -> Result<Object, EvaluationError> {
+        let error = match self.value.on_eval(env)? {
+            Object::Error(e) => e,
+            value => ErrorObj::new("Error", &value.to_string()),
+        };
+        Err(EvaluationError::Thrown(error))
+    }
+}
+
 impl Eval for BlockStatement {
     fn on_eval(&self, env: &mut Environment) -> Result<Object, EvaluationError> {
         eval_block_statement(self, env)
@@ -123,16 +210,27 @@ impl Eval for Identifier {
 }
 
 pub fn eval<T: Eval + std::fmt::Debug>(
-    node: T,
+    node: &T,
     env: &mut Environment,
 ) -> Result<Object, EvaluationError> {
     node.on_eval(env)
 }
 
 fn eval_program(program: &Program, env: &mut Environment) -> Result<Object, EvaluationError> {
+    if let Err(errors) = resolver::resolve(program, env) {
+        let undefined: Vec<String> = errors
+            .iter()
+            .map(|ResolveError::UndefinedVariable(name, position)| {
+                format!("{} at {}", name, position)
+            })
+            .collect();
+        return Err(EvaluationError::IdentError(undefined.join(", ")));
+    }
+    let _run = env.runtime().begin();
     let mut result: Object = NULL;
     for statement in &program.statements {
-        result = eval(statement.clone(), env)?;
+        env.runtime().check_cancelled()?;
+        result = statement.on_eval(env)?;
         if let Object::ReturnValue(rv) = result {
             return Ok(*rv.value);
         }
@@ -140,6 +238,11 @@ fn eval_program(program: &Program, env: &mut Environment) -> Result<Object, Eval
     Ok(result)
 }
 
+fn track_allocation(object: Object, env: &Environment) -> Result<Object, EvaluationError> {
+    env.runtime().allocate(object.allocation_size())?;
+    Ok(object)
+}
+
 fn eval_prefix_expression(operator: &str, right: Object) -> Result<Object, EvaluationError> {
     match operator {
         "!" => Ok(eval_bang_operator_expression(right)),
@@ -171,9 +274,13 @@ fn eval_minus_prefix_operator_expression(right: Object) -> Result<Object, Evalua
             )))
         }
     };
-    Ok(Object::Integer(Integer {
-        value: -integer.value,
-    }))
+    match integer.value.checked_neg() {
+        Some(value) => Ok(Object::Integer(Integer { value })),
+        None => Err(EvaluationError::ArithmeticError(format!(
+            "integer overflow: -({})",
+            integer.value
+        ))),
+    }
 }
 
 fn eval_infix_expression(
@@ -217,20 +324,34 @@ fn eval_integer_infix_expression(
     operator: &str,
     left: Integer,
     right: Integer,
+) -> Result<Object, EvaluationError> {
+    let checked = match operator {
+        "+" => left.value.checked_add(right.value),
+        "-" => left.value.checked_sub(right.value),
+        "*" => left.value.checked_mul(right.value),
+        "/" if right.value == 0 => {
+            return Err(EvaluationError::ArithmeticError(String::from(
+                "division by zero",
+            )))
+        }
+        "/" => left.value.checked_div(right.value),
+        _ => return eval_integer_comparison(operator, left, right),
+    };
+    match checked {
+        Some(value) => Ok(Object::Integer(Integer { value })),
+        None => Err(EvaluationError::ArithmeticError(format!(
+            "integer overflow: {} {} {}",
+            left.value, operator, right.value
+        ))),
+    }
+}
+
+fn eval_integer_comparison(
+    operator: &str,
+    left: Integer,
+    right: Integer,
 ) -> Result<Object, EvaluationError> {
     match operator {
-        "+" => Ok(Object::Integer(Integer {
-            value: left.value + right.value,
-        })),
-        "-" => Ok(Object::Integer(Integer {
-            value: left.value - right.value,
-        })),
-        "*" => Ok(Object::Integer(Integer {
-            value: left.value * right.value,
-        })),
-        "/" => Ok(Object::Integer(Integer {
-            value: left.value / right.value,
-        })),
         "<" => Ok(native_bool_to_bool_struct(left.value < right.value)),
         ">" => Ok(native_bool_to_bool_struct(left.value > right.value)),
         "==" => Ok(native_bool_to_bool_struct(left.value == right.value)),
@@ -248,15 +369,114 @@ fn eval_if_expression(
     if_expression: &IfExpression,
     env: &mut Environment,
 ) -> Result<Object, EvaluationError> {
-    let condition = eval(*if_expression.condition.clone(), env)?;
+    let condition = if_expression.condition.on_eval(env)?;
     if is_truthy(condition) {
-        return eval(if_expression.consequence.clone(), env);
-    } else if if_expression.alternative.is_some() {
-        return eval(if_expression.alternative.clone().unwrap(), env);
+        return if_expression.consequence.on_eval(env);
+    } else if let Some(alternative) = &if_expression.alternative {
+        return alternative.on_eval(env);
     }
     Ok(NULL)
 }
 
+fn eval_try_expression(
+    try_expression: &TryExpression,
+    env: &mut Environment,
+) -> Result<Object, EvaluationError> {
+    let mut result = try_expression.block.on_eval(env);
+    if let (Err(e), Some(catch)) = (&result, &try_expression.catch) {
+        if e.is_catchable() {
+            let mut error = match e {
+                EvaluationError::Thrown(thrown) => thrown.clone(),
+                e => ErrorObj::new(&e.kind(), &e.to_string()),
+            };
+            error.stack = env.runtime().take_stack_trace();
+            env.runtime().clear_traced_error();
+            let mut catch_env =
+                Environment::with_layout(Some(env.clone()), Rc::clone(&catch.layout));
+            bind(&catch.parameter, Object::Error(error), &mut catch_env);
+            result = catch.body.on_eval(&mut catch_env);
+        }
+    }
+    if let Some(finally) = &try_expression.finally {
+        if result.as_ref().is_err_and(|e| !e.is_catchable()) {
+            return result;
+        }
+        let unwound = env.runtime().take_unwound();
+        let traced = env.runtime().take_traced_error();
+        if let Object::ReturnValue(rv) = finally.on_eval(env)? {
+            return Ok(Object::ReturnValue(rv));
+        }
+        env.runtime().restore_unwound(unwound);
+        env.runtime().restore_traced_error(traced);
+    }
+    result
+}
+
+fn is_call_to(call: &CallExpression, name: &str) -> bool {
+    matches!(call.function.as_ref(), Expression::Identifier(i) if i.value == name)
+}
+
+fn quote(call: &CallExpression, env: &mut Environment) -> Result<Object, EvaluationError> {
+    if call.arguments.len() != 1 {
+        return Err(EvaluationError::ArgumentError(format!(
+            "Wrong number of arguments.\nExpected: 1\nGot: {}",
+            call.arguments.len()
+        )));
+    }
+    let node = ast::modify_expression(call.arguments[0].clone(), &mut |node| match node {
+        Expression::CallExpression(ce) if is_call_to(&ce, "unquote") => {
+            if ce.arguments.len() != 1 {
+                return Err(EvaluationError::ArgumentError(format!(
+                    "Wrong number of arguments.\nExpected: 1\nGot: {}",
+                    ce.arguments.len()
+                )));
+            }
+            let unquoted = ce.arguments[0].on_eval(env)?;
+            object_to_expression(unquoted)
+        }
+        node => Ok(node),
+    })?;
+    Ok(Object::Quote(Quote {
+        node: Box::new(node),
+    }))
+}
+
+fn object_to_expression(object: Object) -> Result<Expression, EvaluationError> {
+    match object {
+        Object::Integer(i) => Ok(Expression::IntegerLiteral(IntegerLiteral {
+            token: Token::build(TokenType::Int, &i.value.to_string()),
+            value: i.value,
+        })),
+        Object::Boolean(b) => {
+            let token = match b.value {
+                true => Token::build(TokenType::True, "true"),
+                false => Token::build(TokenType::False, "false"),
+            };
+            Ok(Expression::Boolean(ast::Boolean {
+                token,
+                value: b.value,
+            }))
+        }
+        Object::String(s) => Ok(Expression::StringLiteral(StringLiteral {
+            token: Token::build(TokenType::String, &s.value),
+            value: s.value,
+        })),
+        Object::Array(a) => Ok(Expression::ArrayLiteral(ArrayLiteral {
+            token: Token::build(TokenType::LBracket, "["),
+            elements: a
+                .elements
+                .into_iter()
+                .map(object_to_expression)
+                .collect::<Result<Vec<Expression>, EvaluationError>>()?,
+        })),
+        Object::Quote(q) => Ok(*q.node),
+        o => Err(EvaluationError::MacroError(format!(
+            "Cannot unquote a value of type {}",
+            o.r#type()
+        ))),
+    }
+}
+
 fn is_truthy(object: Object) -> bool {
     match object {
         Object::Null => false,
@@ -272,7 +492,8 @@ fn eval_block_statement(
 ) -> Result<Object, EvaluationError> {
     let mut result = Object::Null;
     for statement in &block.statements {
-        result = eval(statement.clone(), env)?;
+        env.runtime().check_cancelled()?;
+        result = statement.on_eval(env)?;
         if let Object::ReturnValue(_) = result {
             return Ok(result);
         }
@@ -281,16 +502,17 @@ fn eval_block_statement(
 }
 
 fn eval_identifier(node: &Identifier, env: &mut Environment) -> Result<Object, EvaluationError> {
-    if let Ok(val) = env.get(&node.value) {
+    let val = match node.resolution.get() {
+        Some(resolution) => env.get_resolved(&node.value, resolution),
+        None => env.get(&node.value),
+    };
+    if let Ok(val) = val {
         return Ok(val);
     }
-    if let Ok(builtin) = is_builtin(node.clone()) {
-        return Ok(builtin);
+    if let Some(builtin) = env.builtins().get(&node.value) {
+        return Ok(Object::BuiltIn(builtin));
     }
-    Err(EvaluationError::IdentError(format!(
-        "Identifier not found: {}",
-        &node.value
-    )))
+    Err(EvaluationError::IdentError(node.value.clone()))
 }
 
 fn eval_expressions(
@@ -299,37 +521,74 @@ fn eval_expressions(
 ) -> Result<Vec<Object>, EvaluationError> {
     let mut result: Vec<Object> = Vec::new();
     for expression in expressions {
-        let evaluated = eval(expression.clone(), env)?;
+        let evaluated = expression.on_eval(env)?;
         result.push(evaluated);
     }
     Ok(result)
 }
 
-fn apply_function(func: Object, args: &[Object]) -> Result<Object, EvaluationError> {
+pub(crate) fn apply_function(
+    func: &Object,
+    args: &[Object],
+    runtime: &Rc<Runtime>,
+) -> Result<Object, EvaluationError> {
     match func {
         Object::Function(func) => {
-            let mut extended_env = extend_function_env(func.clone(), args);
-            let evaluated = eval(func.body, &mut extended_env)?;
-            Ok(unwrap_return_value(evaluated))
+            if func.parameters.len() != args.len() {
+                return Err(EvaluationError::ArgumentError(format!(
+                    "Wrong number of arguments.\nExpected: {}\nGot: {}",
+                    func.parameters.len(),
+                    args.len()
+                )));
+            }
+            let _call = runtime.enter_call()?;
+            let mut extended_env = extend_function_env(func, args);
+            let _frame = runtime.enter_debug_frame(func, &extended_env);
+            let Some(tracer) = runtime.tracer() else {
+                return func
+                    .body
+                    .on_eval(&mut extended_env)
+                    .map(unwrap_return_value);
+            };
+            tracer.enter_function(func, args);
+            let result = func
+                .body
+                .on_eval(&mut extended_env)
+                .map(unwrap_return_value);
+            tracer.leave_function(func, result.as_ref().ok());
+            result
         }
-        Object::BuiltIn(bi) => bi.run_builtin(args),
-        e => {
-            Err(EvaluationError::Function(format!(
-                "Expected an Object::Function or Object::BuiltIn\nGot: {}",
-                e
-            )))
+        Object::BuiltIn(bi) => {
+            if let Some(tracer) = runtime.tracer() {
+                tracer.call_builtin(bi, args);
+            }
+            bi.run_builtin(args)
         }
+        e => Err(EvaluationError::Function(format!(
+            "Expected an Object::Function or Object::BuiltIn\nGot: {}",
+            e
+        ))),
     }
 }
 
-fn extend_function_env(func: Function, args: &[Object]) -> Environment {
-    let mut env = Environment::new(Some(Box::new(func.env)));
+fn extend_function_env(func: &Function, args: &[Object]) -> Environment {
+    let mut env = Environment::with_layout(Some(func.env.clone()), Rc::clone(&func.layout));
     for (i, param) in func.parameters.iter().enumerate() {
-        env.set(&param.value, args[i].clone());
+        bind(param, args[i].clone(), &mut env);
     }
     env
 }
 
+/// Binds `name` in the current scope, in its slot if it has been resolved.
+/// Everything the interpreter runs is resolved first, so only nodes a host
+/// evaluates on their own are bound by name.
+fn bind(name: &Identifier, val: Object, env: &mut Environment) -> Object {
+    match name.resolution.get() {
+        Some(resolution) => env.set_slot(resolution.slot, val),
+        None => env.set(&name.value, val),
+    }
+}
+
 fn unwrap_return_value(object: Object) -> Object {
     if let Object::ReturnValue(rv) = object {
         return *rv.value;
@@ -337,6 +596,24 @@ fn unwrap_return_value(object: Object) -> Object {
     object
 }
 
+fn eval_index_expression(left: Object, index: Object) -> Result<Object, EvaluationError> {
+    match (&left, &index) {
+        (Object::Array(a), Object::Integer(i)) => {
+            if i.value < 0 || i.value as usize >= a.elements.len() {
+                return Ok(NULL);
+            }
+            Ok(a.elements[i.value as usize].clone())
+        }
+        (Object::Hash(h), Object::String(s)) => Ok(h.pairs.get(&s.value).cloned().unwrap_or(NULL)),
+        (Object::Error(e), Object::String(s)) => Ok(e.field(&s.value).unwrap_or(NULL)),
+        _ => Err(EvaluationError::OperatorError(format!(
+            "{}[{}]",
+            left.r#type(),
+            index.r#type()
+        ))),
+    }
+}
+
 fn eval_string_infix_expression(
     operator: &str,
     left: StringObj,
@@ -355,6 +632,110 @@ fn eval_string_infix_expression(
     }))
 }
 
+/// Watches evaluation one statement at a time, e.g. to pause at breakpoints.
+/// Attach one with [`Interpreter::set_debugger`](crate::interpreter::Interpreter::set_debugger).
+pub trait Debugger {
+    /// Called before each statement runs. `frames` are the calls in progress,
+    /// outermost first, with the statement in the last one. Returning an
+    /// error ends the evaluation with it.
+    fn before_statement(&self, frames: &[DebugFrame]) -> Result<(), EvaluationError>;
+}
+
+/// A call in progress, as seen by a [`Debugger`]. The top level is a frame
+/// of its own, named `<program>`.
+#[derive(Debug, Clone)]
+pub struct DebugFrame {
+    /// Tells calls apart, including calls of the same function at the same depth.
+    pub call: usize,
+    pub function: String,
+    /// The statement the frame is running, or the body of a call that hasn't
+    /// reached one yet.
+    pub position: Position,
+    pub env: Environment,
+}
+
+/// Longest rendering of a single argument in a stack frame.
+const ARGUMENT_SUMMARY_WIDTH: usize = 24;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackFrame {
+    pub function: String,
+    pub position: Position,
+    pub arguments: String,
+}
+
+impl StackFrame {
+    fn new(function: &Object, args: &[Object], position: Position) -> StackFrame {
+        let function = match function {
+            Object::Function(f) => f.display_name().to_string(),
+            Object::BuiltIn(bi) => bi.name.clone(),
+            _ => String::from("<anonymous>"),
+        };
+        StackFrame {
+            function,
+            position,
+            arguments: summarize_arguments(args),
+        }
+    }
+}
+
+/// Shows a value the way it would be written in a script, so strings are quoted.
+pub(crate) fn inspect(value: &Object) -> String {
+    match value {
+        Object::String(s) => format!("{:?}", s.value),
+        value => value.to_string(),
+    }
+}
+
+/// The arguments of a call, each shortened to fit on one line.
+pub(crate) fn summarize_arguments(args: &[Object]) -> String {
+    args.iter()
+        .map(|a| summarize(&inspect(a)))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+fn summarize(value: &str) -> String {
+    let value = value.replace('\n', " ");
+    if value.chars().count() <= ARGUMENT_SUMMARY_WIDTH {
+        return value;
+    }
+    let truncated: String = value.chars().take(ARGUMENT_SUMMARY_WIDTH - 3).collect();
+    truncated + "..."
+}
+
+impl Display for StackFrame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}, in {}({})",
+            self.position, self.function, self.arguments
+        )
+    }
+}
+
+/// Calls that were active when an error was raised, outermost first.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StackTrace {
+    pub frames: Vec<StackFrame>,
+}
+
+impl StackTrace {
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+}
+
+impl Display for StackTrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Traceback (most recent call last):")?;
+        for frame in &self.frames {
+            writeln!(f, "  {}", frame)?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum EvaluationError {
     OperatorError(String),
@@ -363,6 +744,51 @@ pub enum EvaluationError {
     Function(String),
     MatchError(String),
     BuiltInError(String),
+    ArgumentError(String),
+    StepLimitExceeded(u64),
+    RecursionLimitExceeded(usize),
+    Timeout(Duration),
+    Cancelled,
+    OutOfMemory(usize),
+    MacroError(String),
+    ArithmeticError(String),
+    Thrown(ErrorObj),
+}
+
+impl EvaluationError {
+    /// The name scripts see in a caught error's `kind` field.
+    pub fn kind(&self) -> String {
+        let kind = match self {
+            EvaluationError::OperatorError(_) => "OperatorError",
+            EvaluationError::TypeError(_) => "TypeError",
+            EvaluationError::IdentError(_) => "IdentError",
+            EvaluationError::Function(_) => "FunctionError",
+            EvaluationError::MatchError(_) => "MatchError",
+            EvaluationError::BuiltInError(_) => "BuiltInError",
+            EvaluationError::ArgumentError(_) => "ArgumentError",
+            EvaluationError::StepLimitExceeded(_) => "StepLimitExceeded",
+            EvaluationError::RecursionLimitExceeded(_) => "RecursionLimitExceeded",
+            EvaluationError::Timeout(_) => "Timeout",
+            EvaluationError::Cancelled => "Cancelled",
+            EvaluationError::OutOfMemory(_) => "OutOfMemory",
+            EvaluationError::MacroError(_) => "MacroError",
+            EvaluationError::ArithmeticError(_) => "ArithmeticError",
+            EvaluationError::Thrown(e) => return e.kind.clone(),
+        };
+        String::from(kind)
+    }
+
+    /// Resource limits and cancellation stop the whole run and cannot be caught.
+    pub fn is_catchable(&self) -> bool {
+        !matches!(
+            self,
+            EvaluationError::StepLimitExceeded(_)
+                | EvaluationError::RecursionLimitExceeded(_)
+                | EvaluationError::Timeout(_)
+                | EvaluationError::Cancelled
+                | EvaluationError::OutOfMemory(_)
+        )
+    }
 }
 
 impl Display for EvaluationError {
@@ -374,6 +800,31 @@ impl Display for EvaluationError {
             EvaluationError::Function(func) => write!(f, "Not a function: {}", func),
             EvaluationError::MatchError(m) => write!(f, "MatchError: {}", m),
             EvaluationError::BuiltInError(bi) => write!(f, "BuiltInError: {}", bi),
+            EvaluationError::ArgumentError(a) => write!(f, "ArgumentError: {}", a),
+            EvaluationError::StepLimitExceeded(s) => {
+                write!(
+                    f,
+                    "StepLimitExceeded: evaluation took more than {} steps",
+                    s
+                )
+            }
+            EvaluationError::RecursionLimitExceeded(d) => {
+                write!(f, "RecursionLimitExceeded: call depth exceeded {}", d)
+            }
+            EvaluationError::Timeout(t) => {
+                write!(f, "Timeout: evaluation took longer than {:?}", t)
+            }
+            EvaluationError::Cancelled => write!(f, "Cancelled: evaluation was cancelled"),
+            EvaluationError::OutOfMemory(m) => {
+                write!(
+                    f,
+                    "OutOfMemory: values in use would take more than {} bytes",
+                    m
+                )
+            }
+            EvaluationError::MacroError(m) => write!(f, "MacroError: {}", m),
+            EvaluationError::ArithmeticError(a) => write!(f, "ArithmeticError: {}", a),
+            EvaluationError::Thrown(e) => write!(f, "{}", e),
         }
     }
 }
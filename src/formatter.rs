@@ -0,0 +1,489 @@
+use crate::{
+    ast::{
+        self, BlockStatement, Expression, ExpressionStatement, Identifier, Program, Statement,
+        Visitor,
+    },
+    lexer::Lexer,
+    parser::{Parser, ParserError, CALL, INDEX, PREFIX},
+    token::{Comment, Position, Token, TokenType},
+};
+use std::collections::{HashMap, HashSet};
+
+pub const DEFAULT_MAX_WIDTH: usize = 80;
+pub const DEFAULT_INDENT_WIDTH: usize = 4;
+
+/// Binds tighter than any operator, so atoms never need parentheses.
+const ATOM: u8 = INDEX + 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatOptions {
+    pub max_width: usize,
+    pub indent_width: usize,
+}
+
+impl Default for FormatOptions {
+    fn default() -> FormatOptions {
+        FormatOptions {
+            max_width: DEFAULT_MAX_WIDTH,
+            indent_width: DEFAULT_INDENT_WIDTH,
+        }
+    }
+}
+
+pub fn format_source(input: &str) -> Result<String, ParserError> {
+    format_source_with(input, FormatOptions::default())
+}
+
+/// Pretty-prints Monkey source. Formatting the output again returns it unchanged.
+pub fn format_source_with(input: &str, options: FormatOptions) -> Result<String, ParserError> {
+    let program = Parser::new(Lexer::new(input)).parse_program()?;
+    let mut lexer = Lexer::new(input);
+    let mut tokens = Vec::new();
+    loop {
+        let token = lexer.next_token();
+        let eof = token.r#type == TokenType::EOF;
+        tokens.push(token);
+        if eof {
+            break;
+        }
+    }
+    let index = tokens
+        .iter()
+        .enumerate()
+        .map(|(i, t)| (t.position, i))
+        .collect();
+    let layout = Layout::new(&program, &tokens, &index);
+    let comments = layout.attach(&tokens, lexer.comments());
+    let eof = tokens.len() - 1;
+    let mut printer = Printer {
+        options,
+        layout,
+        comments,
+        index,
+        tokens,
+        last_line: None,
+        items: HashMap::new(),
+    };
+    Ok(printer.statements(&program.statements, eof, 0))
+}
+
+fn statement_token(statement: &Statement) -> &Token {
+    match statement {
+        Statement::Let(ls) => &ls.token,
+        Statement::Return(rs) => &rs.token,
+        Statement::Expression(es) => &es.token,
+        Statement::Throw(ts) => &ts.token,
+    }
+}
+
+/// `if` and `try` statements need no `;` after their closing `}`.
+fn ends_with_block(statement: &Statement) -> bool {
+    matches!(
+        statement,
+        Statement::Expression(ExpressionStatement {
+            expression: Expression::IfExpression(_) | Expression::TryExpression(_),
+            ..
+        })
+    )
+}
+
+/// Which tokens each statement spans, found by walking every statement list.
+#[derive(Debug, Default)]
+struct Layout {
+    ends: HashMap<usize, usize>,
+    block_closes: HashSet<usize>,
+    closing: HashMap<usize, usize>,
+}
+
+struct LayoutBuilder<'a> {
+    index: &'a HashMap<Position, usize>,
+    layout: Layout,
+}
+
+impl LayoutBuilder<'_> {
+    fn add(&mut self, statements: &[Statement], close: usize) {
+        let starts: Vec<usize> = statements
+            .iter()
+            .map(|s| self.index[&statement_token(s).position])
+            .collect();
+        for (i, start) in starts.iter().enumerate() {
+            let next = starts.get(i + 1).copied().unwrap_or(close);
+            self.layout.ends.insert(*start, next - 1);
+        }
+        self.layout.block_closes.insert(close);
+    }
+}
+
+impl Visitor for LayoutBuilder<'_> {
+    fn visit_block_statement(&mut self, block: &BlockStatement) {
+        let open = self.index[&block.token.position];
+        let close = self.layout.closing[&open];
+        self.add(&block.statements, close);
+        ast::visit_block_statement(self, block);
+    }
+}
+
+impl Layout {
+    fn new(program: &Program, tokens: &[Token], index: &HashMap<Position, usize>) -> Layout {
+        let mut layout = Layout::default();
+        let mut open = Vec::new();
+        for (i, token) in tokens.iter().enumerate() {
+            match token.r#type {
+                TokenType::LBrace => open.push(i),
+                TokenType::RBrace => {
+                    if let Some(o) = open.pop() {
+                        layout.closing.insert(o, i);
+                    }
+                }
+                _ => {}
+            }
+        }
+        let mut builder = LayoutBuilder { index, layout };
+        builder.add(&program.statements, tokens.len() - 1);
+        builder.visit_program(program);
+        builder.layout
+    }
+
+    /// The innermost statement containing the token at `index`.
+    fn enclosing_statement(&self, index: usize) -> Option<usize> {
+        self.ends
+            .iter()
+            .filter(|(start, end)| **start <= index && index <= **end)
+            .map(|(start, _)| *start)
+            .max()
+    }
+
+    /// A comment on the same line as the end of a statement trails it. Any other
+    /// comment leads the statement after it, or is left dangling before the `}`
+    /// that closes its block. Comments inside an expression move above the
+    /// statement containing them.
+    fn attach(&self, tokens: &[Token], comments: &[Comment]) -> Comments {
+        let starts_by_end: HashMap<usize, usize> =
+            self.ends.iter().map(|(s, e)| (*e, *s)).collect();
+        let mut attached = Comments::default();
+        for comment in comments {
+            let next = tokens.partition_point(|t| t.position < comment.position);
+            if let Some(previous) = next.checked_sub(1) {
+                if tokens[previous].position.line == comment.position.line {
+                    if let Some(start) = starts_by_end.get(&previous) {
+                        attached
+                            .trailing
+                            .entry(*start)
+                            .or_default()
+                            .push(comment.clone());
+                        continue;
+                    }
+                }
+            }
+            let next = next.min(tokens.len() - 1);
+            let (comments, key) = if self.ends.contains_key(&next) {
+                (&mut attached.leading, next)
+            } else if self.block_closes.contains(&next) {
+                (&mut attached.dangling, next)
+            } else if let Some(start) = self.enclosing_statement(next) {
+                (&mut attached.leading, start)
+            } else {
+                (&mut attached.dangling, tokens.len() - 1)
+            };
+            comments.entry(key).or_default().push(comment.clone());
+        }
+        attached
+    }
+}
+
+/// Comments keyed by the index of the first token of their statement, or of
+/// the `}` or end of file they dangle before.
+#[derive(Debug, Default)]
+struct Comments {
+    leading: HashMap<usize, Vec<Comment>>,
+    trailing: HashMap<usize, Vec<Comment>>,
+    dangling: HashMap<usize, Vec<Comment>>,
+}
+
+struct Printer {
+    options: FormatOptions,
+    layout: Layout,
+    comments: Comments,
+    index: HashMap<Position, usize>,
+    tokens: Vec<Token>,
+    last_line: Option<usize>,
+    /// List items already formatted, by node, indent and column. A list that
+    /// doesn't fit formats its items again, so without this the work doubles
+    /// with each level of nesting.
+    items: HashMap<(*const Expression, usize, usize), String>,
+}
+
+impl Printer {
+    fn indent(&self, indent: usize) -> String {
+        " ".repeat(indent * self.options.indent_width)
+    }
+
+    /// Keeps at most one blank line where the source had any.
+    fn separate(&mut self, out: &mut String, line: usize) {
+        if let Some(last) = self.last_line {
+            if line > last + 1 {
+                out.push('\n');
+            }
+        }
+        self.last_line = Some(line);
+    }
+
+    fn comment_lines(&mut self, out: &mut String, comments: &[Comment], indent: usize) {
+        for comment in comments {
+            self.separate(out, comment.position.line);
+            out.push_str(&self.indent(indent));
+            out.push_str(&comment.text);
+            out.push('\n');
+        }
+    }
+
+    fn statements(&mut self, statements: &[Statement], close: usize, indent: usize) -> String {
+        let mut out = String::new();
+        let texts: Vec<String> = statements
+            .iter()
+            .map(|s| self.statement(s, indent))
+            .collect();
+        self.last_line = None;
+        for (i, statement) in statements.iter().enumerate() {
+            let start = self.index[&statement_token(statement).position];
+            let leading = self
+                .comments
+                .leading
+                .get(&start)
+                .cloned()
+                .unwrap_or_default();
+            self.comment_lines(&mut out, &leading, indent);
+            self.separate(&mut out, self.tokens[start].position.line);
+            out.push_str(&self.indent(indent));
+            out.push_str(&texts[i]);
+            // Without a `;`, a following `(`, `[` or `-` would continue the expression.
+            if ends_with_block(statement)
+                && texts
+                    .get(i + 1)
+                    .is_some_and(|next| next.starts_with(['(', '[', '-']))
+            {
+                out.push(';');
+            }
+            self.last_line = Some(self.tokens[self.layout.ends[&start]].position.line);
+            if let Some(trailing) = self.comments.trailing.get(&start) {
+                for comment in trailing {
+                    out.push(' ');
+                    out.push_str(&comment.text);
+                }
+            }
+            out.push('\n');
+        }
+        let dangling = self
+            .comments
+            .dangling
+            .get(&close)
+            .cloned()
+            .unwrap_or_default();
+        self.comment_lines(&mut out, &dangling, indent);
+        out
+    }
+
+    fn statement(&mut self, statement: &Statement, indent: usize) -> String {
+        let column = indent * self.options.indent_width;
+        match statement {
+            Statement::Let(ls) => {
+                let prefix = format!("let {} = ", ls.name.value);
+                let value = self.expression(&ls.value, indent, column + prefix.len());
+                format!("{}{};", prefix, value)
+            }
+            Statement::Return(rs) => {
+                let value = self.expression(&rs.return_value, indent, column + 7);
+                format!("return {};", value)
+            }
+            Statement::Throw(ts) => {
+                let value = self.expression(&ts.value, indent, column + 6);
+                format!("throw {};", value)
+            }
+            Statement::Expression(es) => {
+                let value = self.expression(&es.expression, indent, column);
+                match ends_with_block(statement) {
+                    true => value,
+                    false => value + ";",
+                }
+            }
+        }
+    }
+
+    fn block(&mut self, block: &BlockStatement, indent: usize) -> String {
+        let open = self.index[&block.token.position];
+        let close = self.layout.closing[&open];
+        let body = self.statements(&block.statements, close, indent + 1);
+        if body.is_empty() {
+            return String::from("{}");
+        }
+        format!("{{\n{}{}}}", body, self.indent(indent))
+    }
+
+    fn precedence(expression: &Expression) -> u8 {
+        match expression {
+            Expression::InfixExpression(i) => Parser::precedences(&i.token.r#type),
+            Expression::PrefixExpression(_) => PREFIX,
+            Expression::CallExpression(_) => CALL,
+            Expression::IndexExpression(_) => INDEX,
+            _ => ATOM,
+        }
+    }
+
+    /// Formats `expression` as an operand that must bind at least as tightly as `precedence`.
+    fn operand(
+        &mut self,
+        expression: &Expression,
+        precedence: u8,
+        indent: usize,
+        column: usize,
+    ) -> String {
+        if Printer::precedence(expression) < precedence {
+            return format!("({})", self.expression(expression, indent, column + 1));
+        }
+        self.expression(expression, indent, column)
+    }
+
+    fn expression(&mut self, expression: &Expression, indent: usize, column: usize) -> String {
+        match expression {
+            Expression::Identifier(i) => i.value.clone(),
+            Expression::IntegerLiteral(i) => i.token.literal.clone(),
+            Expression::Boolean(b) => b.value.to_string(),
+            Expression::StringLiteral(s) => format!("\"{}\"", s.value),
+            Expression::PrefixExpression(p) => {
+                let right = self.operand(&p.right, PREFIX, indent, column + p.operator.len());
+                format!("{}{}", p.operator, right)
+            }
+            Expression::InfixExpression(i) => {
+                let precedence = Parser::precedences(&i.token.r#type);
+                let left = self.operand(&i.left, precedence, indent, column);
+                let column = advance(column, &left) + i.operator.len() + 2;
+                let right = self.operand(&i.right, precedence + 1, indent, column);
+                format!("{} {} {}", left, i.operator, right)
+            }
+            Expression::IfExpression(i) => {
+                let condition = self.expression(&i.condition, indent, column + 4);
+                let mut out = format!("if ({}) {}", condition, self.block(&i.consequence, indent));
+                if let Some(alternative) = &i.alternative {
+                    out.push_str(" else ");
+                    out.push_str(&self.block(alternative, indent));
+                }
+                out
+            }
+            Expression::BlockStatement(b) => self.block(b, indent),
+            Expression::FunctionLiteral(fl) => {
+                format!(
+                    "fn({}) {}",
+                    parameters(&fl.parameters),
+                    self.block(&fl.body, indent)
+                )
+            }
+            Expression::MacroLiteral(ml) => {
+                format!(
+                    "macro({}) {}",
+                    parameters(&ml.parameters),
+                    self.block(&ml.body, indent)
+                )
+            }
+            Expression::CallExpression(c) => {
+                let function = self.operand(&c.function, CALL, indent, column);
+                let column = advance(column, &function);
+                let arguments = self.list("(", &c.arguments, ")", indent, column);
+                function + &arguments
+            }
+            Expression::ArrayLiteral(al) => self.list("[", &al.elements, "]", indent, column),
+            Expression::IndexExpression(ie) => {
+                let left = self.operand(&ie.left, CALL, indent, column);
+                let column = advance(column, &left) + 1;
+                let index = self.expression(&ie.index, indent, column);
+                format!("{}[{}]", left, index)
+            }
+            Expression::TryExpression(te) => {
+                let mut out = format!("try {}", self.block(&te.block, indent));
+                if let Some(catch) = &te.catch {
+                    out.push_str(&format!(
+                        " catch ({}) {}",
+                        catch.parameter.value,
+                        self.block(&catch.body, indent)
+                    ));
+                }
+                if let Some(finally) = &te.finally {
+                    out.push_str(" finally ");
+                    out.push_str(&self.block(finally, indent));
+                }
+                out
+            }
+            Expression::Default => String::new(),
+        }
+    }
+
+    /// Keeps a list on one line when it fits, letting only its last element
+    /// span several lines; otherwise puts each element on its own line.
+    fn list(
+        &mut self,
+        open: &str,
+        items: &[Expression],
+        close: &str,
+        indent: usize,
+        column: usize,
+    ) -> String {
+        let mut flat = String::from(open);
+        let mut item_column = column + open.len();
+        let mut fits = true;
+        for (i, item) in items.iter().enumerate() {
+            let text = self.item(item, indent, item_column);
+            if i + 1 < items.len() && text.contains('\n') {
+                fits = false;
+            }
+            if i > 0 {
+                flat.push_str(", ");
+            }
+            flat.push_str(&text);
+            item_column = advance(column, &flat);
+        }
+        flat.push_str(close);
+        let first_line = flat.lines().next().unwrap_or_default();
+        if fits && column + first_line.chars().count() <= self.options.max_width {
+            return flat;
+        }
+        let inner = indent + 1;
+        let mut out = format!("{}\n", open);
+        for (i, item) in items.iter().enumerate() {
+            let column = inner * self.options.indent_width;
+            out.push_str(&self.indent(inner));
+            out.push_str(&self.item(item, inner, column));
+            if i + 1 < items.len() {
+                out.push(',');
+            }
+            out.push('\n');
+        }
+        out.push_str(&self.indent(indent));
+        out.push_str(close);
+        out
+    }
+
+    fn item(&mut self, item: &Expression, indent: usize, column: usize) -> String {
+        let key = (item as *const Expression, indent, column);
+        if let Some(text) = self.items.get(&key) {
+            return text.clone();
+        }
+        let text = self.expression(item, indent, column);
+        self.items.insert(key, text.clone());
+        text
+    }
+}
+
+fn parameters(parameters: &[Identifier]) -> String {
+    parameters
+        .iter()
+        .map(|p| p.value.clone())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// The column right after `text` when it is written starting at `column`.
+fn advance(column: usize, text: &str) -> usize {
+    match text.rsplit_once('\n') {
+        Some((_, last)) => last.chars().count(),
+        None => column + text.chars().count(),
+    }
+}
@@ -0,0 +1,240 @@
+use crate::{
+    builtins::{Arity, NativeFunction},
+    convert::IntoObject,
+    evaluator::{self, apply_function, Debugger, EvaluationError, StackTrace},
+    lexer::Lexer,
+    macro_expansion::{define_macros, expand_macros},
+    object::{BuiltIn, Environment, Object},
+    parser::{Parser, ParserError},
+    runtime::{CancellationToken, Limits},
+    tracer::Tracer,
+};
+use std::{
+    fmt::{Debug, Display},
+    fs,
+    io::{self, Write},
+    path::Path,
+    rc::Rc,
+};
+
+pub trait IntoArgs {
+    fn into_args(self) -> Vec<Object>;
+}
+
+impl IntoArgs for Vec<Object> {
+    fn into_args(self) -> Vec<Object> {
+        self
+    }
+}
+
+impl IntoArgs for &[Object] {
+    fn into_args(self) -> Vec<Object> {
+        self.to_vec()
+    }
+}
+
+macro_rules! impl_into_args {
+    ($($arg:ident),*) => {
+        impl<$($arg: IntoObject),*> IntoArgs for ($($arg,)*) {
+            #[allow(non_snake_case)]
+            fn into_args(self) -> Vec<Object> {
+                let ($($arg,)*) = self;
+                vec![$($arg.into_object()),*]
+            }
+        }
+    };
+}
+
+impl_into_args!();
+impl_into_args!(A);
+impl_into_args!(A, B);
+impl_into_args!(A, B, C);
+impl_into_args!(A, B, C, D);
+impl_into_args!(A, B, C, D, E);
+impl_into_args!(A, B, C, D, E, G);
+
+pub struct Interpreter {
+    env: Environment,
+    macro_env: Environment,
+}
+
+impl Debug for Interpreter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Interpreter")
+            .field("env", &self.env)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for Interpreter {
+    /// Functions defined at the top level hold on to the global scope they
+    /// are bound in, so the scopes are emptied to break those cycles, and
+    /// the call scopes that only those functions kept alive are collected.
+    fn drop(&mut self) {
+        self.macro_env.clear();
+        self.env.clear();
+        self.env.runtime().scopes().collect();
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Interpreter {
+        Interpreter::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Interpreter {
+        let env = Environment::new(None);
+        let macro_env = Environment::new_enclosed_environment(&mut env.clone());
+        Interpreter { env, macro_env }
+    }
+
+    pub fn eval_str(&mut self, input: &str) -> Result<Object, InterpreterError> {
+        let lexer = Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        let mut program = parser.parse_program()?;
+        define_macros(&mut program, &mut self.macro_env);
+        let program = expand_macros(program, &mut self.macro_env)
+            .map_err(|e| InterpreterError::Evaluation(e, StackTrace::default()))?;
+        let result = evaluator::eval(&program, &mut self.env);
+        self.env.builtins().flush_output()?;
+        result.map_err(|e| InterpreterError::Evaluation(e, self.env.runtime().take_stack_trace()))
+    }
+
+    pub fn eval_file<P: AsRef<Path>>(&mut self, path: P) -> Result<Object, InterpreterError> {
+        let input = fs::read_to_string(path)?;
+        self.eval_str(&input)
+    }
+
+    pub fn set_global<T: IntoObject>(&mut self, name: &str, value: T) {
+        self.env.set(name, value.into_object());
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Object> {
+        self.env.get(name).ok()
+    }
+
+    pub fn register_builtin<F>(&mut self, name: &str, arity: Arity, func: F) -> Option<BuiltIn>
+    where
+        F: Fn(&[Object]) -> Result<Object, EvaluationError> + 'static,
+    {
+        self.env.builtins_mut().register(name, arity, func)
+    }
+
+    pub fn register_fn<F, Args>(&mut self, name: &str, func: F) -> Option<BuiltIn>
+    where
+        F: NativeFunction<Args> + 'static,
+    {
+        self.env.builtins_mut().register_fn(name, func)
+    }
+
+    pub fn remove_builtin(&mut self, name: &str) -> Option<BuiltIn> {
+        self.env.builtins_mut().remove(name)
+    }
+
+    pub fn limits(&self) -> Limits {
+        self.env.runtime().limits()
+    }
+
+    /// Limits apply to each `eval_str`, `eval_file` or `call` separately,
+    /// except `max_allocated`, which counts the values left by earlier runs.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.env.runtime().set_limits(limits);
+    }
+
+    /// Approximate bytes taken by the values bound in the global scope and
+    /// in the calls in progress.
+    pub fn allocated_bytes(&self) -> usize {
+        self.env.runtime().allocated_bytes()
+    }
+
+    /// A token that cancels this interpreter's current and future runs until
+    /// it is reset. It can be sent to another thread.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.env.runtime().cancellation_token()
+    }
+
+    /// Redirects everything scripts print with `puts`.
+    pub fn set_output<W: Write + 'static>(&mut self, output: W) {
+        self.env.builtins_mut().set_output(output);
+    }
+
+    /// Attaches a debugger that is consulted before every statement, or
+    /// detaches it with `None`.
+    pub fn set_debugger(&mut self, debugger: Option<Rc<dyn Debugger>>) {
+        self.env.runtime().set_debugger(debugger);
+    }
+
+    /// Attaches a tracer that is told about every statement, call and error,
+    /// or detaches it with `None`.
+    pub fn set_tracer(&mut self, tracer: Option<Rc<dyn Tracer>>) {
+        self.env.runtime().set_tracer(tracer);
+    }
+
+    pub fn environment(&self) -> &Environment {
+        &self.env
+    }
+
+    pub fn environment_mut(&mut self) -> &mut Environment {
+        &mut self.env
+    }
+
+    /// Calls a Monkey function or builtin, e.g. a callback handed to the host by a script.
+    pub fn call<A: IntoArgs>(
+        &mut self,
+        function: &Object,
+        args: A,
+    ) -> Result<Object, EvaluationError> {
+        let _run = self.env.runtime().begin();
+        apply_function(function, &args.into_args(), self.env.runtime())
+    }
+
+    /// Looks up `name` in the global environment and calls it.
+    pub fn call_global<A: IntoArgs>(
+        &mut self,
+        name: &str,
+        args: A,
+    ) -> Result<Object, EvaluationError> {
+        let function = match self.env.get(name) {
+            Ok(function) => function,
+            Err(_) => match self.env.builtins().get(name) {
+                Some(builtin) => Object::BuiltIn(builtin),
+                None => return Err(EvaluationError::IdentError(name.to_string())),
+            },
+        };
+        self.call(&function, args)
+    }
+}
+
+#[derive(Debug)]
+pub enum InterpreterError {
+    Parser(ParserError),
+    Evaluation(EvaluationError, StackTrace),
+    Io(io::Error),
+}
+
+impl From<ParserError> for InterpreterError {
+    fn from(error: ParserError) -> InterpreterError {
+        InterpreterError::Parser(error)
+    }
+}
+
+impl From<io::Error> for InterpreterError {
+    fn from(error: io::Error) -> InterpreterError {
+        InterpreterError::Io(error)
+    }
+}
+
+impl Display for InterpreterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InterpreterError::Parser(p) => write!(f, "{}", p),
+            InterpreterError::Evaluation(e, stack) if stack.is_empty() => write!(f, "{}", e),
+            InterpreterError::Evaluation(e, stack) => write!(f, "{}{}", stack, e),
+            InterpreterError::Io(i) => write!(f, "IoError: {}", i),
+        }
+    }
+}
+
+impl std::error::Error for InterpreterError {}
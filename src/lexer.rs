@@ -1,83 +1,204 @@
-use crate::token::{Token, TokenType};
-use std::collections::HashMap;
+use crate::token::{Comment, Position, Token, TokenRef, TokenType};
+use std::{fmt::Display, iter::FusedIterator, ops::Range};
 
+/// A malformed token. The lexer returns it as an `Illegal` token and keeps
+/// the error, so the parser can report what was wrong with it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LexerError {
+    pub message: String,
+    pub position: Position,
+}
+
+impl Display for LexerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.position, self.message)
+    }
+}
+
+/// The value of an integer literal: decimal, or hexadecimal, octal or binary
+/// with a `0x`, `0o` or `0b` prefix. A single `_` may separate digits.
+pub fn parse_integer(literal: &str) -> Result<i64, String> {
+    let (radix, digits, base) = match literal.get(..2) {
+        Some("0x") => (16, &literal[2..], "hexadecimal"),
+        Some("0o") => (8, &literal[2..], "octal"),
+        Some("0b") => (2, &literal[2..], "binary"),
+        _ => (10, literal, "decimal"),
+    };
+    if digits.is_empty() {
+        return Err(format!("missing digits after `{}`", literal));
+    }
+    if digits.starts_with('_') || digits.ends_with('_') || digits.contains("__") {
+        return Err(format!("`_` in `{}` must be between two digits", literal));
+    }
+    if let Some(c) = digits.chars().find(|c| *c != '_' && !c.is_digit(radix)) {
+        return Err(format!(
+            "invalid digit `{}` in {} literal `{}`",
+            c, base, literal
+        ));
+    }
+    let digits = digits.replace('_', "");
+    i64::from_str_radix(&digits, radix)
+        .map_err(|_| format!("integer literal `{}` is too large", literal))
+}
+
+/// Splits the input into tokens without copying it: [`Lexer::next_token_ref`]
+/// returns tokens whose literals are slices of the input.
 #[derive(Debug, PartialEq, Clone)]
-pub struct Lexer {
-    input: Vec<char>,
+pub struct Lexer<'a> {
+    input: &'a str,
+    /// Byte offset of `char`.
     position: usize,
+    /// Byte offset of the character after `char`.
     read_position: usize,
     char: char,
+    line: usize,
+    column: usize,
+    comments: Vec<Comment>,
+    errors: Vec<LexerError>,
 }
 
-impl Lexer {
-    pub fn new(input: &str) -> Lexer {
-        let input: Vec<char> = input.chars().collect();
+impl<'a> Lexer<'a> {
+    pub fn new(input: &'a str) -> Lexer<'a> {
         let mut lexer = Lexer {
-            input: input.clone(),
+            input,
             position: 0,
             read_position: 0,
             char: '\0',
+            line: 1,
+            column: 0,
+            comments: Vec::new(),
+            errors: Vec::new(),
         };
         lexer.read_char();
         lexer
     }
 
     fn read_char(&mut self) {
-        if self.read_position >= self.input.len() {
-            self.char = '\0';
+        if self.char == '\n' {
+            self.line += 1;
+            self.column = 1;
         } else {
-            self.char = self.input[self.read_position];
+            self.column += 1;
         }
         self.position = self.read_position;
-        self.read_position += 1;
+        match self.input[self.read_position..].chars().next() {
+            Some(char) => {
+                self.char = char;
+                self.read_position += char.len_utf8();
+            }
+            None => self.char = '\0',
+        }
+    }
+
+    /// The input from byte offset `start` up to the current character.
+    fn slice_from(&self, start: usize) -> &'a str {
+        &self.input[start..self.position]
     }
 
-    fn read_identifier(&mut self) -> String {
+    fn is_identifier_start(char: char) -> bool {
+        char == '_' || unicode_ident::is_xid_start(char)
+    }
+
+    /// Identifiers follow Unicode's XID rules, with `_` also allowed first.
+    fn read_identifier(&mut self) -> &'a str {
         let position = self.position;
-        while self.char.is_alphabetic() || self.char == '_' {
+        while unicode_ident::is_xid_continue(self.char) {
             self.read_char();
         }
-        self.input.iter().collect::<String>()[position..self.position].to_string()
+        self.slice_from(position)
     }
 
-    fn loop_up_ident(&self, ident: &str) -> TokenType {
-        let mut keywords: HashMap<String, TokenType> = HashMap::new();
-        keywords.insert(String::from("fn"), TokenType::Function);
-        keywords.insert(String::from("let"), TokenType::Let);
-        keywords.insert(String::from("true"), TokenType::True);
-        keywords.insert(String::from("false"), TokenType::False);
-        keywords.insert(String::from("if"), TokenType::If);
-        keywords.insert(String::from("else"), TokenType::Else);
-        keywords.insert(String::from("return"), TokenType::Return);
-
-        if let Some(token_type) = keywords.get(ident) {
-            return token_type.clone();
+    fn look_up_ident(ident: &str) -> TokenType {
+        match ident {
+            "fn" => TokenType::Function,
+            "let" => TokenType::Let,
+            "true" => TokenType::True,
+            "false" => TokenType::False,
+            "if" => TokenType::If,
+            "else" => TokenType::Else,
+            "return" => TokenType::Return,
+            "try" => TokenType::Try,
+            "catch" => TokenType::Catch,
+            "finally" => TokenType::Finally,
+            "throw" => TokenType::Throw,
+            "macro" => TokenType::Macro,
+            _ => TokenType::Ident,
         }
-        TokenType::Ident
     }
 
     fn eat_whitespace(&mut self) {
-        while self.char == ' ' || self.char == '\t' || self.char == '\n' || self.char == '\r' {
+        loop {
+            match self.char {
+                ' ' | '\t' | '\n' | '\r' => self.read_char(),
+                '/' if self.peek_char() == '/' => self.read_comment(),
+                _ => break,
+            }
+        }
+    }
+
+    fn read_comment(&mut self) {
+        let position = self.current_position();
+        let start = self.position;
+        while self.char != '\n' && self.char != '\0' {
             self.read_char();
         }
+        self.comments.push(Comment {
+            text: String::from(self.slice_from(start).trim_end()),
+            position,
+        });
     }
 
-    fn read_number(&mut self) -> String {
-        let position = self.position;
-        while self.char.is_ascii_digit() {
+    /// All of the input, including what has already been read.
+    pub(crate) fn input(&self) -> &'a str {
+        self.input
+    }
+
+    /// Comments skipped so far, in source order.
+    pub fn comments(&self) -> &[Comment] {
+        &self.comments
+    }
+
+    /// Errors for the `Illegal` tokens returned so far, in source order.
+    pub fn errors(&self) -> &[LexerError] {
+        &self.errors
+    }
+
+    fn illegal(&mut self, message: String, position: Position) -> TokenType {
+        self.errors.push(LexerError { message, position });
+        TokenType::Illegal
+    }
+
+    /// Reads an integer literal. After a radix prefix, letters are read too,
+    /// so that `0xFG` is reported as one malformed literal.
+    fn read_number(&mut self) -> (TokenType, &'a str) {
+        let position = self.current_position();
+        let start = self.position;
+        let prefixed = self.char == '0' && matches!(self.peek_char(), 'x' | 'o' | 'b');
+        if prefixed {
+            self.read_char();
+            self.read_char();
+        }
+        while self.char.is_ascii_digit()
+            || self.char == '_'
+            || (prefixed && unicode_ident::is_xid_continue(self.char))
+        {
             self.read_char();
         }
-        self.input.iter().collect::<String>()[position..self.position].to_string()
+        let literal = self.slice_from(start);
+        match parse_integer(literal) {
+            Ok(_) => (TokenType::Int, literal),
+            Err(message) => (self.illegal(message, position), literal),
+        }
     }
 
     fn peek_char(&self) -> char {
-        if self.read_position >= self.input.len() {
-            return '\0';
-        }
-        self.input[self.read_position]
+        self.input[self.read_position..]
+            .chars()
+            .next()
+            .unwrap_or('\0')
     }
 
-    fn read_string(&mut self) -> String {
+    fn read_string(&mut self) -> &'a str {
         let position = self.position + 1;
         loop {
             self.read_char();
@@ -85,61 +206,97 @@ impl Lexer {
                 break;
             }
         }
-        self.input[position..self.position].iter().collect()
+        self.slice_from(position)
+    }
+
+    fn current_position(&self) -> Position {
+        Position {
+            line: self.line,
+            column: self.column,
+        }
     }
 
     pub fn next_token(&mut self) -> Token {
+        self.next_token_ref().to_token()
+    }
+
+    /// Like [`Lexer::next_token`], but the literal borrows from the input.
+    pub fn next_token_ref(&mut self) -> TokenRef<'a> {
+        self.next_token_spanned().0
+    }
+
+    /// The next token and the bytes of the input it was read from. Unlike the
+    /// literal, the span includes the quotes around strings. Whatever lies
+    /// between two spans is whitespace and comments.
+    pub fn next_token_spanned(&mut self) -> (TokenRef<'a>, Range<usize>) {
         self.eat_whitespace();
-        let token = match self.char {
-            '=' => {
-                if self.peek_char() == '=' {
-                    let ch = self.char;
-                    self.read_char();
-                    Token::build(TokenType::Eq, &(String::from(ch) + &self.char.to_string()))
-                } else {
-                    Token::build(TokenType::Assign, &self.char.to_string())
-                }
+        let position = self.current_position();
+        let start = self.position;
+        let (r#type, literal) = self.read_token(start);
+        let token = TokenRef {
+            r#type,
+            literal,
+            position,
+        };
+        (token, start..self.position)
+    }
+
+    /// Reads one token starting at byte offset `start`, which is the current character.
+    fn read_token(&mut self, start: usize) -> (TokenType, &'a str) {
+        let token_type = match self.char {
+            '=' if self.peek_char() == '=' => {
+                self.read_char();
+                TokenType::Eq
+            }
+            '=' => TokenType::Assign,
+            ';' => TokenType::Semicolon,
+            '(' => TokenType::LParen,
+            ')' => TokenType::RParen,
+            ',' => TokenType::Comma,
+            '+' => TokenType::Plus,
+            '{' => TokenType::LBrace,
+            '}' => TokenType::RBrace,
+            '[' => TokenType::LBracket,
+            ']' => TokenType::RBracket,
+            '-' => TokenType::Minus,
+            '!' if self.peek_char() == '=' => {
+                self.read_char();
+                TokenType::NotEq
             }
-            ';' => Token::build(TokenType::Semicolon, &self.char.to_string()),
-            '(' => Token::build(TokenType::LParen, &self.char.to_string()),
-            ')' => Token::build(TokenType::RParen, &self.char.to_string()),
-            ',' => Token::build(TokenType::Comma, &self.char.to_string()),
-            '+' => Token::build(TokenType::Plus, &self.char.to_string()),
-            '{' => Token::build(TokenType::LBrace, &self.char.to_string()),
-            '}' => Token::build(TokenType::RBrace, &self.char.to_string()),
-            '[' => Token::build(TokenType::LBracket, &self.char.to_string()),
-            ']' => Token::build(TokenType::RBracket, &self.char.to_string()),
-            '-' => Token::build(TokenType::Minus, &self.char.to_string()),
-            '!' => {
-                if self.peek_char() == '=' {
-                    let ch = self.char;
-                    self.read_char();
-                    Token::build(
-                        TokenType::NotEq,
-                        &(String::from(ch) + &self.char.to_string()),
-                    )
-                } else {
-                    Token::build(TokenType::Bang, &self.char.to_string())
-                }
+            '!' => TokenType::Bang,
+            '*' => TokenType::Asterisk,
+            '/' => TokenType::Slash,
+            '<' => TokenType::LT,
+            '>' => TokenType::GT,
+            '"' => {
+                let literal = self.read_string();
+                self.read_char();
+                return (TokenType::String, literal);
             }
-            '*' => Token::build(TokenType::Asterisk, &self.char.to_string()),
-            '/' => Token::build(TokenType::Slash, &self.char.to_string()),
-            '<' => Token::build(TokenType::LT, &self.char.to_string()),
-            '>' => Token::build(TokenType::GT, &self.char.to_string()),
-            '"' => Token::build(TokenType::String, &self.read_string()),
-            '\0' => Token::build(TokenType::EOF, ""),
-            _ => {
-                if self.char.is_alphabetic() || self.char == '_' {
-                    let literal = self.read_identifier();
-                    return Token::build(self.loop_up_ident(&literal), &literal);
-                } else if self.char.is_ascii_digit() {
-                    return Token::build(TokenType::Int, &self.read_number());
-                } else {
-                    Token::build(TokenType::Illegal, &self.char.to_string())
-                }
+            '\0' => return (TokenType::EOF, ""),
+            c if Lexer::is_identifier_start(c) => {
+                let literal = self.read_identifier();
+                return (Lexer::look_up_ident(literal), literal);
+            }
+            c if c.is_ascii_digit() => return self.read_number(),
+            c => {
+                let position = self.current_position();
+                self.illegal(format!("unexpected character `{}`", c), position)
             }
         };
         self.read_char();
-        token
+        (token_type, self.slice_from(start))
+    }
+}
+
+/// Yields every token before `EOF`.
+impl Iterator for Lexer<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.next_token();
+        (token.r#type != TokenType::EOF).then_some(token)
     }
 }
+
+impl FusedIterator for Lexer<'_> {}
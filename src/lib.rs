@@ -1,7 +1,20 @@
 pub mod ast;
 pub mod builtins;
+pub mod convert;
+pub mod cst;
+pub mod dap;
 pub mod evaluator;
+pub mod formatter;
+pub mod interpreter;
 pub mod lexer;
+pub mod linter;
+pub mod lsp;
+pub mod macro_expansion;
 pub mod object;
 pub mod parser;
+pub mod profiler;
+pub mod protocol;
+pub mod resolver;
+pub mod runtime;
 pub mod token;
+pub mod tracer;
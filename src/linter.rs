@@ -0,0 +1,395 @@
+use crate::{
+    ast::{
+        self, BlockStatement, CatchClause, Expression, FunctionLiteral, Identifier,
+        InfixExpression, LetStatement, MacroLiteral, Program, Statement, Visitor,
+    },
+    lexer::Lexer,
+    parser::{Parser, ParserError},
+    token::{Comment, Position},
+};
+use std::{collections::HashMap, fmt::Display};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Rule {
+    UnusedBinding,
+    ShadowedParameter,
+    UnreachableCode,
+    MismatchedTypes,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 4] = [
+        Rule::UnusedBinding,
+        Rule::ShadowedParameter,
+        Rule::UnreachableCode,
+        Rule::MismatchedTypes,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rule::UnusedBinding => "unused-binding",
+            Rule::ShadowedParameter => "shadowed-parameter",
+            Rule::UnreachableCode => "unreachable-code",
+            Rule::MismatchedTypes => "mismatched-types",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Rule> {
+        Rule::ALL.into_iter().find(|rule| rule.name() == name)
+    }
+
+    pub fn default_severity(&self) -> Severity {
+        match self {
+            Rule::MismatchedTypes => Severity::Error,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Allow,
+    Warning,
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Allow => write!(f, "allow"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Per-rule severities; rules that aren't configured use their default.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LintConfig {
+    severities: HashMap<Rule, Severity>,
+}
+
+impl LintConfig {
+    pub fn new() -> LintConfig {
+        LintConfig::default()
+    }
+
+    pub fn set_severity(&mut self, rule: Rule, severity: Severity) {
+        self.severities.insert(rule, severity);
+    }
+
+    pub fn severity(&self, rule: Rule) -> Severity {
+        self.severities
+            .get(&rule)
+            .copied()
+            .unwrap_or(rule.default_severity())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub rule: Rule,
+    pub severity: Severity,
+    pub message: String,
+    pub position: Position,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}[{}]: {}",
+            self.position.line, self.position.column, self.severity, self.rule, self.message
+        )
+    }
+}
+
+/// Lints Monkey source. A `// lint:allow(rule, ...)` comment silences those rules
+/// on its own line and on the line after it.
+pub fn lint_source(input: &str, config: &LintConfig) -> Result<Vec<Diagnostic>, ParserError> {
+    let mut parser = Parser::new(Lexer::new(input));
+    let program = parser.parse_program()?;
+    let allowed = allowed_rules(parser.comments());
+    let diagnostics = lint_program(&program, config)
+        .into_iter()
+        .filter(|d| {
+            !allowed
+                .get(&d.position.line)
+                .is_some_and(|rules| rules.contains(&d.rule))
+        })
+        .collect();
+    Ok(diagnostics)
+}
+
+pub fn lint_program(program: &Program, config: &LintConfig) -> Vec<Diagnostic> {
+    let mut linter = Linter {
+        config,
+        scopes: vec![Scope::default()],
+        defining: Vec::new(),
+        diagnostics: Vec::new(),
+    };
+    linter.visit_program(program);
+    linter.close_scope();
+    linter.diagnostics.sort_by_key(|d| (d.position, d.rule));
+    linter.diagnostics
+}
+
+const ALLOW_DIRECTIVE: &str = "lint:allow(";
+
+/// Lines on which each `lint:allow` comment silences its rules.
+fn allowed_rules(comments: &[Comment]) -> HashMap<usize, Vec<Rule>> {
+    let mut allowed: HashMap<usize, Vec<Rule>> = HashMap::new();
+    for comment in comments {
+        let Some(start) = comment.text.find(ALLOW_DIRECTIVE) else {
+            continue;
+        };
+        let rest = &comment.text[start + ALLOW_DIRECTIVE.len()..];
+        let Some(end) = rest.find(')') else {
+            continue;
+        };
+        let rules: Vec<Rule> = rest[..end]
+            .split(',')
+            .filter_map(|name| Rule::from_name(name.trim()))
+            .collect();
+        for line in [comment.position.line, comment.position.line + 1] {
+            allowed.entry(line).or_default().extend(&rules);
+        }
+    }
+    allowed
+}
+
+#[derive(Debug)]
+struct Binding {
+    name: String,
+    position: Position,
+    used: bool,
+}
+
+/// Bindings of one function body or catch clause. Other blocks don't open
+/// scopes of their own, matching how the evaluator binds `let` in the
+/// function's environment.
+#[derive(Debug, Default)]
+struct Scope {
+    bindings: Vec<Binding>,
+    parameters: Vec<String>,
+}
+
+struct Linter<'a> {
+    config: &'a LintConfig,
+    scopes: Vec<Scope>,
+    /// Functions whose bodies are being visited, as (scope, binding) indices;
+    /// calls they make to themselves don't count as uses.
+    defining: Vec<(usize, usize)>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Linter<'_> {
+    fn report(&mut self, rule: Rule, position: Position, message: String) {
+        let severity = self.config.severity(rule);
+        if severity == Severity::Allow {
+            return;
+        }
+        self.diagnostics.push(Diagnostic {
+            rule,
+            severity,
+            message,
+            position,
+        });
+    }
+
+    fn scope(&mut self) -> &mut Scope {
+        self.scopes
+            .last_mut()
+            .expect("the program scope is never closed early")
+    }
+
+    fn is_parameter(&self, name: &str) -> bool {
+        self.scopes
+            .iter()
+            .any(|s| s.parameters.iter().any(|p| p == name))
+    }
+
+    fn declare(&mut self, name: &Identifier) {
+        if self.is_parameter(&name.value) {
+            self.report(
+                Rule::ShadowedParameter,
+                name.token.position,
+                format!("`{}` shadows a function parameter", name.value),
+            );
+        }
+        self.scope().bindings.push(Binding {
+            name: name.value.clone(),
+            position: name.token.position,
+            used: false,
+        });
+    }
+
+    fn open_function(&mut self, parameters: &[Identifier]) {
+        for parameter in parameters {
+            if self.is_parameter(&parameter.value) {
+                self.report(
+                    Rule::ShadowedParameter,
+                    parameter.token.position,
+                    format!(
+                        "parameter `{}` shadows a parameter of an enclosing function",
+                        parameter.value
+                    ),
+                );
+            }
+        }
+        self.scopes.push(Scope {
+            bindings: Vec::new(),
+            parameters: parameters.iter().map(|p| p.value.clone()).collect(),
+        });
+    }
+
+    fn close_scope(&mut self) {
+        let Some(scope) = self.scopes.pop() else {
+            return;
+        };
+        for binding in scope.bindings {
+            if !binding.used && !binding.name.starts_with('_') {
+                self.report(
+                    Rule::UnusedBinding,
+                    binding.position,
+                    format!("`{}` is never used", binding.name),
+                );
+            }
+        }
+    }
+
+    fn statements(&mut self, statements: &[Statement]) {
+        let mut terminated = false;
+        for statement in statements {
+            if terminated {
+                self.report(
+                    Rule::UnreachableCode,
+                    statement.position(),
+                    String::from("unreachable statement"),
+                );
+                terminated = false;
+            }
+            self.visit_statement(statement);
+            if matches!(statement, Statement::Return(_) | Statement::Throw(_)) {
+                terminated = true;
+            }
+        }
+    }
+}
+
+/// The object type an expression always evaluates to, if that is known statically.
+pub(crate) fn static_type(expression: &Expression) -> Option<&'static str> {
+    match expression {
+        Expression::IntegerLiteral(_) => Some("INTEGER"),
+        Expression::StringLiteral(_) => Some("STRING"),
+        Expression::Boolean(_) => Some("BOOLEAN"),
+        Expression::ArrayLiteral(_) => Some("ARRAY"),
+        Expression::FunctionLiteral(_) => Some("FUNCTION"),
+        Expression::PrefixExpression(p) if p.operator == "!" => Some("BOOLEAN"),
+        Expression::PrefixExpression(p) if p.operator == "-" => Some("INTEGER"),
+        Expression::InfixExpression(i) => match i.operator.as_str() {
+            "==" | "!=" | "<" | ">" => Some("BOOLEAN"),
+            _ => match (static_type(&i.left), static_type(&i.right)) {
+                (Some(left), Some(right)) if left == right => Some(left),
+                _ => None,
+            },
+        },
+        _ => None,
+    }
+}
+
+impl Visitor for Linter<'_> {
+    fn visit_program(&mut self, program: &Program) {
+        self.statements(&program.statements);
+    }
+
+    fn visit_block_statement(&mut self, block: &BlockStatement) {
+        self.statements(&block.statements);
+    }
+
+    fn visit_let_statement(&mut self, statement: &LetStatement) {
+        // Functions are declared first so that they can refer to themselves.
+        if let Expression::FunctionLiteral(_) = statement.value {
+            self.declare(&statement.name);
+            let scope = self.scopes.len() - 1;
+            self.defining
+                .push((scope, self.scopes[scope].bindings.len() - 1));
+            self.visit_expression(&statement.value);
+            self.defining.pop();
+        } else {
+            self.visit_expression(&statement.value);
+            self.declare(&statement.name);
+        }
+    }
+
+    fn visit_identifier(&mut self, identifier: &Identifier) {
+        for (s, scope) in self.scopes.iter_mut().enumerate().rev() {
+            if let Some((b, binding)) = scope
+                .bindings
+                .iter_mut()
+                .enumerate()
+                .rev()
+                .find(|(_, b)| b.name == identifier.value)
+            {
+                if !self.defining.contains(&(s, b)) {
+                    binding.used = true;
+                }
+                return;
+            }
+            if scope.parameters.contains(&identifier.value) {
+                return;
+            }
+        }
+    }
+
+    fn visit_function_literal(&mut self, function: &FunctionLiteral) {
+        self.open_function(&function.parameters);
+        self.visit_block_statement(&function.body);
+        self.close_scope();
+    }
+
+    fn visit_macro_literal(&mut self, r#macro: &MacroLiteral) {
+        self.open_function(&r#macro.parameters);
+        self.visit_block_statement(&r#macro.body);
+        self.close_scope();
+    }
+
+    fn visit_catch_clause(&mut self, catch: &CatchClause) {
+        self.scopes.push(Scope {
+            bindings: vec![Binding {
+                name: catch.parameter.value.clone(),
+                position: catch.parameter.token.position,
+                // Catching without looking at the error is common and fine.
+                used: true,
+            }],
+            parameters: Vec::new(),
+        });
+        self.visit_block_statement(&catch.body);
+        self.close_scope();
+    }
+
+    fn visit_infix_expression(&mut self, expression: &InfixExpression) {
+        if let (Some(left), Some(right)) = (
+            static_type(&expression.left),
+            static_type(&expression.right),
+        ) {
+            if left != right {
+                self.report(
+                    Rule::MismatchedTypes,
+                    expression.left.position(),
+                    format!(
+                        "`{} {} {}` is always a type mismatch",
+                        left, expression.operator, right
+                    ),
+                );
+            }
+        }
+        ast::visit_infix_expression(self, expression);
+    }
+}
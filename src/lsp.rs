@@ -0,0 +1,582 @@
+//! A language server for Monkey. [`Server::handle`] takes one message from
+//! the client and returns the messages to send back, so the transport is up
+//! to the caller; the `rost-lsp` binary speaks it over stdin and stdout.
+//!
+//! Documents are synced in full on every change. Syntax errors come from
+//! [`cst::parse`], which reports all of them; a document without syntax
+//! errors is also resolved and linted, which is what definitions, references
+//! and hovers are built from.
+use crate::{
+    ast::{
+        self, CallExpression, CatchClause, Expression, FunctionLiteral, Identifier, LetStatement,
+        MacroLiteral, Visitor,
+    },
+    builtins::Arity,
+    cst::{self, LineIndex, SyntaxKind, SyntaxNode},
+    formatter::format_source,
+    linter::{lint_source, static_type, LintConfig, Severity},
+    object::{BuiltIn, Environment},
+    resolver::{resolve, Layout, ResolveError},
+    token::TokenType,
+};
+use serde_json::{json, Value};
+use std::{collections::HashMap, ops::Range, rc::Rc};
+
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+const ERROR: u8 = 1;
+const WARNING: u8 = 2;
+
+const COMPLETION_FUNCTION: u8 = 3;
+const COMPLETION_VARIABLE: u8 = 6;
+
+#[derive(Default)]
+pub struct Server {
+    documents: HashMap<String, Document>,
+    shut_down: bool,
+}
+
+impl Server {
+    pub fn new() -> Server {
+        Server::default()
+    }
+
+    /// Whether the client has sent `shutdown`, after which `exit` is a clean exit.
+    pub fn is_shut_down(&self) -> bool {
+        self.shut_down
+    }
+
+    /// Handles a request or notification and returns the response and any
+    /// notifications for the client. `exit` is left to the caller.
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let Some(method) = message["method"].as_str() else {
+            // A response to a request of ours; we don't send any.
+            return Vec::new();
+        };
+        let params = &message["params"];
+        match message.get("id") {
+            Some(id) => {
+                let response = match self.request(method, params) {
+                    Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+                    Err((code, message)) => json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": {"code": code, "message": message},
+                    }),
+                };
+                vec![response]
+            }
+            None => self.notification(method, params),
+        }
+    }
+
+    fn request(&mut self, method: &str, params: &Value) -> Result<Value, (i64, String)> {
+        if method == "initialize" {
+            return Ok(capabilities());
+        }
+        if method == "shutdown" {
+            self.shut_down = true;
+            return Ok(Value::Null);
+        }
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let Some(document) = self.documents.get(uri) else {
+            return match method {
+                "textDocument/definition"
+                | "textDocument/references"
+                | "textDocument/hover"
+                | "textDocument/completion"
+                | "textDocument/formatting" => {
+                    Err((INVALID_PARAMS, format!("unknown document `{}`", uri)))
+                }
+                _ => Err((METHOD_NOT_FOUND, format!("unknown method `{}`", method))),
+            };
+        };
+        let offset = from_lsp_position(&document.text, &params["position"]);
+        let result = match method {
+            "textDocument/definition" => document.definition(uri, offset),
+            "textDocument/references" => {
+                let declarations = params["context"]["includeDeclaration"].as_bool();
+                document.references(uri, offset, declarations.unwrap_or(true))
+            }
+            "textDocument/hover" => document.hover(offset),
+            "textDocument/completion" => document.completion(offset),
+            "textDocument/formatting" => document.formatting(),
+            _ => return Err((METHOD_NOT_FOUND, format!("unknown method `{}`", method))),
+        };
+        Ok(result)
+    }
+
+    fn notification(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let text = match method {
+            "textDocument/didOpen" => params["textDocument"]["text"].as_str(),
+            "textDocument/didChange" => params["contentChanges"]
+                .as_array()
+                .and_then(|changes| changes.last())
+                .and_then(|change| change["text"].as_str()),
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                return vec![publish_diagnostics(uri, Vec::new())];
+            }
+            _ => None,
+        };
+        let Some(text) = text else {
+            return Vec::new();
+        };
+        let document = Document::new(text);
+        let diagnostics = document.analysis.diagnostics.clone();
+        self.documents.insert(String::from(uri), document);
+        vec![publish_diagnostics(uri, diagnostics)]
+    }
+}
+
+fn capabilities() -> Value {
+    json!({
+        "capabilities": {
+            "textDocumentSync": 1,
+            "definitionProvider": true,
+            "referencesProvider": true,
+            "hoverProvider": true,
+            "completionProvider": {},
+            "documentFormattingProvider": true,
+        },
+        "serverInfo": {"name": "rost-lsp"},
+    })
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Value>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": {"uri": uri, "diagnostics": diagnostics},
+    })
+}
+
+/// An LSP position: a line and a column in UTF-16 code units, both from 0.
+fn to_lsp_position(text: &str, offset: usize) -> Value {
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    json!({
+        "line": before.matches('\n').count(),
+        "character": before[line_start..].encode_utf16().count(),
+    })
+}
+
+fn to_lsp_range(text: &str, range: &Range<usize>) -> Value {
+    json!({
+        "start": to_lsp_position(text, range.start),
+        "end": to_lsp_position(text, range.end),
+    })
+}
+
+/// The byte offset of an LSP position, clamped to the end of its line.
+fn from_lsp_position(text: &str, position: &Value) -> usize {
+    let line = position["line"].as_u64().unwrap_or_default() as usize;
+    let character = position["character"].as_u64().unwrap_or_default() as usize;
+    let line_start = match line {
+        0 => 0,
+        _ => match text.match_indices('\n').nth(line - 1) {
+            Some((i, _)) => i + 1,
+            None => return text.len(),
+        },
+    };
+    let line_text = text[line_start..].split('\n').next().unwrap_or_default();
+    let mut units = 0;
+    for (i, char) in line_text.char_indices() {
+        if units >= character {
+            return line_start + i;
+        }
+        units += char.len_utf16();
+    }
+    line_start + line_text.len()
+}
+
+fn diagnostic(text: &str, range: &Range<usize>, severity: u8, message: String) -> Value {
+    json!({
+        "range": to_lsp_range(text, range),
+        "severity": severity,
+        "source": "rost",
+        "message": message,
+    })
+}
+
+fn builtin_detail(builtin: &BuiltIn) -> String {
+    let arguments = match builtin.arity {
+        Arity::Exact(1) => String::from("1 argument"),
+        Arity::Exact(n) => format!("{} arguments", n),
+        Arity::AtLeast(n) => format!("at least {} arguments", n),
+        Arity::Any => String::from("any number of arguments"),
+    };
+    format!("builtin {}({})", builtin.name, arguments)
+}
+
+struct Document {
+    text: String,
+    analysis: Analysis,
+}
+
+/// A variable: every `let`, parameter or catch parameter bound to the same
+/// slot, and every identifier resolved to it.
+struct Symbol {
+    /// What hovering over the symbol shows, from its first declaration.
+    detail: String,
+    is_function: bool,
+    declarations: Vec<Range<usize>>,
+    occurrences: Vec<Range<usize>>,
+}
+
+struct Analysis {
+    syntax: SyntaxNode,
+    /// Formatting is refused while the text doesn't parse.
+    parses: bool,
+    symbols: Vec<Symbol>,
+    diagnostics: Vec<Value>,
+}
+
+impl Document {
+    fn new(text: &str) -> Document {
+        Document {
+            text: String::from(text),
+            analysis: analyze(text),
+        }
+    }
+
+    fn location(&self, uri: &str, range: &Range<usize>) -> Value {
+        json!({"uri": uri, "range": to_lsp_range(&self.text, range)})
+    }
+
+    /// The symbol with an occurrence at or just before `offset`, so that a
+    /// cursor right after a name still finds it.
+    fn symbol_at(&self, offset: usize) -> Option<&Symbol> {
+        self.analysis.symbols.iter().find(|symbol| {
+            symbol
+                .occurrences
+                .iter()
+                .any(|range| range.start <= offset && offset <= range.end)
+        })
+    }
+
+    fn definition(&self, uri: &str, offset: usize) -> Value {
+        match self.symbol_at(offset) {
+            Some(symbol) => self.location(uri, &symbol.declarations[0]),
+            None => Value::Null,
+        }
+    }
+
+    fn references(&self, uri: &str, offset: usize, declarations: bool) -> Value {
+        let Some(symbol) = self.symbol_at(offset) else {
+            return Value::Null;
+        };
+        symbol
+            .occurrences
+            .iter()
+            .filter(|range| declarations || !symbol.declarations.contains(range))
+            .map(|range| self.location(uri, range))
+            .collect()
+    }
+
+    fn hover(&self, offset: usize) -> Value {
+        let hover = |detail: &str, range: &Range<usize>| {
+            json!({
+                "contents": {"kind": "plaintext", "value": detail},
+                "range": to_lsp_range(&self.text, range),
+            })
+        };
+        if let Some(symbol) = self.symbol_at(offset) {
+            let range = symbol
+                .occurrences
+                .iter()
+                .find(|range| range.start <= offset && offset <= range.end)
+                .expect("symbol_at found an occurrence");
+            return hover(&symbol.detail, range);
+        }
+        let Some(token) = identifier_at(&self.analysis.syntax, offset) else {
+            return Value::Null;
+        };
+        match Environment::new(None).builtins().get(token.text()) {
+            Some(builtin) => hover(&builtin_detail(&builtin), &token.text_range()),
+            None => Value::Null,
+        }
+    }
+
+    /// The names declared in scope at `offset`, then the builtins.
+    fn completion(&self, offset: usize) -> Value {
+        let mut items: Vec<Value> = Vec::new();
+        let mut names: Vec<String> = Vec::new();
+        for (name, declaration) in visible_declarations(&self.analysis.syntax, offset) {
+            if names.contains(&name) {
+                continue;
+            }
+            let symbol = self
+                .analysis
+                .symbols
+                .iter()
+                .find(|s| s.declarations.contains(&declaration));
+            let kind = match symbol {
+                Some(symbol) if symbol.is_function => COMPLETION_FUNCTION,
+                _ => COMPLETION_VARIABLE,
+            };
+            let mut item = json!({"label": name, "kind": kind});
+            if let Some(symbol) = symbol {
+                item["detail"] = json!(symbol.detail);
+            }
+            items.push(item);
+            names.push(name);
+        }
+        let env = Environment::new(None);
+        let builtins = env.builtins();
+        for name in builtins.names() {
+            if names.iter().any(|n| n == name) {
+                continue;
+            }
+            let builtin = builtins.get(name).expect("names are registered");
+            items.push(json!({
+                "label": name,
+                "kind": COMPLETION_FUNCTION,
+                "detail": builtin_detail(&builtin),
+            }));
+        }
+        Value::Array(items)
+    }
+
+    /// A single edit replacing the whole document, or `null` if it doesn't parse.
+    fn formatting(&self) -> Value {
+        if !self.analysis.parses {
+            return Value::Null;
+        }
+        match format_source(&self.text) {
+            Ok(formatted) if formatted == self.text => json!([]),
+            Ok(formatted) => json!([{
+                "range": to_lsp_range(&self.text, &(0..self.text.len())),
+                "newText": formatted,
+            }]),
+            Err(_) => Value::Null,
+        }
+    }
+}
+
+fn identifier_at(root: &SyntaxNode, offset: usize) -> Option<cst::SyntaxToken> {
+    root.tokens().find(|token| {
+        let range = token.text_range();
+        token.kind() == SyntaxKind::Token(TokenType::Ident)
+            && range.start <= offset
+            && offset <= range.end
+    })
+}
+
+/// Names declared in the program or in a function around `offset`, with the
+/// range of each declaration. Works on documents with syntax errors too.
+fn visible_declarations(root: &SyntaxNode, offset: usize) -> Vec<(String, Range<usize>)> {
+    let mut declarations = Vec::new();
+    for node in root.descendants() {
+        let Some(parent) = node.parent() else {
+            continue;
+        };
+        if node.kind() != SyntaxKind::Identifier
+            || !matches!(
+                parent.kind(),
+                SyntaxKind::LetStatement | SyntaxKind::ParameterList | SyntaxKind::CatchClause
+            )
+        {
+            continue;
+        }
+        let scope = parent
+            .ancestors()
+            .find(|a| {
+                matches!(
+                    a.kind(),
+                    SyntaxKind::FunctionLiteral | SyntaxKind::MacroLiteral | SyntaxKind::Program
+                )
+            })
+            .expect("the program encloses every node");
+        let range = scope.text_range();
+        if scope.kind() == SyntaxKind::Program || (range.start < offset && offset <= range.end) {
+            declarations.push((node.to_string().trim().to_string(), node.text_range()));
+        }
+    }
+    declarations
+}
+
+fn analyze(text: &str) -> Analysis {
+    let parse = cst::parse(text);
+    let syntax = parse.syntax();
+    let parses = parse.errors().is_empty();
+    let mut diagnostics: Vec<Value> = parse
+        .errors()
+        .iter()
+        .map(|e| diagnostic(text, &e.range, ERROR, e.message.clone()))
+        .collect();
+    let Ok(program) = parse.to_program() else {
+        return Analysis {
+            syntax,
+            parses,
+            symbols: Vec::new(),
+            diagnostics,
+        };
+    };
+
+    let lines = LineIndex::new(text);
+    let env = Environment::new(None);
+    if let Err(errors) = resolve(&program, &env) {
+        for ResolveError::UndefinedVariable(name, position) in errors {
+            let start = lines.offset(position);
+            diagnostics.push(diagnostic(
+                text,
+                &(start..start + name.len()),
+                ERROR,
+                format!("undefined variable `{}`", name),
+            ));
+        }
+    }
+    if let Ok(lints) = lint_source(text, &LintConfig::new()) {
+        for lint in lints {
+            let start = lines.offset(lint.position);
+            let range = syntax
+                .token_at_offset(start)
+                .map_or(start..start, |token| token.text_range());
+            let severity = match lint.severity {
+                Severity::Error => ERROR,
+                _ => WARNING,
+            };
+            let mut diagnostic = diagnostic(text, &range, severity, lint.message);
+            diagnostic["code"] = json!(lint.rule.name());
+            diagnostics.push(diagnostic);
+        }
+    }
+
+    let mut symbols = Symbols {
+        lines: &lines,
+        scopes: vec![env.layout()],
+        quoting: 0,
+        slots: HashMap::new(),
+        symbols: Vec::new(),
+    };
+    symbols.visit_program(&program);
+    Analysis {
+        syntax,
+        parses,
+        symbols: symbols.symbols,
+        diagnostics,
+    }
+}
+
+/// Groups the identifiers of a resolved program by the slot they refer to.
+struct Symbols<'a> {
+    lines: &'a LineIndex,
+    /// Layouts of the enclosing functions, innermost last, as in the resolver.
+    scopes: Vec<Rc<Layout>>,
+    /// How many `quote` calls we're inside; the resolver doesn't enter
+    /// functions there, so they don't open scopes.
+    quoting: usize,
+    slots: HashMap<(*const Layout, usize), usize>,
+    symbols: Vec<Symbol>,
+}
+
+impl Symbols<'_> {
+    fn range(&self, identifier: &Identifier) -> Range<usize> {
+        let start = self.lines.offset(identifier.token.position);
+        start..start + identifier.value.len()
+    }
+
+    fn symbol(&mut self, identifier: &Identifier) -> Option<&mut Symbol> {
+        let resolution = identifier.resolution.get()?;
+        let layout = &self.scopes[self.scopes.len().checked_sub(resolution.depth + 1)?];
+        let key = (Rc::as_ptr(layout), resolution.slot);
+        let index = match self.slots.get(&key) {
+            Some(index) => *index,
+            None => {
+                self.symbols.push(Symbol {
+                    detail: String::new(),
+                    is_function: false,
+                    declarations: Vec::new(),
+                    occurrences: Vec::new(),
+                });
+                self.slots.insert(key, self.symbols.len() - 1);
+                self.symbols.len() - 1
+            }
+        };
+        Some(&mut self.symbols[index])
+    }
+
+    fn declare(&mut self, identifier: &Identifier, detail: String, is_function: bool) {
+        let range = self.range(identifier);
+        if let Some(symbol) = self.symbol(identifier) {
+            if symbol.declarations.is_empty() {
+                symbol.detail = detail;
+                symbol.is_function = is_function;
+            }
+            symbol.declarations.push(range.clone());
+            symbol.occurrences.push(range);
+        }
+    }
+}
+
+fn parameter_list(parameters: &[Identifier]) -> String {
+    let names: Vec<&str> = parameters.iter().map(|p| p.value.as_str()).collect();
+    names.join(", ")
+}
+
+impl Visitor for Symbols<'_> {
+    fn visit_identifier(&mut self, identifier: &Identifier) {
+        let range = self.range(identifier);
+        if let Some(symbol) = self.symbol(identifier) {
+            symbol.occurrences.push(range);
+        }
+    }
+
+    fn visit_let_statement(&mut self, statement: &LetStatement) {
+        let name = &statement.name.value;
+        let (detail, is_function) = match &statement.value {
+            Expression::FunctionLiteral(f) => (
+                format!("let {}: fn({})", name, parameter_list(&f.parameters)),
+                true,
+            ),
+            Expression::MacroLiteral(m) => (
+                format!("let {}: macro({})", name, parameter_list(&m.parameters)),
+                true,
+            ),
+            value => match static_type(value) {
+                Some(r#type) => (format!("let {}: {}", name, r#type), false),
+                None => (format!("let {}", name), false),
+            },
+        };
+        self.declare(&statement.name, detail, is_function);
+        self.visit_expression(&statement.value);
+    }
+
+    fn visit_function_literal(&mut self, function: &FunctionLiteral) {
+        if self.quoting > 0 {
+            self.visit_block_statement(&function.body);
+            return;
+        }
+        self.scopes.push(Rc::clone(&function.layout));
+        for parameter in function.parameters.iter() {
+            self.declare(parameter, format!("parameter {}", parameter.value), false);
+        }
+        self.visit_block_statement(&function.body);
+        self.scopes.pop();
+    }
+
+    fn visit_macro_literal(&mut self, _macro: &MacroLiteral) {}
+
+    fn visit_catch_clause(&mut self, catch: &CatchClause) {
+        if self.quoting > 0 {
+            self.visit_block_statement(&catch.body);
+            return;
+        }
+        self.scopes.push(Rc::clone(&catch.layout));
+        let name = &catch.parameter.value;
+        self.declare(&catch.parameter, format!("catch ({})", name), false);
+        self.visit_block_statement(&catch.body);
+        self.scopes.pop();
+    }
+
+    fn visit_call_expression(&mut self, call: &CallExpression) {
+        let quote =
+            matches!(call.function.as_ref(), Expression::Identifier(i) if i.value == "quote");
+        self.quoting += usize::from(quote);
+        ast::visit_call_expression(self, call);
+        self.quoting -= usize::from(quote);
+    }
+}
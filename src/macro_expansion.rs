@@ -0,0 +1,81 @@
+use crate::{
+    ast::{self, CallExpression, Expression, Program, Statement},
+    evaluator::{eval, EvaluationError},
+    object::{Environment, Macro, Object, Quote},
+};
+
+/// Moves the top-level `let name = macro(...) { ... };` statements out of
+/// `program` and into `env`.
+pub fn define_macros(program: &mut Program, env: &mut Environment) {
+    program.statements.retain(|statement| {
+        let Statement::Let(ls) = statement else {
+            return true;
+        };
+        let Expression::MacroLiteral(ml) = &ls.value else {
+            return true;
+        };
+        env.set(
+            &ls.name.value,
+            Object::Macro(Macro {
+                parameters: ml.parameters.clone(),
+                body: ml.body.clone(),
+                env: env.clone(),
+            }),
+        );
+        false
+    });
+}
+
+/// Replaces every call to a macro defined in `env` with the AST the macro returns.
+pub fn expand_macros(program: Program, env: &mut Environment) -> Result<Program, EvaluationError> {
+    ast::modify(program, &mut |node| {
+        let Expression::CallExpression(call) = node else {
+            return Ok(node);
+        };
+        let Some(r#macro) = macro_for(&call, env) else {
+            return Ok(Expression::CallExpression(call));
+        };
+        if r#macro.parameters.len() != call.arguments.len() {
+            return Err(EvaluationError::ArgumentError(format!(
+                "Wrong number of arguments.\nExpected: {}\nGot: {}",
+                r#macro.parameters.len(),
+                call.arguments.len()
+            )));
+        }
+        let mut macro_env = Environment::new_enclosed_environment(&mut r#macro.env.clone());
+        for (parameter, argument) in r#macro.parameters.iter().zip(call.arguments) {
+            macro_env.set(
+                &parameter.value,
+                Object::Quote(Quote {
+                    node: Box::new(argument),
+                }),
+            );
+        }
+        // Run as a program of its own, so the body is resolved against the
+        // parameters before it is evaluated.
+        let body = Program {
+            statements: r#macro.body.statements,
+        };
+        match eval(&body, &mut macro_env)? {
+            Object::Quote(q) => Ok(*q.node),
+            o => Err(not_a_quote(&o)),
+        }
+    })
+}
+
+fn macro_for(call: &CallExpression, env: &Environment) -> Option<Macro> {
+    let Expression::Identifier(i) = call.function.as_ref() else {
+        return None;
+    };
+    match env.get(&i.value) {
+        Ok(Object::Macro(m)) => Some(m),
+        _ => None,
+    }
+}
+
+fn not_a_quote(object: &Object) -> EvaluationError {
+    EvaluationError::MacroError(format!(
+        "Macros must return a quoted AST node\nGot: {}",
+        object
+    ))
+}
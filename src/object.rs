@@ -1,9 +1,16 @@
 use crate::{
-    ast::{BlockStatement, Identifier},
-    builtins::BuiltInFunction,
-    evaluator::EvaluationError,
+    ast::{BlockStatement, Expression, Identifier, Statement},
+    builtins::{Arity, BuiltInFunction, Builtins},
+    evaluator::{EvaluationError, StackTrace},
+    resolver::{Layout, Resolution},
+    runtime::Runtime,
+};
+use std::{
+    cell::{Cell, Ref, RefCell, RefMut},
+    collections::{BTreeMap, HashMap},
+    fmt::{Debug, Display},
+    rc::{Rc, Weak},
 };
-use std::{collections::HashMap, fmt::Display};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Object {
@@ -14,9 +21,37 @@ pub enum Object {
     Function(Function),
     String(StringObj),
     BuiltIn(BuiltIn),
+    Array(Array),
+    Hash(Hash),
+    Error(ErrorObj),
+    Quote(Quote),
+    Macro(Macro),
     Null,
 }
 
+impl Object {
+    /// Approximate bytes allocated when this value is created. Objects are
+    /// values, so nested arrays and hashes are counted as the copies they are.
+    pub fn allocation_size(&self) -> usize {
+        let heap = match self {
+            Object::String(s) => s.value.len(),
+            Object::Array(a) => a.elements.iter().map(Object::allocation_size).sum(),
+            Object::Hash(h) => h
+                .pairs
+                .iter()
+                .map(|(k, v)| k.len() + size_of::<String>() + v.allocation_size())
+                .sum(),
+            Object::Error(e) => e.kind.len() + e.message.len(),
+            Object::Function(f) => {
+                f.parameters.len() * size_of::<Identifier>()
+                    + f.body.statements.len() * size_of::<Statement>()
+            }
+            _ => 0,
+        };
+        size_of::<Object>() + heap
+    }
+}
+
 impl ObjectTrait for Object {
     fn r#type(&self) -> String {
         match self {
@@ -27,7 +62,12 @@ impl ObjectTrait for Object {
             Object::Function(f) => f.r#type(),
             Object::String(s) => s.r#type(),
             Object::BuiltIn(bi) => bi.r#type(),
-            Object::Null => panic!("See if this ever happens"),
+            Object::Array(a) => a.r#type(),
+            Object::Hash(h) => h.r#type(),
+            Object::Error(e) => e.r#type(),
+            Object::Quote(q) => q.r#type(),
+            Object::Macro(m) => m.r#type(),
+            Object::Null => Null {}.r#type(),
         }
     }
 }
@@ -42,6 +82,11 @@ impl Display for Object {
             Object::Function(func) => write!(f, "{}", func),
             Object::String(s) => write!(f, "{}", s),
             Object::BuiltIn(bi) => write!(f, "{}", bi),
+            Object::Array(a) => write!(f, "{}", a),
+            Object::Hash(h) => write!(f, "{}", h),
+            Object::Error(e) => write!(f, "{}", e),
+            Object::Quote(q) => write!(f, "{}", q),
+            Object::Macro(m) => write!(f, "{}", m),
             Object::Null => write!(f, "null"),
         }
     }
@@ -121,11 +166,20 @@ impl ObjectTrait for ReturnValue {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Function {
-    pub parameters: Vec<Identifier>,
-    pub body: BlockStatement,
+    pub name: Option<String>,
+    pub parameters: Rc<[Identifier]>,
+    pub body: Rc<BlockStatement>,
+    pub layout: Rc<Layout>,
     pub env: Environment,
 }
 
+impl Function {
+    /// The name the function was bound to with `let`, or `<anonymous>`.
+    pub fn display_name(&self) -> &str {
+        self.name.as_deref().unwrap_or("<anonymous>")
+    }
+}
+
 impl Display for Function {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let parameters = self
@@ -144,6 +198,49 @@ impl ObjectTrait for Function {
     }
 }
 
+/// An unevaluated expression produced by `quote`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Quote {
+    pub node: Box<Expression>,
+}
+
+impl Display for Quote {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "QUOTE({})", self.node)
+    }
+}
+
+impl ObjectTrait for Quote {
+    fn r#type(&self) -> String {
+        String::from("QUOTE")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Macro {
+    pub parameters: Vec<Identifier>,
+    pub body: BlockStatement,
+    pub env: Environment,
+}
+
+impl Display for Macro {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let parameters = self
+            .parameters
+            .iter()
+            .map(|p| p.value.to_string())
+            .collect::<Vec<String>>()
+            .join(", ");
+        write!(f, "macro({}) {{\n  {}\n}}", parameters, self.body)
+    }
+}
+
+impl ObjectTrait for Macro {
+    fn r#type(&self) -> String {
+        String::from("MACRO")
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct StringObj {
     pub value: String,
@@ -161,11 +258,30 @@ impl ObjectTrait for StringObj {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Clone)]
 pub struct BuiltIn {
+    pub name: String,
+    pub arity: Arity,
     pub func: BuiltInFunction,
 }
 
+impl Debug for BuiltIn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BuiltIn")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for BuiltIn {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.arity == other.arity && Rc::ptr_eq(&self.func, &other.func)
+    }
+}
+
+impl Eq for BuiltIn {}
+
 impl Display for BuiltIn {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "builtin function",)
@@ -178,6 +294,110 @@ impl ObjectTrait for BuiltIn {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Array {
+    pub elements: Vec<Object>,
+}
+
+impl Display for Array {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let elements = self
+            .elements
+            .iter()
+            .map(|e| e.to_string())
+            .collect::<Vec<String>>()
+            .join(", ");
+        write!(f, "[{}]", elements)
+    }
+}
+
+impl ObjectTrait for Array {
+    fn r#type(&self) -> String {
+        String::from("ARRAY")
+    }
+}
+
+/// A map with string keys. Scripts have no literal for one; hashes come from
+/// the host, converted from a `HashMap` with
+/// [`IntoObject`](crate::convert::IntoObject), and scripts read them by index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hash {
+    pub pairs: BTreeMap<String, Object>,
+}
+
+impl Display for Hash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pairs = self
+            .pairs
+            .iter()
+            .map(|(k, v)| format!("{}: {}", k, v))
+            .collect::<Vec<String>>()
+            .join(", ");
+        write!(f, "{{{}}}", pairs)
+    }
+}
+
+impl ObjectTrait for Hash {
+    fn r#type(&self) -> String {
+        String::from("HASH")
+    }
+}
+
+/// A runtime error as a script sees it in a `catch` block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorObj {
+    pub kind: String,
+    pub message: String,
+    pub stack: StackTrace,
+}
+
+impl ErrorObj {
+    pub fn new(kind: &str, message: &str) -> ErrorObj {
+        ErrorObj {
+            kind: String::from(kind),
+            message: String::from(message),
+            stack: StackTrace::default(),
+        }
+    }
+
+    /// Looks up the fields scripts can read with `error["kind"]` and friends.
+    pub fn field(&self, name: &str) -> Option<Object> {
+        match name {
+            "kind" => Some(Object::String(StringObj {
+                value: self.kind.clone(),
+            })),
+            "message" => Some(Object::String(StringObj {
+                value: self.message.clone(),
+            })),
+            "stack" => Some(Object::Array(Array {
+                elements: self
+                    .stack
+                    .frames
+                    .iter()
+                    .map(|frame| {
+                        Object::String(StringObj {
+                            value: frame.to_string(),
+                        })
+                    })
+                    .collect(),
+            })),
+            _ => None,
+        }
+    }
+}
+
+impl Display for ErrorObj {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.kind, self.message)
+    }
+}
+
+impl ObjectTrait for ErrorObj {
+    fn r#type(&self) -> String {
+        String::from("ERROR")
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Null {}
 
@@ -193,39 +413,341 @@ impl ObjectTrait for Null {
     }
 }
 
-#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// A handle to a scope. Clones share the same bindings, so closures observe
+/// later `let`s in their defining scope, which makes recursion work.
+///
+/// Bindings are kept in slots numbered by the scope's [`Layout`]; resolved
+/// identifiers index them directly and everything else goes through the names.
+#[derive(Clone)]
 pub struct Environment {
-    store: HashMap<String, Object>,
-    outer: Option<Box<Environment>>,
+    scope: Rc<RefCell<Scope>>,
+    runtime: Rc<Runtime>,
+}
+
+#[derive(Default)]
+struct Scope {
+    layout: Rc<Layout>,
+    slots: Vec<Option<Object>>,
+    outer: Option<Environment>,
+}
+
+impl Scope {
+    fn set(&mut self, slot: usize, val: Object) {
+        if slot >= self.slots.len() {
+            self.slots.resize(slot + 1, None);
+        }
+        self.slots[slot] = Some(val);
+    }
+}
+
+impl Default for Environment {
+    fn default() -> Environment {
+        Environment::new(None)
+    }
 }
 
+impl Debug for Environment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let scope = self.scope.borrow();
+        let mut names: Vec<String> = scope
+            .layout
+            .names()
+            .into_iter()
+            .enumerate()
+            .filter(|(slot, _)| matches!(scope.slots.get(*slot), Some(Some(_))))
+            .map(|(_, name)| name)
+            .collect();
+        names.sort();
+        f.debug_struct("Environment")
+            .field("names", &names)
+            .field("outer", &scope.outer)
+            .finish()
+    }
+}
+
+/// Environments are compared by identity; comparing contents would recurse
+/// forever through closures that capture their own scope.
+impl PartialEq for Environment {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.scope, &other.scope)
+    }
+}
+
+impl Eq for Environment {}
+
 impl Environment {
     pub fn new(outer: Option<Box<Environment>>) -> Environment {
-        Environment {
-            store: HashMap::new(),
+        Environment::with_layout(outer.map(|o| *o), Rc::default())
+    }
+
+    /// A scope whose slots are numbered by `layout`, as for a call of a resolved function.
+    pub fn with_layout(outer: Option<Environment>, layout: Rc<Layout>) -> Environment {
+        let runtime = match &outer {
+            Some(outer) => Rc::clone(&outer.runtime),
+            None => Rc::new(Runtime::new()),
+        };
+        let scope = Rc::new(RefCell::new(Scope {
+            slots: vec![None; layout.len()],
+            layout,
             outer,
-        }
+        }));
+        runtime.scopes().track(&scope);
+        Environment { scope, runtime }
     }
 
     pub fn get(&self, name: &str) -> Result<Object, EvaluationError> {
-        match self.store.get(name) {
-            Some(value) => Ok(value.clone()),
-            None => {
-                if self.outer.is_some() {
-                    Ok(self.outer.as_ref().unwrap().get(name)?)
-                } else {
-                    Err(EvaluationError::IdentError(name.to_string()))
-                }
-            }
+        let scope = self.scope.borrow();
+        let value = scope
+            .layout
+            .slot(name)
+            .and_then(|slot| scope.slots.get(slot))
+            .and_then(Option::as_ref);
+        match (value, &scope.outer) {
+            (Some(value), _) => Ok(value.clone()),
+            (None, Some(outer)) => outer.get(name),
+            (None, None) => Err(EvaluationError::IdentError(name.to_string())),
+        }
+    }
+
+    /// Looks up a resolved identifier. A slot that hasn't been assigned yet
+    /// falls back to looking `name` up in the enclosing scopes.
+    pub fn get_resolved(
+        &self,
+        name: &str,
+        resolution: Resolution,
+    ) -> Result<Object, EvaluationError> {
+        let scope = self.scope.borrow();
+        if resolution.depth > 0 {
+            return match &scope.outer {
+                Some(outer) => outer.get_resolved(
+                    name,
+                    Resolution {
+                        depth: resolution.depth - 1,
+                        slot: resolution.slot,
+                    },
+                ),
+                None => Err(EvaluationError::IdentError(name.to_string())),
+            };
+        }
+        match (scope.slots.get(resolution.slot), &scope.outer) {
+            (Some(Some(value)), _) => Ok(value.clone()),
+            (_, Some(outer)) => outer.get(name),
+            (_, None) => Err(EvaluationError::IdentError(name.to_string())),
         }
     }
 
+    /// Binds `name` in this scope, giving it a slot in the scope's layout if
+    /// it has none. Meant for defining globals before a run; evaluation binds
+    /// resolved slots and leaves layouts as the resolver made them.
     pub fn set(&mut self, name: &str, val: Object) -> Object {
-        self.store.insert(name.to_string(), val.clone());
+        let mut scope = self.scope.borrow_mut();
+        let slot = scope.layout.declare(name);
+        scope.set(slot, val.clone());
         val
     }
 
+    /// Binds a slot of this scope, as given by a resolution with depth 0.
+    pub fn set_slot(&mut self, slot: usize, val: Object) -> Object {
+        self.scope.borrow_mut().set(slot, val.clone());
+        val
+    }
+
+    /// The values bound in this scope alone, in the order they were declared.
+    pub fn bindings(&self) -> Vec<(String, Object)> {
+        let scope = self.scope.borrow();
+        scope
+            .layout
+            .names()
+            .into_iter()
+            .zip(&scope.slots)
+            .filter_map(|(name, value)| Some((name, value.clone()?)))
+            .collect()
+    }
+
+    pub fn outer(&self) -> Option<Environment> {
+        self.scope.borrow().outer.clone()
+    }
+
+    /// Drops every binding of this scope. Closures stored in a scope keep it
+    /// alive through their own environment, so a scope that is done with has
+    /// to be emptied for it to be freed.
+    pub(crate) fn clear(&self) {
+        let slots = std::mem::take(&mut self.scope.borrow_mut().slots);
+        // Dropped here, after the borrow ends, in case a value's drop looks
+        // at this scope.
+        drop(slots);
+    }
+
+    pub(crate) fn layout(&self) -> Rc<Layout> {
+        Rc::clone(&self.scope.borrow().layout)
+    }
+
+    /// Calls `visit` with each environment this scope holds on to: its
+    /// enclosing scope and the ones captured by the values in its slots.
+    fn for_each_captured(scope: &Scope, visit: &mut impl FnMut(&Environment)) {
+        if let Some(outer) = &scope.outer {
+            visit(outer);
+        }
+        for value in scope.slots.iter().flatten() {
+            value.for_each_captured(visit);
+        }
+    }
+
     pub fn new_enclosed_environment(outer: &mut Environment) -> Environment {
         Environment::new(Some(Box::new(outer.clone())))
     }
+
+    pub fn runtime(&self) -> &Rc<Runtime> {
+        &self.runtime
+    }
+
+    /// The builtin registry is shared by every environment enclosed by the same root.
+    pub fn builtins(&self) -> Ref<'_, Builtins> {
+        self.runtime.builtins()
+    }
+
+    pub fn builtins_mut(&self) -> RefMut<'_, Builtins> {
+        self.runtime.builtins_mut()
+    }
+}
+
+impl Object {
+    fn for_each_captured(&self, visit: &mut impl FnMut(&Environment)) {
+        match self {
+            Object::Function(function) => visit(&function.env),
+            Object::Macro(r#macro) => visit(&r#macro.env),
+            Object::ReturnValue(value) => value.value.for_each_captured(visit),
+            Object::Array(array) => array
+                .elements
+                .iter()
+                .for_each(|element| element.for_each_captured(visit)),
+            Object::Hash(hash) => hash
+                .pairs
+                .values()
+                .for_each(|value| value.for_each_captured(visit)),
+            _ => {}
+        }
+    }
+}
+
+/// The scopes of one runtime, kept to free the ones that are only alive
+/// because closures stored in them capture each other.
+///
+/// A call that stores a closure leaves a cycle between its scope and the
+/// closure, so reference counting alone never frees it. [`Scopes::collect`]
+/// finds the scopes that nothing outside the scopes themselves refers to and
+/// empties them, which breaks the cycles.
+#[derive(Debug, Default)]
+pub(crate) struct Scopes {
+    scopes: RefCell<Vec<Weak<RefCell<Scope>>>>,
+    /// How many tracked scopes trigger the next collection.
+    threshold: Cell<usize>,
+}
+
+impl Scopes {
+    const MIN_THRESHOLD: usize = 1024;
+
+    fn track(&self, scope: &Rc<RefCell<Scope>>) {
+        let tracked = {
+            let mut scopes = self.scopes.borrow_mut();
+            scopes.push(Rc::downgrade(scope));
+            scopes.len()
+        };
+        if tracked >= self.threshold.get().max(Self::MIN_THRESHOLD) {
+            self.collect();
+        }
+    }
+
+    /// Empties every scope that is only reachable from other scopes.
+    ///
+    /// A scope is in use when it has more strong references than the scopes
+    /// account for: an [`Environment`] held by the host, by a call in
+    /// progress or by a value on its way somewhere. Everything those scopes
+    /// reach is kept and the rest is garbage.
+    pub(crate) fn collect(&self) {
+        let live: Vec<Rc<RefCell<Scope>>> = {
+            let mut scopes = self.scopes.borrow_mut();
+            scopes.retain(|scope| scope.strong_count() > 0);
+            scopes.iter().filter_map(Weak::upgrade).collect()
+        };
+        let index: HashMap<*const RefCell<Scope>, usize> = live
+            .iter()
+            .enumerate()
+            .map(|(i, scope)| (Rc::as_ptr(scope), i))
+            .collect();
+
+        let mut internal = vec![0; live.len()];
+        let mut edges = vec![Vec::new(); live.len()];
+        let mut in_use = vec![false; live.len()];
+        for (i, scope) in live.iter().enumerate() {
+            // A scope that is being written to belongs to code that is running.
+            let Ok(scope) = scope.try_borrow() else {
+                in_use[i] = true;
+                continue;
+            };
+            Environment::for_each_captured(&scope, &mut |env| {
+                if let Some(&j) = index.get(&Rc::as_ptr(&env.scope)) {
+                    internal[j] += 1;
+                    edges[i].push(j);
+                }
+            });
+        }
+
+        // `live` holds one reference of its own to each scope.
+        let mut pending: Vec<usize> = (0..live.len())
+            .filter(|&i| in_use[i] || Rc::strong_count(&live[i]) - 1 > internal[i])
+            .collect();
+        pending.iter().for_each(|&i| in_use[i] = true);
+        while let Some(i) = pending.pop() {
+            for &j in &edges[i] {
+                if !in_use[j] {
+                    in_use[j] = true;
+                    pending.push(j);
+                }
+            }
+        }
+
+        let garbage: Vec<Vec<Option<Object>>> = live
+            .iter()
+            .zip(&in_use)
+            .filter(|(_, in_use)| !**in_use)
+            .map(|(scope, _)| std::mem::take(&mut scope.borrow_mut().slots))
+            .collect();
+        // Dropped after the borrows end, as values may free other scopes.
+        drop(garbage);
+        drop(live);
+
+        let tracked = {
+            let mut scopes = self.scopes.borrow_mut();
+            scopes.retain(|scope| scope.strong_count() > 0);
+            scopes.len()
+        };
+        self.threshold.set(tracked * 2);
+    }
+
+    /// Approximate bytes taken by the values bound in live scopes, once the
+    /// garbage is collected. Values are copied when bound, so each binding
+    /// counts on its own.
+    pub(crate) fn live_bytes(&self) -> usize {
+        self.collect();
+        let live: Vec<Rc<RefCell<Scope>>> = self
+            .scopes
+            .borrow()
+            .iter()
+            .filter_map(Weak::upgrade)
+            .collect();
+        live.iter()
+            .filter_map(|scope| {
+                let scope = scope.try_borrow().ok()?;
+                Some(
+                    scope
+                        .slots
+                        .iter()
+                        .flatten()
+                        .map(Object::allocation_size)
+                        .sum::<usize>(),
+                )
+            })
+            .sum()
+    }
 }
@@ -0,0 +1,188 @@
+//! A tracer that measures where a script spends its time and memory.
+use crate::{
+    object::{Function, Object},
+    token::Position,
+    tracer::Tracer,
+};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    fmt::Write as _,
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+/// What was measured for one function literal, over all the closures made from it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionProfile {
+    pub name: String,
+    /// Where the function's body starts.
+    pub position: Position,
+    pub calls: u64,
+    /// Time spent in the function and everything it called. Recursive calls
+    /// are only counted once, in the outermost one.
+    pub total_time: Duration,
+    /// Time spent in the function's own statements.
+    pub self_time: Duration,
+    /// Approximate bytes allocated by the function and everything it called.
+    pub total_allocated: usize,
+    /// Approximate bytes allocated by the function's own statements.
+    pub self_allocated: usize,
+}
+
+impl FunctionProfile {
+    /// The name shown in reports, which tells apart functions with the same name.
+    pub fn label(&self) -> String {
+        format!("{} (line {})", self.name, self.position.line)
+    }
+}
+
+/// Records call counts, time and allocations for each Monkey function.
+/// Attach it with [`Interpreter::set_tracer`](crate::interpreter::Interpreter::set_tracer).
+#[derive(Default)]
+pub struct Profiler {
+    state: RefCell<State>,
+}
+
+#[derive(Default)]
+struct State {
+    profiles: Vec<FunctionProfile>,
+    /// How many calls of each profile are in progress, so recursion isn't
+    /// counted twice in the totals.
+    active: Vec<usize>,
+    indices: HashMap<(String, Position), usize>,
+    calls: Vec<Call>,
+    /// Self time in nanoseconds of each distinct call stack, keyed by the
+    /// stack's labels joined with `;`.
+    stacks: BTreeMap<String, u128>,
+}
+
+struct Call {
+    index: usize,
+    started: Instant,
+    children_time: Duration,
+    allocated: usize,
+    children_allocated: usize,
+}
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        Profiler::default()
+    }
+
+    /// Every function called so far, the one with the most self time first.
+    pub fn functions(&self) -> Vec<FunctionProfile> {
+        let mut profiles = self.state.borrow().profiles.clone();
+        profiles.sort_by(|a, b| {
+            b.self_time
+                .cmp(&a.self_time)
+                .then_with(|| a.position.cmp(&b.position))
+        });
+        profiles
+    }
+
+    /// A table of [`functions`](Profiler::functions), one row per function.
+    pub fn report(&self) -> String {
+        let mut report = format!(
+            "{:<24} {:>8} {:>12} {:>12} {:>12}\n",
+            "function", "calls", "total", "self", "allocated"
+        );
+        for profile in self.functions() {
+            let _ = writeln!(
+                report,
+                "{:<24} {:>8} {:>12} {:>12} {:>12}",
+                profile.label(),
+                profile.calls,
+                format!("{:.2?}", profile.total_time),
+                format!("{:.2?}", profile.self_time),
+                format!("{} B", profile.self_allocated),
+            );
+        }
+        report
+    }
+
+    /// Writes each call stack with its self time in nanoseconds, in the
+    /// collapsed format read by flamegraph tools: `outer;inner 1234`.
+    pub fn write_collapsed_stacks<W: Write>(&self, mut output: W) -> io::Result<()> {
+        for (stack, nanos) in &self.state.borrow().stacks {
+            writeln!(output, "{} {}", stack, nanos)?;
+        }
+        output.flush()
+    }
+}
+
+impl Tracer for Profiler {
+    fn enter_function(&self, function: &Function, _args: &[Object]) {
+        let mut state = self.state.borrow_mut();
+        let key = (
+            function.display_name().to_string(),
+            function.body.token.position,
+        );
+        let index = match state.indices.get(&key) {
+            Some(index) => *index,
+            None => {
+                let index = state.profiles.len();
+                state.profiles.push(FunctionProfile {
+                    name: key.0.clone(),
+                    position: key.1,
+                    calls: 0,
+                    total_time: Duration::ZERO,
+                    self_time: Duration::ZERO,
+                    total_allocated: 0,
+                    self_allocated: 0,
+                });
+                state.active.push(0);
+                state.indices.insert(key, index);
+                index
+            }
+        };
+        state.profiles[index].calls += 1;
+        state.active[index] += 1;
+        state.calls.push(Call {
+            index,
+            started: Instant::now(),
+            children_time: Duration::ZERO,
+            allocated: 0,
+            children_allocated: 0,
+        });
+    }
+
+    fn leave_function(&self, _function: &Function, _result: Option<&Object>) {
+        let mut state = self.state.borrow_mut();
+        let Some(call) = state.calls.pop() else {
+            return;
+        };
+        let elapsed = call.started.elapsed();
+        let self_time = elapsed.saturating_sub(call.children_time);
+        let total_allocated = call.allocated + call.children_allocated;
+
+        state.active[call.index] -= 1;
+        let outermost = state.active[call.index] == 0;
+        let profile = &mut state.profiles[call.index];
+        profile.self_time += self_time;
+        profile.self_allocated += call.allocated;
+        if outermost {
+            profile.total_time += elapsed;
+            profile.total_allocated += total_allocated;
+        }
+
+        let mut stack: Vec<String> = state
+            .calls
+            .iter()
+            .map(|c| state.profiles[c.index].label())
+            .collect();
+        stack.push(state.profiles[call.index].label());
+        *state.stacks.entry(stack.join(";")).or_default() += self_time.as_nanos();
+
+        if let Some(parent) = state.calls.last_mut() {
+            parent.children_time += elapsed;
+            parent.children_allocated += total_allocated;
+        }
+    }
+
+    fn allocate(&self, bytes: usize) {
+        if let Some(call) = self.state.borrow_mut().calls.last_mut() {
+            call.allocated += bytes;
+        }
+    }
+}
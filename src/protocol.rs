@@ -0,0 +1,41 @@
+//! The framing used by the language server and debug adapter protocols: each
+//! message is a JSON body preceded by a `Content-Length` header.
+use serde_json::Value;
+use std::io::{self, BufRead, ErrorKind, Write};
+
+/// Reads the next message, or returns `None` at the end of the input.
+pub fn read_message<R: BufRead>(reader: &mut R) -> io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                let value = value
+                    .trim()
+                    .parse::<usize>()
+                    .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+                length = Some(value);
+            }
+        }
+    }
+    let length = length
+        .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "missing Content-Length header"))?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+}
+
+pub fn write_message<W: Write>(writer: &mut W, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
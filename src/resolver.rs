@@ -0,0 +1,238 @@
+use crate::{
+    ast::{
+        self, CallExpression, CatchClause, Expression, FunctionLiteral, Identifier, LetStatement,
+        MacroLiteral, Program, Visitor,
+    },
+    object::Environment,
+    token::Position,
+};
+use std::{cell::RefCell, collections::HashMap, fmt::Display, rc::Rc};
+
+/// Where a variable lives: `slot` of the scope `depth` functions out from
+/// the one the identifier appears in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Resolution {
+    pub depth: usize,
+    pub slot: usize,
+}
+
+/// The names bound in one scope, in slot order. A function literal's layout
+/// is shared by the environments of all its calls.
+#[derive(Debug, Default)]
+pub struct Layout {
+    slots: RefCell<HashMap<String, usize>>,
+}
+
+impl Layout {
+    pub fn new() -> Layout {
+        Layout::default()
+    }
+
+    pub fn slot(&self, name: &str) -> Option<usize> {
+        self.slots.borrow().get(name).copied()
+    }
+
+    /// Returns the slot for `name`, adding one if it has none yet.
+    pub fn declare(&self, name: &str) -> usize {
+        let mut slots = self.slots.borrow_mut();
+        let next = slots.len();
+        *slots.entry(name.to_string()).or_insert(next)
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.borrow().is_empty()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        let slots = self.slots.borrow();
+        let mut names: Vec<(&String, &usize)> = slots.iter().collect();
+        names.sort_by_key(|(_, slot)| **slot);
+        names.into_iter().map(|(name, _)| name.clone()).collect()
+    }
+}
+
+impl PartialEq for Layout {
+    fn eq(&self, other: &Self) -> bool {
+        *self.slots.borrow() == *other.slots.borrow()
+    }
+}
+
+impl Eq for Layout {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolveError {
+    UndefinedVariable(String, Position),
+}
+
+impl Display for ResolveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResolveError::UndefinedVariable(name, position) => {
+                write!(f, "{}: undefined variable `{}`", position, name)
+            }
+        }
+    }
+}
+
+/// Binds every identifier in `program` to a slot, with `env` as the program's scope.
+///
+/// Only functions and catch clauses open scopes, and a `let` anywhere else in
+/// a function body gets a slot in that function, so later `let`s are visible
+/// to closures created earlier. Names that aren't bound anywhere, aren't defined in `env` and
+/// aren't builtins are returned as errors. Builtins and names from
+/// environments enclosing `env` stay unresolved and are looked up by name.
+pub fn resolve(program: &Program, env: &Environment) -> Result<(), Vec<ResolveError>> {
+    let layout = env.layout();
+    let mut declarations = Declarations { layout: &layout };
+    declarations.visit_program(program);
+    let mut resolver = Resolver {
+        env,
+        scopes: vec![layout],
+        errors: Vec::new(),
+    };
+    resolver.visit_program(program);
+    if resolver.errors.is_empty() {
+        return Ok(());
+    }
+    Err(resolver.errors)
+}
+
+/// Gives slots to the `let`s of one scope, without entering the functions
+/// and catch clauses nested in it.
+struct Declarations<'a> {
+    layout: &'a Layout,
+}
+
+impl Declarations<'_> {
+    fn declare(&mut self, name: &Identifier) {
+        let slot = self.layout.declare(&name.value);
+        name.resolution.set(Some(Resolution { depth: 0, slot }));
+    }
+}
+
+impl Visitor for Declarations<'_> {
+    fn visit_let_statement(&mut self, statement: &LetStatement) {
+        self.declare(&statement.name);
+        self.visit_expression(&statement.value);
+    }
+
+    fn visit_catch_clause(&mut self, _catch: &CatchClause) {}
+
+    fn visit_function_literal(&mut self, _function: &FunctionLiteral) {}
+
+    fn visit_macro_literal(&mut self, _macro: &MacroLiteral) {}
+
+    fn visit_identifier(&mut self, _identifier: &Identifier) {}
+}
+
+struct Resolver<'a> {
+    env: &'a Environment,
+    /// Layouts of the enclosing functions, innermost last.
+    scopes: Vec<Rc<Layout>>,
+    errors: Vec<ResolveError>,
+}
+
+impl Resolver<'_> {
+    fn is_known(&self, name: &str) -> bool {
+        self.env.get(name).is_ok() || self.env.builtins().get(name).is_some()
+    }
+
+    /// Resolves the `unquote` calls inside a quoted expression and nothing else.
+    fn visit_quoted(&mut self, expression: &Expression) {
+        let mut unquotes = Unquotes { resolver: self };
+        unquotes.visit_expression(expression);
+    }
+}
+
+fn is_call_to(call: &CallExpression, name: &str) -> bool {
+    matches!(call.function.as_ref(), Expression::Identifier(i) if i.value == name)
+}
+
+impl Visitor for Resolver<'_> {
+    fn visit_let_statement(&mut self, statement: &LetStatement) {
+        // The name already got its slot from `Declarations`.
+        self.visit_expression(&statement.value);
+    }
+
+    fn visit_catch_clause(&mut self, catch: &CatchClause) {
+        let layout = Rc::clone(&catch.layout);
+        let slot = layout.declare(&catch.parameter.value);
+        catch
+            .parameter
+            .resolution
+            .set(Some(Resolution { depth: 0, slot }));
+        let mut declarations = Declarations { layout: &layout };
+        declarations.visit_block_statement(&catch.body);
+        self.scopes.push(layout);
+        self.visit_block_statement(&catch.body);
+        self.scopes.pop();
+    }
+
+    fn visit_identifier(&mut self, identifier: &Identifier) {
+        let name = &identifier.value;
+        let resolution = self
+            .scopes
+            .iter()
+            .rev()
+            .enumerate()
+            .find_map(|(depth, layout)| {
+                Some(Resolution {
+                    depth,
+                    slot: layout.slot(name)?,
+                })
+            });
+        identifier.resolution.set(resolution);
+        if resolution.is_none() && !self.is_known(name) {
+            self.errors.push(ResolveError::UndefinedVariable(
+                name.clone(),
+                identifier.token.position,
+            ));
+        }
+    }
+
+    fn visit_function_literal(&mut self, function: &FunctionLiteral) {
+        let layout = Rc::clone(&function.layout);
+        for parameter in function.parameters.iter() {
+            let slot = layout.declare(&parameter.value);
+            parameter
+                .resolution
+                .set(Some(Resolution { depth: 0, slot }));
+        }
+        let mut declarations = Declarations { layout: &layout };
+        declarations.visit_block_statement(&function.body);
+        self.scopes.push(layout);
+        self.visit_block_statement(&function.body);
+        self.scopes.pop();
+    }
+
+    /// Macros are expanded before resolution; one that's left over isn't evaluated.
+    fn visit_macro_literal(&mut self, _macro: &MacroLiteral) {}
+
+    fn visit_call_expression(&mut self, call: &CallExpression) {
+        if is_call_to(call, "quote") {
+            call.arguments.iter().for_each(|a| self.visit_quoted(a));
+            return;
+        }
+        ast::visit_call_expression(self, call);
+    }
+}
+
+struct Unquotes<'r, 'a> {
+    resolver: &'r mut Resolver<'a>,
+}
+
+impl Visitor for Unquotes<'_, '_> {
+    fn visit_call_expression(&mut self, call: &CallExpression) {
+        if is_call_to(call, "unquote") {
+            call.arguments
+                .iter()
+                .for_each(|a| self.resolver.visit_expression(a));
+            return;
+        }
+        ast::visit_call_expression(self, call);
+    }
+}
@@ -0,0 +1,438 @@
+use crate::{
+    builtins::Builtins,
+    evaluator::{DebugFrame, Debugger, EvaluationError, StackFrame, StackTrace},
+    object::{Environment, Function, Scopes},
+    token::Position,
+    tracer::Tracer,
+};
+use std::{
+    cell::{Cell, Ref, RefCell, RefMut},
+    fmt::Debug,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+pub const DEFAULT_MAX_DEPTH: usize = 1000;
+
+/// Native stack an evaluation may use before calls fail, which keeps deep
+/// recursion from overflowing a 2 MiB thread (Rust's default for spawned threads).
+pub const DEFAULT_MAX_STACK: usize = 1024 * 1024;
+
+/// The wall clock is only consulted every this many steps.
+const TIMEOUT_CHECK_INTERVAL: u64 = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    pub max_steps: Option<u64>,
+    pub max_depth: Option<usize>,
+    pub max_stack: Option<usize>,
+    /// Approximate bytes the values in use may take: the ones bound in any
+    /// scope, globals included, and the one being created.
+    pub max_allocated: Option<usize>,
+    pub timeout: Option<Duration>,
+}
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits {
+            max_steps: None,
+            max_depth: Some(DEFAULT_MAX_DEPTH),
+            max_stack: Some(DEFAULT_MAX_STACK),
+            max_allocated: None,
+            timeout: None,
+        }
+    }
+}
+
+impl Limits {
+    /// No limits at all; only use this for trusted scripts on a large stack.
+    pub fn unlimited() -> Limits {
+        Limits {
+            max_steps: None,
+            max_depth: None,
+            max_stack: None,
+            max_allocated: None,
+            timeout: None,
+        }
+    }
+}
+
+/// Lets another thread stop a running evaluation. The evaluator checks it
+/// before every statement and function call.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Clears a previous cancellation so the interpreter can run again.
+    pub fn reset(&self) {
+        self.cancelled.store(false, Ordering::Relaxed);
+    }
+}
+
+/// State shared by every environment of one interpreter.
+#[derive(Debug, Default)]
+pub struct Runtime {
+    builtins: RefCell<Builtins>,
+    limits: Cell<Limits>,
+    steps: Cell<u64>,
+    depth: Cell<usize>,
+    /// Runs in progress, counting ones re-entered from builtins or hooks.
+    runs: Cell<usize>,
+    started: Cell<Option<Instant>>,
+    stack_base: Cell<Option<usize>>,
+    cancellation: CancellationToken,
+    allocated: Cell<usize>,
+    unwound: RefCell<Vec<StackFrame>>,
+    hooks: RefCell<Hooks>,
+    scopes: Scopes,
+}
+
+/// The attached debugger and tracer. Frames are only kept while a debugger
+/// is attached.
+#[derive(Default)]
+struct Hooks {
+    debugger: Option<Rc<dyn Debugger>>,
+    frames: Vec<DebugFrame>,
+    /// Frames pushed so far, to number the next one.
+    calls: usize,
+    tracer: Option<Rc<dyn Tracer>>,
+    /// Whether the error propagating now has been traced, so each error is
+    /// traced once, at the innermost statement it fails.
+    error_traced: bool,
+}
+
+impl Debug for Hooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Hooks")
+            .field("debugger", &self.debugger.is_some())
+            .field("frames", &self.frames)
+            .field("tracer", &self.tracer.is_some())
+            .finish()
+    }
+}
+
+impl Hooks {
+    fn next_call(&mut self) -> usize {
+        self.calls += 1;
+        self.calls
+    }
+}
+
+/// Approximates the stack pointer with the address of a local.
+#[inline(never)]
+fn stack_address() -> usize {
+    let marker = 0u8;
+    std::hint::black_box(&marker) as *const u8 as usize
+}
+
+impl Runtime {
+    pub fn new() -> Runtime {
+        Runtime::default()
+    }
+
+    pub fn builtins(&self) -> Ref<'_, Builtins> {
+        self.builtins.borrow()
+    }
+
+    pub(crate) fn scopes(&self) -> &Scopes {
+        &self.scopes
+    }
+
+    pub fn builtins_mut(&self) -> RefMut<'_, Builtins> {
+        self.builtins.borrow_mut()
+    }
+
+    pub fn limits(&self) -> Limits {
+        self.limits.get()
+    }
+
+    pub fn set_limits(&self, limits: Limits) {
+        self.limits.set(limits);
+    }
+
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancellation.clone()
+    }
+
+    /// Number of nodes evaluated since the current run began.
+    pub fn steps(&self) -> u64 {
+        self.steps.get()
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth.get()
+    }
+
+    /// Approximate bytes taken by the values bound in every live scope.
+    pub fn allocated_bytes(&self) -> usize {
+        self.allocated.set(self.scopes.live_bytes());
+        self.allocated.get()
+    }
+
+    /// Starts a run that lasts until the returned guard is dropped. The step
+    /// counter and the clock are reset only for the outermost run, so code
+    /// re-entered from a builtin counts against the run that called it.
+    pub(crate) fn begin(self: &Rc<Runtime>) -> RunGuard {
+        let runs = self.runs.get();
+        if runs == 0 {
+            self.steps.set(0);
+            self.unwound.borrow_mut().clear();
+            self.hooks.borrow_mut().frames.clear();
+            self.started.set(Some(Instant::now()));
+            self.stack_base.set(Some(stack_address()));
+        }
+        self.runs.set(runs + 1);
+        RunGuard {
+            runtime: Rc::clone(self),
+        }
+    }
+
+    pub(crate) fn step(&self) -> Result<(), EvaluationError> {
+        let steps = self.steps.get() + 1;
+        self.steps.set(steps);
+        let limits = self.limits.get();
+        if let Some(max_steps) = limits.max_steps {
+            if steps > max_steps {
+                return Err(EvaluationError::StepLimitExceeded(max_steps));
+            }
+        }
+        if steps.is_multiple_of(TIMEOUT_CHECK_INTERVAL) {
+            self.check_timeout()?;
+        }
+        Ok(())
+    }
+
+    /// Called for each call an error propagates out of, innermost first.
+    pub(crate) fn record_frame(&self, frame: StackFrame) {
+        self.unwound.borrow_mut().push(frame);
+    }
+
+    /// Takes the stack trace of the error that ended the last run.
+    pub fn take_stack_trace(&self) -> StackTrace {
+        let mut frames = std::mem::take(&mut *self.unwound.borrow_mut());
+        frames.reverse();
+        StackTrace { frames }
+    }
+
+    /// Sets aside the frames of an error while a `finally` block runs.
+    pub(crate) fn take_unwound(&self) -> Vec<StackFrame> {
+        std::mem::take(&mut *self.unwound.borrow_mut())
+    }
+
+    pub(crate) fn restore_unwound(&self, frames: Vec<StackFrame>) {
+        *self.unwound.borrow_mut() = frames;
+    }
+
+    pub(crate) fn allocate(&self, bytes: usize) -> Result<(), EvaluationError> {
+        self.reserve(bytes)?;
+        self.allocated
+            .set(self.allocated.get().saturating_add(bytes));
+        if let Some(tracer) = self.tracer() {
+            tracer.allocate(bytes);
+        }
+        Ok(())
+    }
+
+    /// Fails if allocating `bytes` more would exceed the limit, so large
+    /// values can be refused before they are built.
+    ///
+    /// The count only grows between allocations, as values are dropped
+    /// without telling the runtime; it is an upper bound on the bytes in
+    /// use. Once it reaches the limit, the bytes actually in use are
+    /// measured and the count starts again from there.
+    pub(crate) fn reserve(&self, bytes: usize) -> Result<(), EvaluationError> {
+        if let Some(max_allocated) = self.limits.get().max_allocated {
+            if self.allocated.get().saturating_add(bytes) > max_allocated
+                && self.allocated_bytes().saturating_add(bytes) > max_allocated
+            {
+                return Err(EvaluationError::OutOfMemory(max_allocated));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn set_debugger(&self, debugger: Option<Rc<dyn Debugger>>) {
+        let mut hooks = self.hooks.borrow_mut();
+        hooks.debugger = debugger;
+        hooks.frames.clear();
+    }
+
+    pub fn set_tracer(&self, tracer: Option<Rc<dyn Tracer>>) {
+        self.hooks.borrow_mut().tracer = tracer;
+    }
+
+    pub(crate) fn tracer(&self) -> Option<Rc<dyn Tracer>> {
+        self.hooks.borrow().tracer.clone()
+    }
+
+    /// Traces `error` unless a statement inside the one at `position` already did.
+    pub(crate) fn trace_error(
+        &self,
+        tracer: &dyn Tracer,
+        error: &EvaluationError,
+        position: Position,
+    ) {
+        let mut hooks = self.hooks.borrow_mut();
+        if !hooks.error_traced {
+            hooks.error_traced = true;
+            drop(hooks);
+            tracer.error(error, position);
+        }
+    }
+
+    /// Forgets the traced error once a statement starts or the error is caught.
+    pub(crate) fn clear_traced_error(&self) {
+        self.hooks.borrow_mut().error_traced = false;
+    }
+
+    /// Sets aside whether the propagating error was traced while a `finally` block runs.
+    pub(crate) fn take_traced_error(&self) -> bool {
+        std::mem::take(&mut self.hooks.borrow_mut().error_traced)
+    }
+
+    pub(crate) fn restore_traced_error(&self, traced: bool) {
+        self.hooks.borrow_mut().error_traced = traced;
+    }
+
+    /// Hands the debugger, if one is attached, the statement at `position`
+    /// that is about to run in `env`.
+    pub(crate) fn before_statement(
+        &self,
+        position: Position,
+        env: &Environment,
+    ) -> Result<(), EvaluationError> {
+        let mut hooks = self.hooks.borrow_mut();
+        let Some(debugger) = hooks.debugger.clone() else {
+            return Ok(());
+        };
+        match hooks.frames.last_mut() {
+            Some(frame) => {
+                frame.position = position;
+                frame.env = env.clone();
+            }
+            None => {
+                let call = hooks.next_call();
+                hooks.frames.push(DebugFrame {
+                    call,
+                    function: String::from("<program>"),
+                    position,
+                    env: env.clone(),
+                })
+            }
+        }
+        // The debugger may evaluate code of its own, which needs the hooks.
+        let frames = hooks.frames.clone();
+        drop(hooks);
+        debugger.before_statement(&frames)
+    }
+
+    /// Shows a call of `function` to the debugger until the returned guard is dropped.
+    pub(crate) fn enter_debug_frame(
+        self: &Rc<Runtime>,
+        function: &Function,
+        env: &Environment,
+    ) -> Option<DebugFrameGuard> {
+        let mut hooks = self.hooks.borrow_mut();
+        hooks.debugger.as_ref()?;
+        let call = hooks.next_call();
+        hooks.frames.push(DebugFrame {
+            call,
+            function: function.display_name().to_string(),
+            position: function.body.token.position,
+            env: env.clone(),
+        });
+        Some(DebugFrameGuard {
+            runtime: Rc::clone(self),
+        })
+    }
+
+    pub(crate) fn check_cancelled(&self) -> Result<(), EvaluationError> {
+        if self.cancellation.is_cancelled() {
+            return Err(EvaluationError::Cancelled);
+        }
+        Ok(())
+    }
+
+    fn check_timeout(&self) -> Result<(), EvaluationError> {
+        if let (Some(timeout), Some(started)) = (self.limits.get().timeout, self.started.get()) {
+            if started.elapsed() > timeout {
+                return Err(EvaluationError::Timeout(timeout));
+            }
+        }
+        Ok(())
+    }
+
+    /// Accounts for a function call until the returned guard is dropped.
+    pub(crate) fn enter_call(self: &Rc<Runtime>) -> Result<CallGuard, EvaluationError> {
+        let depth = self.depth.get() + 1;
+        let limits = self.limits.get();
+        if let Some(max_depth) = limits.max_depth {
+            if depth > max_depth {
+                return Err(EvaluationError::RecursionLimitExceeded(max_depth));
+            }
+        }
+        if let (Some(max_stack), Some(base)) = (limits.max_stack, self.stack_base.get()) {
+            if base.abs_diff(stack_address()) > max_stack {
+                return Err(EvaluationError::RecursionLimitExceeded(depth - 1));
+            }
+        }
+        self.check_cancelled()?;
+        self.check_timeout()?;
+        self.depth.set(depth);
+        Ok(CallGuard {
+            runtime: Rc::clone(self),
+        })
+    }
+}
+
+pub(crate) struct RunGuard {
+    runtime: Rc<Runtime>,
+}
+
+impl Drop for RunGuard {
+    fn drop(&mut self) {
+        let runs = self.runtime.runs.get() - 1;
+        self.runtime.runs.set(runs);
+        if runs == 0 {
+            self.runtime.scopes.collect();
+        }
+    }
+}
+
+pub(crate) struct CallGuard {
+    runtime: Rc<Runtime>,
+}
+
+impl Drop for CallGuard {
+    fn drop(&mut self) {
+        self.runtime.depth.set(self.runtime.depth.get() - 1);
+    }
+}
+
+pub(crate) struct DebugFrameGuard {
+    runtime: Rc<Runtime>,
+}
+
+impl Drop for DebugFrameGuard {
+    fn drop(&mut self) {
+        self.runtime.hooks.borrow_mut().frames.pop();
+    }
+}
@@ -1,7 +1,37 @@
+use std::fmt::Display;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+/// A `//` comment. The lexer skips comments but keeps them for tools like the formatter.
 #[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comment {
+    pub text: String,
+    pub position: Position,
+}
+
+#[derive(Debug, Clone, Eq)]
 pub struct Token {
     pub r#type: TokenType,
     pub literal: String,
+    pub position: Position,
+}
+
+/// Tokens are compared by type and literal only, so hand-built tokens match
+/// lexed ones regardless of where they appeared.
+impl PartialEq for Token {
+    fn eq(&self, other: &Self) -> bool {
+        self.r#type == other.r#type && self.literal == other.literal
+    }
 }
 
 impl Token {
@@ -9,6 +39,25 @@ impl Token {
         Token {
             r#type: token_type,
             literal: String::from(literal),
+            position: Position::default(),
+        }
+    }
+}
+
+/// A token whose literal is a slice of the lexer's input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenRef<'a> {
+    pub r#type: TokenType,
+    pub literal: &'a str,
+    pub position: Position,
+}
+
+impl TokenRef<'_> {
+    pub fn to_token(&self) -> Token {
+        Token {
+            r#type: self.r#type.clone(),
+            literal: String::from(self.literal),
+            position: self.position,
         }
     }
 }
@@ -45,4 +94,9 @@ pub enum TokenType {
     Else,
     Return,
     String,
+    Try,
+    Catch,
+    Finally,
+    Throw,
+    Macro,
 }
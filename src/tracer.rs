@@ -0,0 +1,181 @@
+//! Callbacks for watching an evaluation, and tracers that log what they see.
+use crate::{
+    ast::Statement,
+    evaluator::{inspect, summarize_arguments, EvaluationError},
+    object::{BuiltIn, Function, Object},
+    token::Position,
+};
+use serde_json::{json, Value};
+use std::{
+    cell::{Cell, RefCell},
+    io::Write,
+    slice,
+};
+
+/// Told about each step of an evaluation. Attach one with
+/// [`Interpreter::set_tracer`](crate::interpreter::Interpreter::set_tracer);
+/// every callback does nothing unless it is overridden.
+pub trait Tracer {
+    fn enter_statement(&self, _statement: &Statement) {}
+
+    fn leave_statement(&self, _statement: &Statement) {}
+
+    fn enter_function(&self, _function: &Function, _args: &[Object]) {}
+
+    /// `result` is `None` when the call ends with an error.
+    fn leave_function(&self, _function: &Function, _result: Option<&Object>) {}
+
+    fn call_builtin(&self, _builtin: &BuiltIn, _args: &[Object]) {}
+
+    /// Called for every object created, with its approximate size in bytes.
+    fn allocate(&self, _bytes: usize) {}
+
+    /// Called once for each error, caught or not, with the innermost
+    /// statement it failed.
+    fn error(&self, _error: &EvaluationError, _position: Position) {}
+}
+
+/// Prints every call and builtin invocation, indented by how deeply it is
+/// nested, with what each call returned and where errors happened.
+///
+/// Traces are best effort: output that can't be written is dropped.
+pub struct CallTracer {
+    output: RefCell<Box<dyn Write>>,
+    depth: Cell<usize>,
+}
+
+impl CallTracer {
+    pub fn new<W: Write + 'static>(output: W) -> CallTracer {
+        CallTracer {
+            output: RefCell::new(Box::new(output)),
+            depth: Cell::new(0),
+        }
+    }
+
+    fn line(&self, text: &str) {
+        let indent = "  ".repeat(self.depth.get());
+        let _ = writeln!(self.output.borrow_mut(), "{}{}", indent, text);
+    }
+}
+
+impl Tracer for CallTracer {
+    fn enter_function(&self, function: &Function, args: &[Object]) {
+        self.line(&format!(
+            "-> {}({})",
+            function.display_name(),
+            summarize_arguments(args)
+        ));
+        self.depth.set(self.depth.get() + 1);
+    }
+
+    fn leave_function(&self, function: &Function, result: Option<&Object>) {
+        self.depth.set(self.depth.get().saturating_sub(1));
+        match result {
+            Some(value) => self.line(&format!(
+                "<- {} = {}",
+                function.display_name(),
+                summarize_arguments(slice::from_ref(value))
+            )),
+            None => self.line(&format!("<- {} failed", function.display_name())),
+        }
+    }
+
+    fn call_builtin(&self, builtin: &BuiltIn, args: &[Object]) {
+        self.line(&format!(
+            "builtin {}({})",
+            builtin.name,
+            summarize_arguments(args)
+        ));
+    }
+
+    fn error(&self, error: &EvaluationError, position: Position) {
+        let error = error.to_string().replace('\n', " ");
+        self.line(&format!("!! {}: {}", position, error));
+    }
+}
+
+/// Writes one JSON object per line for every statement, call and error, for
+/// other tools to consume.
+///
+/// Each object has an `event` field named after the [`Tracer`] callback.
+/// Statements and errors carry their `line` and `column`; calls carry the
+/// `function`, its `arguments` or `result` (`null` for a call that failed),
+/// and the call `depth`.
+pub struct JsonTracer {
+    output: RefCell<Box<dyn Write>>,
+    depth: Cell<usize>,
+}
+
+impl JsonTracer {
+    pub fn new<W: Write + 'static>(output: W) -> JsonTracer {
+        JsonTracer {
+            output: RefCell::new(Box::new(output)),
+            depth: Cell::new(0),
+        }
+    }
+
+    fn emit(&self, event: Value) {
+        let _ = writeln!(self.output.borrow_mut(), "{}", event);
+    }
+}
+
+impl Tracer for JsonTracer {
+    fn enter_statement(&self, statement: &Statement) {
+        let position = statement.position();
+        self.emit(json!({
+            "event": "enter_statement",
+            "line": position.line,
+            "column": position.column,
+        }));
+    }
+
+    fn leave_statement(&self, statement: &Statement) {
+        let position = statement.position();
+        self.emit(json!({
+            "event": "leave_statement",
+            "line": position.line,
+            "column": position.column,
+        }));
+    }
+
+    fn enter_function(&self, function: &Function, args: &[Object]) {
+        self.depth.set(self.depth.get() + 1);
+        let arguments: Vec<String> = args.iter().map(inspect).collect();
+        self.emit(json!({
+            "event": "enter_function",
+            "function": function.display_name(),
+            "arguments": arguments,
+            "depth": self.depth.get(),
+        }));
+    }
+
+    fn leave_function(&self, function: &Function, result: Option<&Object>) {
+        self.emit(json!({
+            "event": "leave_function",
+            "function": function.display_name(),
+            "result": result.map(inspect),
+            "depth": self.depth.get(),
+        }));
+        self.depth.set(self.depth.get().saturating_sub(1));
+    }
+
+    fn call_builtin(&self, builtin: &BuiltIn, args: &[Object]) {
+        let arguments: Vec<String> = args.iter().map(inspect).collect();
+        self.emit(json!({
+            "event": "call_builtin",
+            "function": builtin.name,
+            "arguments": arguments,
+            "depth": self.depth.get(),
+        }));
+    }
+
+    fn error(&self, error: &EvaluationError, position: Position) {
+        self.emit(json!({
+            "event": "error",
+            "kind": error.kind(),
+            "message": error.to_string(),
+            "line": position.line,
+            "column": position.column,
+        }));
+    }
+}
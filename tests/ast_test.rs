@@ -1,9 +1,21 @@
 #[cfg(test)]
 mod tests {
     use rost_interpreter::{
-        ast::{Expression, Identifier, LetStatement, Program, Statement},
-        token::{Token, TokenType},
+        ast::{
+            self, Expression, Fold, Identifier, IntegerLiteral, LetStatement, Program, Statement,
+            Visitor,
+        },
+        lexer::Lexer,
+        parser::Parser,
+        token::{Position, Token, TokenType},
     };
+    use std::cell::Cell;
+
+    fn test_parse_program(input: &str) -> Program {
+        let lexer = Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        parser.parse_program().unwrap()
+    }
 
     #[test]
     fn test_display_trait() {
@@ -12,23 +24,117 @@ mod tests {
                 token: Token {
                     r#type: TokenType::Let,
                     literal: String::from("let"),
+                    position: Position::default(),
                 },
                 name: Identifier {
                     token: Token {
                         r#type: TokenType::Ident,
                         literal: String::from("myVar"),
+                        position: Position::default(),
                     },
                     value: String::from("myVar"),
+                    resolution: Cell::default(),
                 },
                 value: Expression::Identifier(Identifier {
                     token: Token {
                         r#type: TokenType::Ident,
                         literal: String::from("anotherVar"),
+                        position: Position::default(),
                     },
                     value: String::from("anotherVar"),
+                    resolution: Cell::default(),
                 }),
             })],
         };
         assert_eq!(format!("{}", program), format!("let myVar = anotherVar;"));
     }
+
+    #[test]
+    fn test_visitor() {
+        struct IdentifierCollector {
+            identifiers: Vec<String>,
+            calls: usize,
+        }
+
+        impl Visitor for IdentifierCollector {
+            fn visit_identifier(&mut self, identifier: &Identifier) {
+                self.identifiers.push(identifier.value.clone());
+            }
+
+            fn visit_call_expression(&mut self, call: &ast::CallExpression) {
+                self.calls += 1;
+                ast::visit_call_expression(self, call);
+            }
+        }
+
+        let program = test_parse_program(
+            "let add = fn(a, b) { a + b }; try { add(x, [y][0]) } catch (e) { puts(e) }",
+        );
+        let mut collector = IdentifierCollector {
+            identifiers: Vec::new(),
+            calls: 0,
+        };
+        collector.visit_program(&program);
+        assert_eq!(
+            collector.identifiers,
+            vec!["add", "a", "b", "a", "b", "add", "x", "y", "e", "puts", "e"]
+        );
+        assert_eq!(collector.calls, 2);
+    }
+
+    #[test]
+    fn test_fold() {
+        struct Doubler;
+
+        impl Fold for Doubler {
+            fn fold_integer_literal(&mut self, integer: IntegerLiteral) -> IntegerLiteral {
+                let value = integer.value * 2;
+                let mut token = integer.token;
+                token.literal = value.to_string();
+                IntegerLiteral { token, value }
+            }
+
+            fn fold_identifier(&mut self, identifier: Identifier) -> Identifier {
+                let value = identifier.value.to_uppercase();
+                let mut token = identifier.token;
+                token.literal = value.clone();
+                Identifier {
+                    token,
+                    value,
+                    resolution: identifier.resolution,
+                }
+            }
+        }
+
+        let program =
+            test_parse_program("let x = fn(y) { if (y > 1) { [y, 2][0] } else { -3 } }; x[4 + 5];");
+        let folded = Doubler.fold_program(program);
+        assert_eq!(
+            folded.to_string(),
+            "let X = Here: fn(Y) if (Y > 2) ([Y, 4][0]) else (-6);(X[(8 + 10)])"
+        );
+    }
+
+    #[test]
+    fn test_modify() {
+        let program = test_parse_program("let a = 1 + 2; [3, -4];");
+        let modified = ast::modify(
+            test_parse_program("let a = 1 + 2; [3, -4];"),
+            &mut |expression| match expression {
+                Expression::IntegerLiteral(mut i) => {
+                    i.value += 10;
+                    i.token.literal = i.value.to_string();
+                    Ok::<Expression, String>(Expression::IntegerLiteral(i))
+                }
+                e => Ok(e),
+            },
+        )
+        .unwrap();
+        assert_eq!(modified.to_string(), "let a = (11 + 12);[13, (-14)]");
+        let failed = ast::modify(program, &mut |expression| match expression {
+            Expression::PrefixExpression(_) => Err(String::from("no prefixes")),
+            e => Ok(e),
+        });
+        assert_eq!(failed.unwrap_err(), "no prefixes");
+    }
 }
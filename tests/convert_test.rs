@@ -0,0 +1,117 @@
+#[cfg(test)]
+mod tests {
+    use rost_interpreter::{
+        convert::{FromObject, IntoObject},
+        evaluator::{eval, EvaluationError},
+        lexer::Lexer,
+        object::{Environment, Object},
+        parser::Parser,
+    };
+    use std::collections::HashMap;
+
+    fn test_eval_in(input: &str, env: &mut Environment) -> Result<Object, EvaluationError> {
+        let lexer = Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_program().unwrap();
+        eval(&program, env)
+    }
+
+    #[test]
+    fn test_round_trip_conversions() {
+        assert_eq!(i64::from_object(&42.into_object()), Ok(42));
+        assert_eq!(bool::from_object(&true.into_object()), Ok(true));
+        assert_eq!(
+            String::from_object(&"monkey".into_object()),
+            Ok(String::from("monkey"))
+        );
+        assert_eq!(
+            Vec::<i64>::from_object(&vec![1, 2, 3].into_object()),
+            Ok(vec![1, 2, 3])
+        );
+        let mut map = HashMap::new();
+        map.insert(String::from("a"), vec![true]);
+        assert_eq!(
+            HashMap::<String, Vec<bool>>::from_object(&map.clone().into_object()),
+            Ok(map)
+        );
+        assert_eq!(Option::<i64>::from_object(&Object::Null), Ok(None));
+        assert_eq!(Option::<i64>::from_object(&5.into_object()), Ok(Some(5)));
+        assert_eq!(None::<i64>.into_object(), Object::Null);
+        assert_eq!(
+            Vec::<i64>::from_object(&vec![Object::Null].into_object()),
+            Err(EvaluationError::BuiltInError(String::from(
+                "Wrong kind of argument.\nExpected: INTEGER\nGot: NULL"
+            )))
+        );
+    }
+
+    #[test]
+    fn test_register_native_function() {
+        let mut env = Environment::new(None);
+        env.builtins_mut()
+            .register_fn("repeat", |s: String, n: i64| -> Result<String, String> {
+                if n < 0 {
+                    return Err(format!("Cannot repeat {} times", n));
+                }
+                Ok(s.repeat(n as usize))
+            });
+        env.builtins_mut()
+            .register_fn("sum", |values: Vec<i64>| values.iter().sum::<i64>());
+        env.builtins_mut().register_fn(
+            "lookup",
+            |map: HashMap<String, i64>, key: String, default: Option<i64>| {
+                map.get(&key).copied().or(default)
+            },
+        );
+
+        assert_eq!(
+            test_eval_in(r#"repeat("ab", 3)"#, &mut env)
+                .unwrap()
+                .to_string(),
+            "ababab"
+        );
+        assert_eq!(
+            test_eval_in(r#"repeat("ab", -1)"#, &mut env),
+            Err(EvaluationError::BuiltInError(String::from(
+                "Cannot repeat -1 times"
+            )))
+        );
+        assert_eq!(
+            test_eval_in(r#"repeat(1, 1)"#, &mut env),
+            Err(EvaluationError::BuiltInError(String::from(
+                "Wrong kind of argument.\nExpected: STRING\nGot: INTEGER"
+            )))
+        );
+        assert_eq!(
+            test_eval_in(r#"repeat("ab")"#, &mut env),
+            Err(EvaluationError::BuiltInError(String::from(
+                "Wrong number of arguments.\nExpected: 2\nGot: 1"
+            )))
+        );
+        assert_eq!(
+            test_eval_in("sum([1, 2, 3, 4])", &mut env),
+            Ok(10.into_object())
+        );
+
+        let mut scores = HashMap::new();
+        scores.insert(String::from("alice"), 3);
+        env.set("scores", scores.into_object());
+        assert_eq!(
+            test_eval_in(r#"lookup(scores, "alice", 0)"#, &mut env),
+            Ok(3.into_object())
+        );
+        env.set("none", Object::Null);
+        assert_eq!(
+            test_eval_in(r#"lookup(scores, "bob", none)"#, &mut env),
+            Ok(Object::Null)
+        );
+        assert_eq!(
+            test_eval_in(r#"lookup(scores, "bob", 7)"#, &mut env),
+            Ok(7.into_object())
+        );
+        assert_eq!(
+            test_eval_in(r#"scores["alice"]"#, &mut env),
+            Ok(3.into_object())
+        );
+    }
+}
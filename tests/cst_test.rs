@@ -0,0 +1,247 @@
+#[cfg(test)]
+mod tests {
+    use rost_interpreter::{
+        cst::{parse, GreenNode, GreenToken, LineIndex, SyntaxKind, SyntaxNode},
+        lexer::Lexer,
+        parser::Parser,
+        token::{Position, TokenType},
+    };
+
+    #[test]
+    fn test_round_trip() {
+        let tests = vec![
+            "",
+            "   \n\t ",
+            "// only a comment",
+            "let x = 5;  // five\r\n\nlet y = x * (2 + 3)",
+            "let add = fn(a, b) { a + b };\nadd(1, 2)[0];",
+            "if (x < 1) { \"one\" } else { [1, 2, 3] }",
+            "try { throw \"e\" } catch (e) { e } finally { puts(e) }",
+            "let s = \"unterminated",
+            "let = ;;) } ] @ # 0xZ",
+            "fn(1, , x { let",
+            "let ünïcödé = \"ß\"; // ✓",
+            "1 + 2\0 everything after a NUL",
+            "macro(x) { quote(unquote(x)) }",
+        ];
+        for input in tests {
+            let parse = parse(input);
+            let root = parse.syntax();
+            assert_eq!(root.to_string(), input);
+            assert_eq!(root.text_range(), 0..input.len());
+            let text: String = root.tokens().map(|t| t.to_string()).collect();
+            assert_eq!(text, input);
+        }
+    }
+
+    #[test]
+    fn test_to_program_matches_parser() {
+        let tests = vec![
+            "let x = 5; let y = -x * (2 + 3) / 4 != 1 == !true;",
+            "let add = fn(a, b) { return a + b; }; add(1, add(2, 3));",
+            "let f = fn() { fn(x) { x } }; f()(1); (f)()(2);",
+            "[1, 2 * 2, \"three\"][1 + 1]; a[0][1];",
+            "if (a < b) { a } else { b }; if (x > 1) { x };",
+            "try { throw \"e\"; } catch (e) { puts(e) } finally { 1 }",
+            "let m = macro(a, b) { quote(unquote(b) - unquote(a)) }; m(1, 2)",
+            "let s = \"text\" // comment\n  ;\n\tlen(s)",
+            "0xFF + 0o10 + 0b11 + 1_000",
+            include_str!("../benches/workloads/fib.mky"),
+            include_str!("../benches/workloads/strings.mky"),
+            include_str!("../benches/workloads/arrays.mky"),
+            include_str!("../benches/workloads/closures.mky"),
+            include_str!("../benches/workloads/calls.mky"),
+        ];
+        for input in tests {
+            let expected = Parser::new(Lexer::new(input)).parse_program().unwrap();
+            let program = parse(input).to_program().unwrap();
+            assert_eq!(format!("{:?}", program), format!("{:?}", expected));
+        }
+    }
+
+    #[test]
+    fn test_parser_reports_the_first_syntax_error() {
+        let tests = vec![
+            ("fn ( { ) {", "FunctionLiteral"),
+            ("let = 5;", "StatementError"),
+            ("if (x { 1 }", "IfExpression"),
+            ("add(1, 2", "CallArguments"),
+            ("try { 1 }", "TryExpression"),
+            ("fn() { 1", "FunctionLiteral"),
+            ("99999999999999999999", "Lexer"),
+        ];
+        for (input, variant) in tests {
+            let error = parse(input).errors()[0].to_string();
+            match Parser::new(Lexer::new(input)).parse_program() {
+                Err(e) => {
+                    assert!(
+                        format!("{:?}", e).starts_with(variant),
+                        "{}: {:?}",
+                        input,
+                        e
+                    );
+                    assert!(e.to_string().ends_with(&error), "{}: {}", input, e);
+                }
+                Ok(program) => panic!("Expected an error for {}\nGot: {}", input, program),
+            }
+        }
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        struct Test {
+            input: String,
+            expected: Vec<String>,
+        }
+        let tests = vec![
+            Test {
+                input: String::from("let = 5;"),
+                expected: vec![String::from(
+                    "line 1, column 5: expected an identifier, found `=`",
+                )],
+            },
+            Test {
+                input: String::from("let x = 1;\nx + ;\n)"),
+                expected: vec![
+                    String::from("line 2, column 5: expected an expression, found `;`"),
+                    String::from("line 3, column 1: expected an expression, found `)`"),
+                ],
+            },
+            Test {
+                input: String::from("add(1, 2"),
+                expected: vec![String::from(
+                    "line 1, column 9: expected `)`, found end of input",
+                )],
+            },
+            Test {
+                input: String::from("fn(x, 1) { x }"),
+                expected: vec![String::from(
+                    "line 1, column 7: expected a parameter name, found `1`",
+                )],
+            },
+            Test {
+                input: String::from("try { 1 }"),
+                expected: vec![String::from(
+                    "line 1, column 10: expected `catch` or `finally`, found end of input",
+                )],
+            },
+            Test {
+                input: String::from("let x = 0xFG + @;"),
+                expected: vec![
+                    String::from(
+                        "line 1, column 9: invalid digit `G` in hexadecimal literal `0xFG`",
+                    ),
+                    String::from("line 1, column 16: unexpected character `@`"),
+                ],
+            },
+            Test {
+                input: String::from("if (x) { 1 "),
+                expected: vec![String::from(
+                    "line 1, column 12: expected `}`, found end of input",
+                )],
+            },
+        ];
+        for test in tests {
+            let parse = parse(&test.input);
+            let errors: Vec<String> = parse.errors().iter().map(|e| e.to_string()).collect();
+            assert_eq!(errors, test.expected, "{}", test.input);
+            assert_eq!(
+                parse.to_program().unwrap_err(),
+                parse.errors()[0].clone(),
+                "{}",
+                test.input
+            );
+            assert_eq!(parse.syntax().to_string(), test.input);
+        }
+    }
+
+    #[test]
+    fn test_line_index() {
+        let text = "let é = 1; let b = é;\nb";
+        let index = LineIndex::new(text);
+        let positions = [
+            (0, Position { line: 1, column: 1 }),
+            (4, Position { line: 1, column: 5 }),
+            (
+                20,
+                Position {
+                    line: 1,
+                    column: 20,
+                },
+            ),
+            (7, Position { line: 1, column: 7 }),
+            (24, Position { line: 2, column: 1 }),
+            (
+                15,
+                Position {
+                    line: 1,
+                    column: 15,
+                },
+            ),
+        ];
+        for (offset, position) in positions {
+            assert_eq!(index.position(offset), position, "offset {}", offset);
+        }
+        for (offset, position) in positions.into_iter().rev() {
+            assert_eq!(index.offset(position), offset, "{:?}", position);
+        }
+    }
+
+    #[test]
+    fn test_tree() {
+        let parse = parse("// x\nlet x = a + 1; ?");
+        assert_eq!(
+            parse.syntax().debug_tree(),
+            r#"Program@0..21
+  Comment@0..4 "// x"
+  Whitespace@4..5 "\n"
+  LetStatement@5..19
+    Token(Let)@5..8 "let"
+    Whitespace@8..9 " "
+    Identifier@9..10
+      Token(Ident)@9..10 "x"
+    Whitespace@10..11 " "
+    Token(Assign)@11..12 "="
+    Whitespace@12..13 " "
+    InfixExpression@13..18
+      Identifier@13..14
+        Token(Ident)@13..14 "a"
+      Whitespace@14..15 " "
+      Token(Plus)@15..16 "+"
+      Whitespace@16..17 " "
+      IntegerLiteral@17..18
+        Token(Int)@17..18 "1"
+    Token(Semicolon)@18..19 ";"
+  Whitespace@19..20 " "
+  ExpressionStatement@20..21
+    Error@20..21
+      Token(Illegal)@20..21 "?"
+"#
+        );
+    }
+
+    #[test]
+    fn test_rename_keeps_formatting() {
+        let input = "let count = 1; // start\nlet f = fn(n) {\n  count + n // add\n};\n";
+        let root = parse(input).syntax();
+        let mut green = root.green().clone();
+        loop {
+            let root = SyntaxNode::new_root(green.clone());
+            let Some(token) = root
+                .tokens()
+                .find(|t| t.kind() == SyntaxKind::Token(TokenType::Ident) && t.text() == "count")
+            else {
+                break;
+            };
+            green = token.replace_with(GreenToken::new(token.kind(), "total"));
+        }
+        assert_eq!(
+            green.to_string(),
+            "let total = 1; // start\nlet f = fn(n) {\n  total + n // add\n};\n"
+        );
+        assert_eq!(root.to_string(), input);
+        let statement = root.children().nth(1).unwrap();
+        let replaced = statement.replace_with(GreenNode::new(SyntaxKind::LetStatement, vec![]));
+        assert_eq!(replaced.to_string(), "let count = 1; // start\n\n");
+    }
+}
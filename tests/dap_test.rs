@@ -0,0 +1,310 @@
+#[cfg(test)]
+mod tests {
+    use rost_interpreter::{dap::serve, protocol::read_message};
+    use serde_json::{json, Value};
+    use std::{
+        cell::RefCell,
+        env, fs,
+        io::{self, Cursor, Write},
+        path::PathBuf,
+        rc::Rc,
+        sync::mpsc,
+    };
+
+    const SOURCE: &str = "let add = fn(a, b) {
+  let sum = a + b;
+  sum
+};
+let x = add(1, 2);
+puts(x);
+";
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn script(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("rost_dap_{}_{}.mky", name, std::process::id()));
+        fs::write(&path, SOURCE).unwrap();
+        path
+    }
+
+    /// Sends every request up front and returns all the adapter wrote.
+    fn session(requests: Vec<(&str, Value)>) -> Vec<Value> {
+        let (sender, receiver) = mpsc::channel();
+        for (seq, (command, arguments)) in requests.into_iter().enumerate() {
+            sender
+                .send(json!({
+                    "seq": seq + 1,
+                    "type": "request",
+                    "command": command,
+                    "arguments": arguments,
+                }))
+                .unwrap();
+        }
+        drop(sender);
+        let output = SharedBuffer::default();
+        serve(receiver, output.clone()).unwrap();
+        let mut reader = Cursor::new(output.0.borrow().clone());
+        let mut messages = Vec::new();
+        while let Some(message) = read_message(&mut reader).unwrap() {
+            messages.push(message);
+        }
+        messages
+    }
+
+    fn launch(path: &PathBuf, breakpoints: &[u64]) -> Vec<(&'static str, Value)> {
+        let breakpoints: Vec<Value> = breakpoints.iter().map(|l| json!({"line": l})).collect();
+        vec![
+            ("initialize", json!({"adapterID": "rost"})),
+            ("launch", json!({"program": path})),
+            (
+                "setBreakpoints",
+                json!({"source": {"path": path}, "breakpoints": breakpoints}),
+            ),
+            ("configurationDone", json!({})),
+        ]
+    }
+
+    /// Summarizes each message as its command or event name.
+    fn kinds(messages: &[Value]) -> Vec<String> {
+        messages
+            .iter()
+            .map(|m| match m["type"].as_str().unwrap() {
+                "event" => format!("event {}", m["event"].as_str().unwrap()),
+                _ => m["command"].as_str().unwrap().to_string(),
+            })
+            .collect()
+    }
+
+    fn response(messages: &[Value], request_seq: usize) -> &Value {
+        messages
+            .iter()
+            .find(|m| m["type"] == "response" && m["request_seq"] == request_seq)
+            .unwrap()
+    }
+
+    fn frames(response: &Value) -> Vec<(String, u64)> {
+        response["body"]["stackFrames"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|f| {
+                (
+                    f["name"].as_str().unwrap().to_string(),
+                    f["line"].as_u64().unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    fn variables(response: &Value) -> Vec<(String, String)> {
+        response["body"]["variables"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| {
+                (
+                    v["name"].as_str().unwrap().to_string(),
+                    v["value"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_breakpoints_and_stepping() {
+        let path = script("stepping");
+        let mut requests = launch(&path, &[5]);
+        requests.extend([
+            ("stackTrace", json!({"threadId": 1})),
+            ("stepIn", json!({"threadId": 1})),
+            ("stackTrace", json!({"threadId": 1})),
+            ("scopes", json!({"frameId": 1})),
+            ("variables", json!({"variablesReference": 1})),
+            ("next", json!({"threadId": 1})),
+            ("scopes", json!({"frameId": 1})),
+            ("variables", json!({"variablesReference": 1})),
+            ("stepOut", json!({"threadId": 1})),
+            ("stackTrace", json!({"threadId": 1})),
+            ("continue", json!({"threadId": 1})),
+            ("disconnect", json!({})),
+        ]);
+        let messages = session(requests);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            kinds(&messages),
+            vec![
+                "initialize",
+                "event initialized",
+                "launch",
+                "setBreakpoints",
+                "configurationDone",
+                "event stopped",
+                "stackTrace",
+                "stepIn",
+                "event stopped",
+                "stackTrace",
+                "scopes",
+                "variables",
+                "next",
+                "event stopped",
+                "scopes",
+                "variables",
+                "stepOut",
+                "event stopped",
+                "stackTrace",
+                "continue",
+                "event output",
+                "event exited",
+                "event terminated",
+                "disconnect",
+            ]
+        );
+        assert!(messages.iter().all(|m| m["success"] != false));
+        let reasons: Vec<&Value> = messages
+            .iter()
+            .filter(|m| m["event"] == "stopped")
+            .map(|m| &m["body"]["reason"])
+            .collect();
+        assert_eq!(reasons, vec!["breakpoint", "step", "step", "step"]);
+        assert_eq!(
+            frames(response(&messages, 5)),
+            vec![(String::from("<program>"), 5)]
+        );
+        assert_eq!(
+            frames(response(&messages, 7)),
+            vec![(String::from("add"), 2), (String::from("<program>"), 5)]
+        );
+        let scopes: Vec<&Value> = response(&messages, 8)["body"]["scopes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| &s["name"])
+            .collect();
+        assert_eq!(scopes, vec!["Locals", "Globals"]);
+        assert_eq!(
+            variables(response(&messages, 9)),
+            vec![
+                (String::from("a"), String::from("1")),
+                (String::from("b"), String::from("2")),
+            ]
+        );
+        assert_eq!(
+            variables(response(&messages, 12)),
+            vec![
+                (String::from("a"), String::from("1")),
+                (String::from("b"), String::from("2")),
+                (String::from("sum"), String::from("3")),
+            ]
+        );
+        assert_eq!(
+            frames(response(&messages, 14)),
+            vec![(String::from("<program>"), 6)]
+        );
+        let output = messages.iter().find(|m| m["event"] == "output").unwrap();
+        assert_eq!(output["body"]["output"], "3\n");
+        let exited = messages.iter().find(|m| m["event"] == "exited").unwrap();
+        assert_eq!(exited["body"]["exitCode"], 0);
+    }
+
+    #[test]
+    fn test_breakpoint_stops_in_every_call() {
+        let path = env::temp_dir().join(format!("rost_dap_calls_{}.mky", std::process::id()));
+        fs::write(
+            &path,
+            "let f = fn(x) { x * 2 };\nputs([f(1), f(2), f(3)]);\n",
+        )
+        .unwrap();
+        let mut requests = launch(&path, &[1]);
+        requests.push(("continue", json!({"threadId": 1})));
+        for _ in 0..3 {
+            requests.extend([
+                ("stackTrace", json!({"threadId": 1})),
+                ("scopes", json!({"frameId": 1})),
+                ("variables", json!({"variablesReference": 1})),
+                ("continue", json!({"threadId": 1})),
+            ]);
+        }
+        requests.push(("disconnect", json!({})));
+        let messages = session(requests);
+        fs::remove_file(&path).unwrap();
+
+        let stops = messages.iter().filter(|m| m["event"] == "stopped").count();
+        assert_eq!(stops, 4);
+        for (call, seq) in [6, 10, 14].into_iter().enumerate() {
+            assert_eq!(
+                frames(response(&messages, seq)),
+                vec![(String::from("f"), 1), (String::from("<program>"), 2)]
+            );
+            assert_eq!(
+                variables(response(&messages, seq + 2)),
+                vec![(String::from("x"), (call + 1).to_string())]
+            );
+        }
+        let output = messages.iter().find(|m| m["event"] == "output").unwrap();
+        assert_eq!(output["body"]["output"], "[2, 4, 6]\n");
+    }
+
+    #[test]
+    fn test_nested_variables() {
+        let path = env::temp_dir().join(format!("rost_dap_nested_{}.mky", std::process::id()));
+        fs::write(&path, "let xs = [1, \"two\", [3]];\nputs(len(xs));\n").unwrap();
+        let mut requests = launch(&path, &[2]);
+        requests.extend([
+            ("scopes", json!({"frameId": 0})),
+            ("variables", json!({"variablesReference": 1})),
+            ("variables", json!({"variablesReference": 2})),
+            ("variables", json!({"variablesReference": 9})),
+            ("disconnect", json!({})),
+        ]);
+        let messages = session(requests);
+        fs::remove_file(&path).unwrap();
+
+        let scopes = &response(&messages, 5)["body"]["scopes"];
+        assert_eq!(
+            scopes,
+            &json!([{"name": "Globals", "variablesReference": 1, "expensive": false}])
+        );
+        let xs = &response(&messages, 6)["body"]["variables"][0];
+        assert_eq!(xs["name"], "xs");
+        assert_eq!(xs["type"], "ARRAY");
+        assert_eq!(xs["variablesReference"], 2);
+        assert_eq!(
+            variables(response(&messages, 7)),
+            vec![
+                (String::from("0"), String::from("1")),
+                (String::from("1"), String::from("\"two\"")),
+                (String::from("2"), String::from("[3]")),
+            ]
+        );
+        assert_eq!(response(&messages, 8)["success"], false);
+        assert!(!messages.iter().any(|m| m["event"] == "terminated"));
+    }
+
+    #[test]
+    fn test_errors_are_reported() {
+        let path = env::temp_dir().join(format!("rost_dap_error_{}.mky", std::process::id()));
+        fs::write(&path, "let x = 1;\nx + true;\n").unwrap();
+        let mut requests = launch(&path, &[]);
+        requests.insert(1, ("stackTrace", json!({"threadId": 1})));
+        let messages = session(requests);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(response(&messages, 2)["success"], false);
+        let output = messages.iter().find(|m| m["event"] == "output").unwrap();
+        assert_eq!(output["body"]["category"], "stderr");
+        let exited = messages.iter().find(|m| m["event"] == "exited").unwrap();
+        assert_eq!(exited["body"]["exitCode"], 1);
+    }
+}
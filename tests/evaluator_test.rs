@@ -1,10 +1,12 @@
 #[cfg(test)]
 mod tests {
     use rost_interpreter::{
+        builtins::Arity,
         evaluator::{eval, EvaluationError},
         lexer::Lexer,
-        object::{Environment, Object},
+        object::{Environment, Integer, Object},
         parser::Parser,
+        runtime::Limits,
     };
     use std::cell::RefCell;
 
@@ -13,10 +15,17 @@ mod tests {
         let mut parser = Parser::new(lexer);
         let program = parser.parse_program().unwrap();
         let env = RefCell::new(Environment::new(None));
-        let result = eval(program, &mut env.borrow_mut());
+        let result = eval(&program, &mut env.borrow_mut());
         result
     }
 
+    fn test_eval_in(input: &str, env: &mut Environment) -> Result<Object, EvaluationError> {
+        let lexer = Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_program().unwrap();
+        eval(&program, env)
+    }
+
     fn test_integer_object(object: Object, expected: i64) {
         let result = match object {
             Object::Integer(i) => i,
@@ -52,6 +61,10 @@ mod tests {
                 input: String::from("10"),
                 expected: 10,
             },
+            Test {
+                input: String::from("0xFF + 0o10 + 0b11 + 1_000"),
+                expected: 1266,
+            },
             Test {
                 input: String::from("-5"),
                 expected: -5,
@@ -378,13 +391,49 @@ mod tests {
             Test {
                 input: String::from("foobar"),
                 expected_message: EvaluationError::IdentError(String::from(
-                    "Identifier not found: foobar",
+                    "foobar at line 1, column 1",
                 )),
             },
             Test {
                 input: String::from(r#""Hello" - "World""#),
                 expected_message: EvaluationError::OperatorError(String::from("STRING - STRING")),
             },
+            Test {
+                input: String::from("puts(1 / 0)"),
+                expected_message: EvaluationError::ArithmeticError(String::from(
+                    "division by zero",
+                )),
+            },
+            Test {
+                input: String::from("9223372036854775807 + 1"),
+                expected_message: EvaluationError::ArithmeticError(String::from(
+                    "integer overflow: 9223372036854775807 + 1",
+                )),
+            },
+            Test {
+                input: String::from("-9223372036854775807 - 2"),
+                expected_message: EvaluationError::ArithmeticError(String::from(
+                    "integer overflow: -9223372036854775807 - 2",
+                )),
+            },
+            Test {
+                input: String::from("4611686018427387904 * 2"),
+                expected_message: EvaluationError::ArithmeticError(String::from(
+                    "integer overflow: 4611686018427387904 * 2",
+                )),
+            },
+            Test {
+                input: String::from("(-9223372036854775807 - 1) / -1"),
+                expected_message: EvaluationError::ArithmeticError(String::from(
+                    "integer overflow: -9223372036854775808 / -1",
+                )),
+            },
+            Test {
+                input: String::from("-(-9223372036854775807 - 1)"),
+                expected_message: EvaluationError::ArithmeticError(String::from(
+                    "integer overflow: -(-9223372036854775808)",
+                )),
+            },
         ];
         for test in tests {
             let evaluated = test_eval(&test.input);
@@ -482,6 +531,13 @@ mod tests {
         test_integer_object(test_eval(input).unwrap(), 4)
     }
 
+    #[test]
+    fn test_recursive_functions() {
+        let input: &str =
+            "let fib = fn(n) { if (n < 2) { n } else { fib(n - 1) + fib(n - 2) } }; fib(10);";
+        test_integer_object(test_eval(input).unwrap(), 55)
+    }
+
     #[test]
     fn test_string_literal() {
         let input = r#""Hello World!""#;
@@ -527,7 +583,17 @@ mod tests {
             Test {
                 input: String::from(r#"len(1)"#),
                 expected: Err(EvaluationError::BuiltInError(String::from(
-                    "Wrong kind of argument.\nExpected: String\nGot: INTEGER",
+                    "Wrong kind of argument.\nExpected: String or Array\nGot: INTEGER",
+                ))),
+            },
+            Test {
+                input: String::from(r#"len([1, "two"])"#),
+                expected: Ok(2),
+            },
+            Test {
+                input: String::from(r#"len(true)"#),
+                expected: Err(EvaluationError::BuiltInError(String::from(
+                    "Wrong kind of argument.\nExpected: String or Array\nGot: BOOLEAN",
                 ))),
             },
             Test {
@@ -547,4 +613,302 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_builtin_registry() {
+        let mut env = Environment::new(None);
+        env.builtins_mut()
+            .register("double", Arity::Exact(1), |args| match &args[0] {
+                Object::Integer(i) => Ok(Object::Integer(Integer { value: i.value * 2 })),
+                e => Err(EvaluationError::BuiltInError(format!(
+                    "Wrong kind of argument.\nExpected: Integer\nGot: {}",
+                    e
+                ))),
+            });
+        test_integer_object(test_eval_in("double(21)", &mut env).unwrap(), 42);
+        test_integer_object(
+            test_eval_in("let f = fn(x) { double(x) + 1 }; f(2)", &mut env).unwrap(),
+            5,
+        );
+        assert_eq!(
+            test_eval_in("double(1, 2)", &mut env),
+            Err(EvaluationError::BuiltInError(String::from(
+                "Wrong number of arguments.\nExpected: 1\nGot: 2",
+            )))
+        );
+
+        env.builtins_mut().register("len", Arity::Any, |args| {
+            Ok(Object::Integer(Integer {
+                value: args.len() as i64,
+            }))
+        });
+        test_integer_object(test_eval_in("len(1, 2, 3)", &mut env).unwrap(), 3);
+
+        assert!(env.builtins_mut().remove("double").is_some());
+        assert_eq!(
+            test_eval_in("double(1)", &mut env),
+            Err(EvaluationError::IdentError(String::from(
+                "double at line 1, column 1"
+            )))
+        );
+    }
+
+    #[test]
+    fn test_array_literals() {
+        let evaluated = test_eval("[1, 2 * 2, 3 + 3]").unwrap();
+        let array = match evaluated {
+            Object::Array(a) => a,
+            e => panic!("Expected Object::Array\nGot: {:?}", e),
+        };
+        assert_eq!(array.elements.len(), 3);
+        test_integer_object(array.elements[0].clone(), 1);
+        test_integer_object(array.elements[1].clone(), 4);
+        test_integer_object(array.elements[2].clone(), 6);
+    }
+
+    #[test]
+    fn test_array_index_expressions() {
+        struct Test {
+            input: String,
+            expected: Option<i64>,
+        }
+        let tests = vec![
+            Test {
+                input: String::from("[1, 2, 3][0]"),
+                expected: Some(1),
+            },
+            Test {
+                input: String::from("[1, 2, 3][1 + 1]"),
+                expected: Some(3),
+            },
+            Test {
+                input: String::from(
+                    "let myArray = [1, 2, 3]; myArray[0] + myArray[1] + myArray[2];",
+                ),
+                expected: Some(6),
+            },
+            Test {
+                input: String::from("let myArray = [1, 2, 3]; let i = myArray[0]; myArray[i]"),
+                expected: Some(2),
+            },
+            Test {
+                input: String::from("[1, 2, 3][3]"),
+                expected: None,
+            },
+            Test {
+                input: String::from("[1, 2, 3][-1]"),
+                expected: None,
+            },
+        ];
+        for test in tests {
+            let evaluated = test_eval(&test.input).unwrap();
+            match test.expected {
+                Some(i) => test_integer_object(evaluated, i),
+                None => test_null_object(evaluated),
+            }
+        }
+    }
+
+    #[test]
+    fn test_array_builtin_functions() {
+        struct Test {
+            input: String,
+            expected: String,
+        }
+        let tests = vec![
+            Test {
+                input: String::from("len([1, 2, 3])"),
+                expected: String::from("3"),
+            },
+            Test {
+                input: String::from("first([1, 2, 3])"),
+                expected: String::from("1"),
+            },
+            Test {
+                input: String::from("first([])"),
+                expected: String::from("null"),
+            },
+            Test {
+                input: String::from("last([1, 2, 3])"),
+                expected: String::from("3"),
+            },
+            Test {
+                input: String::from("rest([1, 2, 3])"),
+                expected: String::from("[2, 3]"),
+            },
+            Test {
+                input: String::from("rest([])"),
+                expected: String::from("null"),
+            },
+            Test {
+                input: String::from("push([], 1)"),
+                expected: String::from("[1]"),
+            },
+        ];
+        for test in tests {
+            let evaluated = test_eval(&test.input).unwrap();
+            assert_eq!(evaluated.to_string(), test.expected);
+        }
+        assert_eq!(
+            test_eval("first(1)"),
+            Err(EvaluationError::BuiltInError(String::from(
+                "Wrong kind of argument.\nExpected: ARRAY\nGot: INTEGER"
+            )))
+        );
+    }
+
+    #[test]
+    fn test_try_catch() {
+        struct Test {
+            input: String,
+            expected: String,
+        }
+        let tests = vec![
+            Test {
+                input: String::from("try { 1 } catch (e) { 2 }"),
+                expected: String::from("1"),
+            },
+            Test {
+                input: String::from("try { throw \"boom\"; } catch (e) { e }"),
+                expected: String::from("Error: boom"),
+            },
+            Test {
+                input: String::from(
+                    "try { throw error(\"KeyError\", \"missing\"); } catch (e) { e[\"kind\"] + \"/\" + e[\"message\"] }",
+                ),
+                expected: String::from("KeyError/missing"),
+            },
+            Test {
+                input: String::from("try { 1 + true } catch (e) { e[\"kind\"] }"),
+                expected: String::from("TypeError"),
+            },
+            Test {
+                input: String::from("try { 1 + true } catch (e) { e[\"message\"] }"),
+                expected: String::from("Type mismatch: INTEGER + BOOLEAN"),
+            },
+            Test {
+                input: String::from("try { len(1, 2) } catch (e) { e[\"kind\"] }"),
+                expected: String::from("BuiltInError"),
+            },
+            Test {
+                input: String::from(
+                    "let fail = fn(x) { throw x; }; try { fail(1) } catch (e) { len(e[\"stack\"]) }",
+                ),
+                expected: String::from("1"),
+            },
+            Test {
+                input: String::from("let x = 0; let y = try { 5 } finally { let x = 1; }; [x, y]"),
+                expected: String::from("[1, 5]"),
+            },
+            Test {
+                input: String::from(
+                    "let f = fn() { try { return 1; } finally { return 2; } }; f()",
+                ),
+                expected: String::from("2"),
+            },
+            Test {
+                input: String::from(
+                    "try { try { throw \"inner\"; } catch (e) { throw \"outer\"; } } catch (e) { e[\"message\"] }",
+                ),
+                expected: String::from("outer"),
+            },
+            Test {
+                input: String::from("let e = 1; try { throw \"x\" } catch (e) { 0 }; e"),
+                expected: String::from("1"),
+            },
+            Test {
+                input: String::from(
+                    "let f = fn() { let e = 2; try { throw \"x\" } catch (e) { let n = 3; e }; e }; f()",
+                ),
+                expected: String::from("2"),
+            },
+        ];
+        for test in tests {
+            let evaluated = test_eval(&test.input).unwrap();
+            assert_eq!(evaluated.to_string(), test.expected);
+        }
+    }
+
+    #[test]
+    fn test_uncaught_errors() {
+        assert_eq!(
+            test_eval("try { 1 } finally { throw \"cleanup\"; }")
+                .unwrap_err()
+                .to_string(),
+            "Error: cleanup"
+        );
+        assert_eq!(
+            test_eval("throw error(\"KeyError\", \"missing\");")
+                .unwrap_err()
+                .kind(),
+            "KeyError"
+        );
+        let mut env = Environment::new(None);
+        env.runtime().set_limits(Limits {
+            max_steps: Some(50),
+            ..Limits::default()
+        });
+        let result = test_eval_in(
+            "let loop = fn(n) { loop(n + 1) }; try { loop(0) } catch (e) { 0 }",
+            &mut env,
+        );
+        assert_eq!(result, Err(EvaluationError::StepLimitExceeded(50)));
+    }
+
+    #[test]
+    fn test_quote_unquote() {
+        struct Test {
+            input: String,
+            expected: String,
+        }
+        let tests = vec![
+            Test {
+                input: String::from("quote(5)"),
+                expected: String::from("5"),
+            },
+            Test {
+                input: String::from("quote(foobar + barfoo)"),
+                expected: String::from("(foobar + barfoo)"),
+            },
+            Test {
+                input: String::from("quote(unquote(4 + 4))"),
+                expected: String::from("8"),
+            },
+            Test {
+                input: String::from("quote(8 + unquote(4 + 4))"),
+                expected: String::from("(8 + 8)"),
+            },
+            Test {
+                input: String::from("let foobar = 8; quote(unquote(foobar) + barfoo)"),
+                expected: String::from("(8 + barfoo)"),
+            },
+            Test {
+                input: String::from("quote(unquote(true == false))"),
+                expected: String::from("false"),
+            },
+            Test {
+                input: String::from("quote(unquote(quote(4 + 4)))"),
+                expected: String::from("(4 + 4)"),
+            },
+            Test {
+                input: String::from(
+                    "let quotedInfix = quote(4 + 4); quote(unquote(4 + 4) + unquote(quotedInfix))",
+                ),
+                expected: String::from("(8 + (4 + 4))"),
+            },
+        ];
+        for test in tests {
+            let quote = match test_eval(&test.input).unwrap() {
+                Object::Quote(q) => q,
+                e => panic!("Expected Object::Quote\nGot: {:?}", e),
+            };
+            assert_eq!(quote.node.to_string(), test.expected);
+        }
+        assert_eq!(
+            test_eval("quote(unquote(fn(x) { x }))"),
+            Err(EvaluationError::MacroError(String::from(
+                "Cannot unquote a value of type FUNCTION"
+            )))
+        );
+    }
 }
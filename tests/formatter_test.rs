@@ -0,0 +1,142 @@
+#[cfg(test)]
+mod tests {
+    use rost_interpreter::formatter::{format_source, format_source_with, FormatOptions};
+
+    #[test]
+    fn test_format_source() {
+        struct Test {
+            input: String,
+            expected: String,
+        }
+        let tests = vec![
+            Test {
+                input: String::from("let x=5;let y = 10 ;x+y"),
+                expected: String::from("let x = 5;\nlet y = 10;\nx + y;\n"),
+            },
+            Test {
+                input: String::from("let add=fn(a,b){a+b};let nothing = fn() {\n};"),
+                expected: String::from(
+                    "let add = fn(a, b) {\n    a + b;\n};\nlet nothing = fn() {};\n",
+                ),
+            },
+            Test {
+                input: String::from("if (x>1) { return x*(2+3) } else { -x }"),
+                expected: String::from(
+                    "if (x > 1) {\n    return x * (2 + 3);\n} else {\n    -x;\n}\n",
+                ),
+            },
+            Test {
+                input: String::from("(1 + 2) * 3 - -x; a - (b - c); (a - b) - c; !(a == b); f(x)[0];"),
+                expected: String::from(
+                    "(1 + 2) * 3 - -x;\na - (b - c);\na - b - c;\n!(a == b);\nf(x)[0];\n",
+                ),
+            },
+            Test {
+                input: String::from(
+                    "try { throw error(\"E\", \"m\"); } catch (e) { puts(e[\"message\"]) } finally { 1 }",
+                ),
+                expected: String::from(
+                    "try {\n    throw error(\"E\", \"m\");\n} catch (e) {\n    puts(e[\"message\"]);\n} finally {\n    1;\n}\n",
+                ),
+            },
+            Test {
+                input: String::from("if (a) { b }; (1 + 2) * 3;"),
+                expected: String::from("if (a) {\n    b;\n};\n(1 + 2) * 3;\n"),
+            },
+            Test {
+                input: String::from("let mask=0xFF_FF*0b1;let mode = 0o755+1_000"),
+                expected: String::from("let mask = 0xFF_FF * 0b1;\nlet mode = 0o755 + 1_000;\n"),
+            },
+            Test {
+                input: String::from("let a = 1;\n\n\n\nlet b = 2;\nlet c = 3;"),
+                expected: String::from("let a = 1;\n\nlet b = 2;\nlet c = 3;\n"),
+            },
+        ];
+        for test in tests {
+            assert_eq!(format_source(&test.input).unwrap(), test.expected);
+        }
+    }
+
+    #[test]
+    fn test_format_wraps_long_lists() {
+        let input = "let long = someFunction(aVeryLongArgumentName, anotherVeryLongArgumentName, [1, 2]);\nmap(arr, fn(x) { x * 2 });";
+        let expected = "\
+let long = someFunction(
+    aVeryLongArgumentName,
+    anotherVeryLongArgumentName,
+    [1, 2]
+);
+map(arr, fn(x) {
+    x * 2;
+});
+";
+        assert_eq!(format_source(input).unwrap(), expected);
+        let narrow = FormatOptions {
+            max_width: 10,
+            indent_width: 2,
+        };
+        assert_eq!(
+            format_source_with("[100, 200, 300];", narrow).unwrap(),
+            "[\n  100,\n  200,\n  300\n];\n"
+        );
+    }
+
+    #[test]
+    fn test_format_deep_nesting() {
+        let depth = 40;
+        let input = format!("{}1{};", "[".repeat(depth), ", 2]".repeat(depth));
+        let formatted = format_source(&input).unwrap();
+        assert!(formatted.starts_with("[\n    [\n        [\n"));
+        assert_eq!(format_source(&formatted).unwrap(), formatted);
+    }
+
+    #[test]
+    fn test_format_preserves_comments() {
+        let input = "\
+// leading
+let x = 5; // trailing
+let f = fn(x) { // first
+  x
+
+  // dangling
+};
+puts(add(1, // inside
+  2));
+// the end
+";
+        let expected = "\
+// leading
+let x = 5; // trailing
+let f = fn(x) {
+    // first
+    x;
+
+    // dangling
+};
+// inside
+puts(add(1, 2));
+// the end
+";
+        assert_eq!(format_source(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_format_is_idempotent() {
+        let inputs = vec![
+            "let a=[1,2,3,[4,5],fn(x){x}];// c\n\n\nlet b=a[0]",
+            "let f = fn(a) { if (a) { // x\n 1 } else { 2 } // y\n }; f(fn() { // z\n})",
+            "puts(someFunction(aVeryLongArgumentName, anotherVeryLongArgumentName), fn(x) { x });",
+            "try { 1 } finally { 2 }\n-1;\nmacro(a) { quote(unquote(a) + 1) };",
+        ];
+        for input in inputs {
+            let once = format_source(input).unwrap();
+            assert_eq!(format_source(&once).unwrap(), once);
+        }
+    }
+
+    #[test]
+    fn test_format_rejects_invalid_source() {
+        assert!(format_source("let = 5;").is_err());
+        assert!(format_source("let x = 0x;").is_err());
+    }
+}
@@ -0,0 +1,360 @@
+#[cfg(test)]
+mod tests {
+    use rost_interpreter::{
+        builtins::Arity,
+        convert::IntoObject,
+        evaluator::{self, DebugFrame, Debugger, EvaluationError},
+        interpreter::{Interpreter, InterpreterError},
+        lexer::Lexer,
+        object::Object,
+        parser::Parser,
+    };
+    use std::{
+        cell::{Cell, RefCell},
+        collections::HashMap,
+        env, fs,
+        io::{self, Write},
+        rc::Rc,
+    };
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedBuffer {
+        fn contents(&self) -> String {
+            String::from_utf8(self.0.borrow().clone()).unwrap()
+        }
+    }
+
+    #[test]
+    fn test_call_registered_callback() {
+        let mut interpreter = Interpreter::new();
+        let handlers: Rc<RefCell<HashMap<String, Object>>> = Rc::default();
+        let registered = Rc::clone(&handlers);
+        interpreter.register_fn("on", move |event: String, handler: Object| {
+            registered.borrow_mut().insert(event, handler);
+        });
+        interpreter
+            .eval_str(r#"let greeting = "Hello, "; on("greet", fn(name) { greeting + name });"#)
+            .unwrap();
+
+        let handler = handlers.borrow()["greet"].clone();
+        assert_eq!(
+            interpreter.call(&handler, ("Monkey",)),
+            Ok("Hello, Monkey".into_object())
+        );
+        assert_eq!(
+            interpreter.call(&handler, ()),
+            Err(EvaluationError::ArgumentError(String::from(
+                "Wrong number of arguments.\nExpected: 1\nGot: 0"
+            )))
+        );
+    }
+
+    #[test]
+    fn test_call_global() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .eval_str("let add = fn(a, b) { a + b };")
+            .unwrap();
+        assert_eq!(interpreter.call_global("add", (2, 3)), Ok(5.into_object()));
+        assert_eq!(
+            interpreter.call_global("len", (vec![1, 2, 3],)),
+            Ok(3.into_object())
+        );
+        assert_eq!(
+            interpreter.call_global("push", vec![vec![1].into_object(), 2.into_object()]),
+            Ok(vec![1, 2].into_object())
+        );
+        assert_eq!(
+            interpreter.call(&5.into_object(), ()),
+            Err(EvaluationError::Function(String::from(
+                "Expected an Object::Function or Object::BuiltIn\nGot: 5"
+            )))
+        );
+        assert_eq!(
+            interpreter.call_global("missing", ()),
+            Err(EvaluationError::IdentError(String::from("missing")))
+        );
+    }
+
+    #[test]
+    fn test_eval_str_keeps_globals() {
+        let mut interpreter = Interpreter::new();
+        interpreter.eval_str("let x = 5;").unwrap();
+        assert_eq!(interpreter.eval_str("x * 2").unwrap(), 10.into_object());
+        assert_eq!(interpreter.get_global("x"), Some(5.into_object()));
+        assert_eq!(interpreter.get_global("y"), None);
+
+        interpreter.set_global("names", vec!["a", "b"]);
+        assert_eq!(interpreter.eval_str("len(names)").unwrap(), 2.into_object());
+
+        interpreter.environment_mut().set("three", 3.into_object());
+        assert_eq!(interpreter.eval_str("three + x").unwrap(), 8.into_object());
+
+        match interpreter.eval_str("let = 5;") {
+            Err(InterpreterError::Parser(_)) => {}
+            e => panic!("Expected InterpreterError::Parser\nGot: {:?}", e),
+        }
+        match interpreter.eval_str("x + true") {
+            Err(InterpreterError::Evaluation(EvaluationError::TypeError(t), _)) => {
+                assert_eq!(t, "INTEGER + BOOLEAN")
+            }
+            e => panic!("Expected InterpreterError::Evaluation\nGot: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_dropping_frees_closures() {
+        let marker = Rc::new(());
+        let mut interpreter = Interpreter::new();
+        let held = Rc::clone(&marker);
+        interpreter.register_fn("marker", move || Rc::strong_count(&held) as i64);
+        interpreter
+            .eval_str("let f = fn() { f() }; let m = marker;")
+            .unwrap();
+        drop(interpreter);
+        assert_eq!(Rc::strong_count(&marker), 1);
+    }
+
+    #[test]
+    fn test_calls_creating_closures_free_their_scopes() {
+        let mut interpreter = Interpreter::new();
+        interpreter.register_fn("marker", || 1);
+        // Every copy of the builtin the script holds shares its function.
+        let marker = interpreter.environment().builtins().get("marker").unwrap();
+        interpreter
+            .eval_str("let f = fn() { let b = marker; let g = fn() { b }; 1 };")
+            .unwrap();
+        let before = Rc::strong_count(&marker.func);
+        for _ in 0..100 {
+            interpreter.eval_str("f();").unwrap();
+        }
+        assert_eq!(Rc::strong_count(&marker.func), before);
+
+        interpreter
+            .eval_str("let repeat = fn(n) { if (n > 0) { f(); repeat(n - 1) } }; repeat(20);")
+            .unwrap();
+        assert_eq!(Rc::strong_count(&marker.func), before);
+
+        interpreter
+            .eval_str("let make = fn() { let b = marker; fn() { b } }; let kept = make();")
+            .unwrap();
+        assert_eq!(Rc::strong_count(&marker.func), before + 1);
+        interpreter.eval_str("let kept = 0;").unwrap();
+        assert_eq!(Rc::strong_count(&marker.func), before);
+    }
+
+    #[test]
+    fn test_eval_file() {
+        let path = env::temp_dir().join(format!("rost_eval_file_{}.mky", std::process::id()));
+        fs::write(&path, "let add = fn(a, b) {\n  a + b\n};\nadd(1, 2)\n").unwrap();
+        let mut interpreter = Interpreter::new();
+        let result = interpreter.eval_file(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(result.unwrap(), 3.into_object());
+
+        match interpreter.eval_file(&path) {
+            Err(InterpreterError::Io(_)) => {}
+            e => panic!("Expected InterpreterError::Io\nGot: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_builtin_registration_and_output() {
+        let mut interpreter = Interpreter::new();
+        let output = SharedBuffer::default();
+        interpreter.set_output(output.clone());
+        interpreter.register_builtin("answer", Arity::Exact(0), |_| Ok(42.into_object()));
+        interpreter
+            .eval_str(r#"puts("answer:", answer())"#)
+            .unwrap();
+        assert_eq!(output.contents(), "answer:\n42\n");
+
+        assert!(interpreter.remove_builtin("answer").is_some());
+        assert!(interpreter.eval_str("answer()").is_err());
+    }
+
+    #[test]
+    fn test_stack_trace() {
+        let mut interpreter = Interpreter::new();
+        let input = r#"let check = fn(x) {
+  x + true
+};
+let outer = fn(a, b) {
+  let r = check(a);
+  r
+};
+outer(1, "two");"#;
+        let (error, stack) = match interpreter.eval_str(input) {
+            Err(InterpreterError::Evaluation(e, stack)) => (e, stack),
+            e => panic!("Expected InterpreterError::Evaluation\nGot: {:?}", e),
+        };
+        assert_eq!(
+            error,
+            EvaluationError::TypeError(String::from("INTEGER + BOOLEAN"))
+        );
+        let frames: Vec<String> = stack.frames.iter().map(|f| f.to_string()).collect();
+        assert_eq!(
+            frames,
+            vec![
+                r#"line 8, column 1, in outer(1, "two")"#,
+                "line 5, column 11, in check(1)",
+            ]
+        );
+        assert_eq!(
+            interpreter.eval_str(input).unwrap_err().to_string(),
+            r#"Traceback (most recent call last):
+  line 8, column 1, in outer(1, "two")
+  line 5, column 11, in check(1)
+Type mismatch: INTEGER + BOOLEAN"#
+        );
+
+        match interpreter.eval_str("fn(x) { len(x) }(5)") {
+            Err(InterpreterError::Evaluation(_, stack)) => {
+                let frames: Vec<String> = stack.frames.iter().map(|f| f.to_string()).collect();
+                assert_eq!(
+                    frames,
+                    vec![
+                        "line 1, column 1, in <anonymous>(5)",
+                        "line 1, column 9, in len(5)"
+                    ]
+                );
+            }
+            e => panic!("Expected InterpreterError::Evaluation\nGot: {:?}", e),
+        }
+
+        match interpreter.eval_str("1 + true") {
+            Err(InterpreterError::Evaluation(_, stack)) => assert!(stack.is_empty()),
+            e => panic!("Expected InterpreterError::Evaluation\nGot: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_eval_str_expands_macros() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .eval_str(
+                "let unless = macro(condition, consequence, alternative) {
+                    quote(if (!(unquote(condition))) { unquote(consequence) } else { unquote(alternative) });
+                };",
+            )
+            .unwrap();
+        let result = interpreter
+            .eval_str("unless(10 > 5, \"not greater\", \"greater\")")
+            .unwrap();
+        assert_eq!(result.to_string(), "greater");
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        statements: RefCell<Vec<String>>,
+        stop_at_line: Option<usize>,
+    }
+
+    impl Debugger for Recorder {
+        fn before_statement(&self, frames: &[DebugFrame]) -> Result<(), EvaluationError> {
+            let frame = frames.last().unwrap();
+            let names: Vec<String> = frame
+                .env
+                .bindings()
+                .into_iter()
+                .map(|(name, value)| match value {
+                    Object::Function(_) => name,
+                    value => format!("{}={}", name, value),
+                })
+                .collect();
+            self.statements.borrow_mut().push(format!(
+                "{}:{} in {} [{}]",
+                frames.len(),
+                frame.position.line,
+                frame.function,
+                names.join(" ")
+            ));
+            if Some(frame.position.line) == self.stop_at_line {
+                return Err(EvaluationError::Cancelled);
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_debugger_sees_every_statement() {
+        let mut interpreter = Interpreter::new();
+        let recorder = Rc::new(Recorder::default());
+        interpreter.set_debugger(Some(recorder.clone()));
+        interpreter
+            .eval_str(
+                "let double = fn(n) {\n  let twice = n * 2;\n  twice\n};\nlet x = double(4);\nx",
+            )
+            .unwrap();
+        assert_eq!(
+            *recorder.statements.borrow(),
+            vec![
+                "1:1 in <program> []",
+                "1:5 in <program> [double]",
+                "2:2 in double [n=4]",
+                "2:3 in double [n=4 twice=8]",
+                "1:6 in <program> [double x=8]",
+            ]
+        );
+
+        let recorder = Rc::new(Recorder {
+            stop_at_line: Some(2),
+            ..Recorder::default()
+        });
+        interpreter.set_debugger(Some(recorder.clone()));
+        match interpreter.eval_str("let a = 1;\nlet b = 2;\nlet c = 3;") {
+            Err(InterpreterError::Evaluation(EvaluationError::Cancelled, _)) => {}
+            e => panic!("Expected a cancelled evaluation\nGot: {:?}", e),
+        }
+        assert_eq!(recorder.statements.borrow().len(), 2);
+
+        interpreter.set_debugger(None);
+        assert!(interpreter.eval_str("let a = 1;\nlet b = 2;").is_ok());
+        assert_eq!(recorder.statements.borrow().len(), 2);
+    }
+
+    /// Evaluates `f(1)` in the frame paused at line 3.
+    #[derive(Default)]
+    struct Watcher {
+        evaluating: Cell<bool>,
+        watched: RefCell<Vec<String>>,
+    }
+
+    impl Debugger for Watcher {
+        fn before_statement(&self, frames: &[DebugFrame]) -> Result<(), EvaluationError> {
+            let frame = frames.last().unwrap();
+            if frame.position.line != 3 || self.evaluating.replace(true) {
+                return Ok(());
+            }
+            let program = Parser::new(Lexer::new("f(1)")).parse_program().unwrap();
+            let result = evaluator::eval(&program, &mut frame.env.clone());
+            self.watched.borrow_mut().push(result.unwrap().to_string());
+            self.evaluating.set(false);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_debugger_can_evaluate_code() {
+        let mut interpreter = Interpreter::new();
+        let watcher = Rc::new(Watcher::default());
+        interpreter.set_debugger(Some(watcher.clone()));
+        let result = interpreter
+            .eval_str("let f = fn(n) { n * 10 };\nlet x = 1;\nlet y = f(x) + 1;\ny")
+            .unwrap();
+        assert_eq!(result.to_string(), "11");
+        assert_eq!(*watcher.watched.borrow(), vec!["10"]);
+    }
+}
@@ -1,9 +1,10 @@
 #[cfg(test)]
 mod tests {
     use rost_interpreter::{
-        lexer::Lexer,
-        token::{Token, TokenType},
+        lexer::{Lexer, LexerError},
+        token::{Comment, Position, Token, TokenRef, TokenType},
     };
+    use std::time::{Duration, Instant};
 
     #[test]
     fn test_lexer_symbols() {
@@ -12,62 +13,77 @@ mod tests {
             Token {
                 r#type: TokenType::Assign,
                 literal: String::from('='),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Plus,
                 literal: String::from('+'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::LParen,
                 literal: String::from('('),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::RParen,
                 literal: String::from(')'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::LBrace,
                 literal: String::from('{'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::RBrace,
                 literal: String::from('}'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Comma,
                 literal: String::from(','),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Semicolon,
                 literal: String::from(';'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Bang,
                 literal: String::from('!'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Minus,
                 literal: String::from('-'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Slash,
                 literal: String::from('/'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Asterisk,
                 literal: String::from('*'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::LT,
                 literal: String::from('<'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::GT,
                 literal: String::from('>'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::EOF,
                 literal: String::from(""),
+                position: Position::default(),
             },
         ];
         let mut lexer = Lexer::new(input);
@@ -106,330 +122,412 @@ if (5 < 10) {
             Token {
                 r#type: TokenType::Let,
                 literal: String::from("let"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Ident,
                 literal: String::from("five"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Assign,
                 literal: String::from('='),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Int,
                 literal: String::from("5"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Semicolon,
                 literal: String::from(';'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Let,
                 literal: String::from("let"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Ident,
                 literal: String::from("ten"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Assign,
                 literal: String::from('='),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Int,
                 literal: String::from("10"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Semicolon,
                 literal: String::from(';'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Let,
                 literal: String::from("let"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Ident,
                 literal: String::from("add"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Assign,
                 literal: String::from('='),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Function,
                 literal: String::from("fn"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::LParen,
                 literal: String::from('('),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Ident,
                 literal: String::from("x"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Comma,
                 literal: String::from(','),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Ident,
                 literal: String::from("y"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::RParen,
                 literal: String::from(')'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::LBrace,
                 literal: String::from('{'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Ident,
                 literal: String::from("x"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Plus,
                 literal: String::from('+'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Ident,
                 literal: String::from("y"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Semicolon,
                 literal: String::from(';'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::RBrace,
                 literal: String::from('}'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Semicolon,
                 literal: String::from(';'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Let,
                 literal: String::from("let"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Ident,
                 literal: String::from("result"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Assign,
                 literal: String::from('='),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Ident,
                 literal: String::from("add"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::LParen,
                 literal: String::from('('),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Ident,
                 literal: String::from("five"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Comma,
                 literal: String::from(','),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Ident,
                 literal: String::from("ten"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::RParen,
                 literal: String::from(')'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Semicolon,
                 literal: String::from(';'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Bang,
                 literal: String::from('!'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Minus,
                 literal: String::from('-'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Slash,
                 literal: String::from('/'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Asterisk,
                 literal: String::from('*'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Int,
                 literal: String::from("5"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Semicolon,
                 literal: String::from(';'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Int,
                 literal: String::from("5"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::LT,
                 literal: String::from('<'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Int,
                 literal: String::from("10"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::GT,
                 literal: String::from('>'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Int,
                 literal: String::from("5"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Semicolon,
                 literal: String::from(';'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::If,
                 literal: String::from("if"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::LParen,
                 literal: String::from('('),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Int,
                 literal: String::from("5"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::LT,
                 literal: String::from('<'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Int,
                 literal: String::from("10"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::RParen,
                 literal: String::from(')'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::LBrace,
                 literal: String::from('{'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Return,
                 literal: String::from("return"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::True,
                 literal: String::from("true"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Semicolon,
                 literal: String::from(';'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::RBrace,
                 literal: String::from('}'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Else,
                 literal: String::from("else"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::LBrace,
                 literal: String::from('{'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Return,
                 literal: String::from("return"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::False,
                 literal: String::from("false"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Semicolon,
                 literal: String::from(';'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::RBrace,
                 literal: String::from('}'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Int,
                 literal: String::from("10"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Eq,
                 literal: String::from("=="),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Int,
                 literal: String::from("10"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Semicolon,
                 literal: String::from(';'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Int,
                 literal: String::from("10"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::NotEq,
                 literal: String::from("!="),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Int,
                 literal: String::from("9"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Semicolon,
                 literal: String::from(';'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::String,
                 literal: String::from("foobar"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::String,
                 literal: String::from("foo bar"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::LBracket,
                 literal: String::from('['),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Int,
                 literal: String::from("1"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Comma,
                 literal: String::from(','),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Int,
                 literal: String::from("2"),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::RBracket,
                 literal: String::from(']'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::Semicolon,
                 literal: String::from(';'),
+                position: Position::default(),
             },
             Token {
                 r#type: TokenType::EOF,
                 literal: String::from(""),
+                position: Position::default(),
             },
         ];
         let mut lexer = Lexer::new(input);
@@ -439,4 +537,234 @@ if (5 < 10) {
             assert_eq!(tok, token);
         }
     }
+
+    #[test]
+    fn test_lexer_positions() {
+        let input = "let x = 5;\n  add(x,\n\tten);";
+        let expected = vec![
+            ("let", 1, 1),
+            ("x", 1, 5),
+            ("=", 1, 7),
+            ("5", 1, 9),
+            (";", 1, 10),
+            ("add", 2, 3),
+            ("(", 2, 6),
+            ("x", 2, 7),
+            (",", 2, 8),
+            ("ten", 3, 2),
+            (")", 3, 5),
+            (";", 3, 6),
+        ];
+        let mut lexer = Lexer::new(input);
+        for (literal, line, column) in expected {
+            let token = lexer.next_token();
+            assert_eq!(token.literal, literal);
+            assert_eq!(token.position, Position { line, column });
+        }
+        assert_eq!(lexer.next_token().r#type, TokenType::EOF);
+    }
+
+    #[test]
+    fn test_lexer_comments() {
+        let input = "// header\nlet x = 10 / 2; // half\n//\n";
+        let mut lexer = Lexer::new(input);
+        let literals: Vec<String> = std::iter::from_fn(|| {
+            let token = lexer.next_token();
+            (token.r#type != TokenType::EOF).then_some(token.literal)
+        })
+        .collect();
+        assert_eq!(literals, vec!["let", "x", "=", "10", "/", "2", ";"]);
+        assert_eq!(
+            lexer.comments(),
+            &[
+                Comment {
+                    text: String::from("// header"),
+                    position: Position { line: 1, column: 1 },
+                },
+                Comment {
+                    text: String::from("// half"),
+                    position: Position {
+                        line: 2,
+                        column: 17
+                    },
+                },
+                Comment {
+                    text: String::from("//"),
+                    position: Position { line: 3, column: 1 },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lexer_borrows_input() {
+        let input = String::from("let émoji = \"naïve\" != x;");
+        let mut lexer = Lexer::new(&input);
+        let expected = vec![
+            (TokenType::Let, "let", 1),
+            (TokenType::Ident, "émoji", 5),
+            (TokenType::Assign, "=", 11),
+            (TokenType::String, "naïve", 13),
+            (TokenType::NotEq, "!=", 21),
+            (TokenType::Ident, "x", 24),
+            (TokenType::Semicolon, ";", 25),
+            (TokenType::EOF, "", 26),
+        ];
+        for (r#type, literal, column) in expected {
+            let token = lexer.next_token_ref();
+            assert_eq!(
+                token,
+                TokenRef {
+                    r#type,
+                    literal,
+                    position: Position { line: 1, column },
+                }
+            );
+            if !literal.is_empty() {
+                let offset = token.literal.as_ptr() as usize - input.as_ptr() as usize;
+                assert!(
+                    offset < input.len(),
+                    "{} isn't a slice of the input",
+                    literal
+                );
+            }
+        }
+    }
+
+    fn time_to_lex(input: &str) -> Duration {
+        (0..3)
+            .map(|_| {
+                let start = Instant::now();
+                let mut lexer = Lexer::new(input);
+                while lexer.next_token_ref().r#type != TokenType::EOF {}
+                start.elapsed()
+            })
+            .min()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_lexer_is_linear() {
+        let chunk = "let add_values = fn(first, second) { first + second * 42 }; // sum\n";
+        let small = chunk.repeat(1_000);
+        let large = chunk.repeat(16_000);
+        let (small, large) = (time_to_lex(&small), time_to_lex(&large));
+        // 16 times the input should take about 16 times as long; a quadratic
+        // lexer would take 256 times as long.
+        assert!(
+            large < small * 64,
+            "lexing 16x the input took {:?} instead of about 16 * {:?}",
+            large,
+            small
+        );
+    }
+
+    #[test]
+    fn test_lexer_iterator() {
+        let literals: Vec<String> = Lexer::new("let x = [1, 2];")
+            .map(|token| token.literal)
+            .collect();
+        assert_eq!(
+            literals,
+            vec!["let", "x", "=", "[", "1", ",", "2", "]", ";"]
+        );
+        assert_eq!(Lexer::new("  // only a comment").next(), None);
+    }
+
+    #[test]
+    fn test_lexer_identifiers() {
+        let input = "x1 user2id utf8_len _0 letter iffy fnord returned größe 名前 π2 1x";
+        let expected = vec![
+            (TokenType::Ident, "x1"),
+            (TokenType::Ident, "user2id"),
+            (TokenType::Ident, "utf8_len"),
+            (TokenType::Ident, "_0"),
+            (TokenType::Ident, "letter"),
+            (TokenType::Ident, "iffy"),
+            (TokenType::Ident, "fnord"),
+            (TokenType::Ident, "returned"),
+            (TokenType::Ident, "größe"),
+            (TokenType::Ident, "名前"),
+            (TokenType::Ident, "π2"),
+            (TokenType::Int, "1"),
+            (TokenType::Ident, "x"),
+        ];
+        let tokens: Vec<(TokenType, String)> = Lexer::new(input)
+            .map(|token| (token.r#type, token.literal))
+            .collect();
+        let expected: Vec<(TokenType, String)> = expected
+            .into_iter()
+            .map(|(r#type, literal)| (r#type, String::from(literal)))
+            .collect();
+        assert_eq!(tokens, expected);
+    }
+
+    #[test]
+    fn test_lexer_integer_literals() {
+        let input = "0xFF 0o755 0b1010 1_000_000 0 007";
+        let literals: Vec<(TokenType, String)> = Lexer::new(input)
+            .map(|token| (token.r#type, token.literal))
+            .collect();
+        let expected: Vec<(TokenType, String)> =
+            ["0xFF", "0o755", "0b1010", "1_000_000", "0", "007"]
+                .into_iter()
+                .map(|literal| (TokenType::Int, String::from(literal)))
+                .collect();
+        assert_eq!(literals, expected);
+    }
+
+    #[test]
+    fn test_lexer_malformed_integer_literals() {
+        struct Test {
+            input: String,
+            literal: String,
+            message: String,
+        }
+        let tests = vec![
+            Test {
+                input: String::from("x = 0x;"),
+                literal: String::from("0x"),
+                message: String::from("missing digits after `0x`"),
+            },
+            Test {
+                input: String::from("x = 1__0;"),
+                literal: String::from("1__0"),
+                message: String::from("`_` in `1__0` must be between two digits"),
+            },
+            Test {
+                input: String::from("x = 10_;"),
+                literal: String::from("10_"),
+                message: String::from("`_` in `10_` must be between two digits"),
+            },
+            Test {
+                input: String::from("x = 0xFG;"),
+                literal: String::from("0xFG"),
+                message: String::from("invalid digit `G` in hexadecimal literal `0xFG`"),
+            },
+            Test {
+                input: String::from("x = 0b102;"),
+                literal: String::from("0b102"),
+                message: String::from("invalid digit `2` in binary literal `0b102`"),
+            },
+            Test {
+                input: String::from("x = 9223372036854775808;"),
+                literal: String::from("9223372036854775808"),
+                message: String::from("integer literal `9223372036854775808` is too large"),
+            },
+        ];
+        for test in tests {
+            let mut lexer = Lexer::new(&test.input);
+            let tokens: Vec<Token> = lexer.by_ref().collect();
+            assert_eq!(tokens[2].r#type, TokenType::Illegal, "{}", test.input);
+            assert_eq!(tokens[2].literal, test.literal);
+            assert_eq!(tokens[3].r#type, TokenType::Semicolon);
+            assert_eq!(
+                lexer.errors(),
+                &[LexerError {
+                    message: test.message,
+                    position: Position { line: 1, column: 5 },
+                }]
+            );
+        }
+    }
 }
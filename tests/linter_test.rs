@@ -0,0 +1,102 @@
+#[cfg(test)]
+mod tests {
+    use rost_interpreter::linter::{lint_source, LintConfig, Rule, Severity};
+
+    fn test_lint(input: &str) -> Vec<(Rule, usize, usize)> {
+        lint_source(input, &LintConfig::default())
+            .unwrap()
+            .into_iter()
+            .map(|d| (d.rule, d.position.line, d.position.column))
+            .collect()
+    }
+
+    #[test]
+    fn test_rules() {
+        struct Test {
+            input: String,
+            expected: Vec<(Rule, usize, usize)>,
+        }
+        let tests = vec![
+            Test {
+                input: String::from("let x = 1; let y = 2; puts(y);"),
+                expected: vec![(Rule::UnusedBinding, 1, 5)],
+            },
+            Test {
+                input: String::from("let _x = 1; let f = fn(a) { a }; f(1);"),
+                expected: vec![],
+            },
+            Test {
+                input: String::from(
+                    "let count = fn(n) { if (n == 0) { 0 } else { count(n - 1) } };",
+                ),
+                expected: vec![(Rule::UnusedBinding, 1, 5)],
+            },
+            Test {
+                input: String::from("let x = 1; let x = x + 1; x;"),
+                expected: vec![],
+            },
+            Test {
+                input: String::from("let f = fn(a) { let a = 2; a }; f(1);"),
+                expected: vec![(Rule::ShadowedParameter, 1, 21)],
+            },
+            Test {
+                input: String::from("let f = fn(a) { fn(a) { a } }; f(1);"),
+                expected: vec![(Rule::ShadowedParameter, 1, 20)],
+            },
+            Test {
+                input: String::from("let f = fn() {\n  return 1;\n  puts(2);\n  puts(3);\n}; f();"),
+                expected: vec![(Rule::UnreachableCode, 3, 3)],
+            },
+            Test {
+                input: String::from("let f = fn() { throw \"x\"; 1 }; f();"),
+                expected: vec![(Rule::UnreachableCode, 1, 27)],
+            },
+            Test {
+                input: String::from("1 == \"1\"; true + 1; (1 < 2) == 3; -1 + 2; \"a\" + \"b\";"),
+                expected: vec![
+                    (Rule::MismatchedTypes, 1, 1),
+                    (Rule::MismatchedTypes, 1, 11),
+                    (Rule::MismatchedTypes, 1, 22),
+                ],
+            },
+            Test {
+                input: String::from("try { risky() } catch (e) { 1 }"),
+                expected: vec![],
+            },
+        ];
+        for test in tests {
+            assert_eq!(test_lint(&test.input), test.expected, "{}", test.input);
+        }
+    }
+
+    #[test]
+    fn test_severities() {
+        let input = "let x = 1; 1 == true;";
+        let diagnostics = lint_source(input, &LintConfig::default()).unwrap();
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[1].severity, Severity::Error);
+        assert_eq!(
+            diagnostics[1].to_string(),
+            "1:12: error[mismatched-types]: `INTEGER == BOOLEAN` is always a type mismatch"
+        );
+
+        let mut config = LintConfig::new();
+        config.set_severity(Rule::UnusedBinding, Severity::Error);
+        config.set_severity(Rule::MismatchedTypes, Severity::Allow);
+        let diagnostics = lint_source(input, &config).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].rule, Rule::UnusedBinding);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+    }
+
+    #[test]
+    fn test_allow_comments() {
+        let input = "\
+let unused = 1; // lint:allow(unused-binding)
+// lint:allow(mismatched-types, unused-binding)
+let other = 1 == \"1\";
+let third = 1;
+";
+        assert_eq!(test_lint(input), vec![(Rule::UnusedBinding, 4, 5)]);
+    }
+}
@@ -0,0 +1,332 @@
+#[cfg(test)]
+mod tests {
+    use rost_interpreter::{
+        lsp::Server,
+        protocol::{read_message, write_message},
+    };
+    use serde_json::{json, Value};
+    use std::io::Cursor;
+
+    const URI: &str = "file:///test.mky";
+
+    const SOURCE: &str = "let limit = 10;
+let add = fn(a, b) { a + b };
+let total = add(limit, 5);
+puts(total);
+";
+
+    fn open(server: &mut Server, text: &str) -> Vec<Value> {
+        server.handle(&json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": {"textDocument": {"uri": URI, "languageId": "monkey", "version": 1, "text": text}},
+        }))
+    }
+
+    fn request(server: &mut Server, method: &str, params: Value) -> Value {
+        let mut responses = server.handle(&json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        }));
+        assert_eq!(responses.len(), 1);
+        responses.remove(0)
+    }
+
+    fn at(line: u64, character: u64) -> Value {
+        json!({"textDocument": {"uri": URI}, "position": {"line": line, "character": character}})
+    }
+
+    fn range(line: u64, start: u64, end: u64) -> Value {
+        json!({"start": {"line": line, "character": start}, "end": {"line": line, "character": end}})
+    }
+
+    #[test]
+    fn test_messages_round_trip() {
+        let message = json!({"jsonrpc": "2.0", "id": 7, "method": "shutdown"});
+        let mut buffer = Vec::new();
+        write_message(&mut buffer, &message).unwrap();
+        write_message(&mut buffer, &message).unwrap();
+        let body = message.to_string();
+        let framed = format!("Content-Length: {}\r\n\r\n{}", body.len(), body);
+        assert_eq!(buffer, framed.repeat(2).into_bytes());
+        let mut reader = Cursor::new(buffer);
+        assert_eq!(read_message(&mut reader).unwrap(), Some(message.clone()));
+        assert_eq!(read_message(&mut reader).unwrap(), Some(message));
+        assert_eq!(read_message(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn test_initialize_and_shutdown() {
+        let mut server = Server::new();
+        let response = request(&mut server, "initialize", json!({"capabilities": {}}));
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["result"]["capabilities"]["hoverProvider"], true);
+        assert!(!server.is_shut_down());
+        let response = request(&mut server, "shutdown", Value::Null);
+        assert_eq!(response["result"], Value::Null);
+        assert!(server.is_shut_down());
+        let response = request(&mut server, "workspace/symbol", json!({}));
+        assert_eq!(response["error"]["code"], -32601);
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        struct Test {
+            input: String,
+            expected: Vec<(Value, u64, String)>,
+        }
+        let tests = vec![
+            Test {
+                input: String::from(SOURCE),
+                expected: vec![],
+            },
+            Test {
+                input: String::from("let x = 1;\nlet y = ;\nx + y"),
+                expected: vec![(
+                    range(1, 8, 9),
+                    1,
+                    String::from("expected an expression, found `;`"),
+                )],
+            },
+            Test {
+                input: String::from("let x = 1;\nputs(y);"),
+                expected: vec![
+                    (range(1, 5, 6), 1, String::from("undefined variable `y`")),
+                    (range(0, 4, 5), 2, String::from("`x` is never used")),
+                ],
+            },
+        ];
+        for test in tests {
+            let mut server = Server::new();
+            let notifications = open(&mut server, &test.input);
+            assert_eq!(notifications.len(), 1);
+            let notification = &notifications[0];
+            assert_eq!(notification["method"], "textDocument/publishDiagnostics");
+            assert_eq!(notification["params"]["uri"], URI);
+            let diagnostics: Vec<(Value, u64, String)> = notification["params"]["diagnostics"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|d| {
+                    (
+                        d["range"].clone(),
+                        d["severity"].as_u64().unwrap(),
+                        d["message"].as_str().unwrap().to_string(),
+                    )
+                })
+                .collect();
+            assert_eq!(diagnostics, test.expected, "{}", test.input);
+        }
+    }
+
+    #[test]
+    fn test_did_change_and_close() {
+        let mut server = Server::new();
+        open(&mut server, "let x = ");
+        let notifications = server.handle(&json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didChange",
+            "params": {
+                "textDocument": {"uri": URI, "version": 2},
+                "contentChanges": [{"text": "let x = 1; x"}],
+            },
+        }));
+        assert_eq!(notifications[0]["params"]["diagnostics"], json!([]));
+        let response = request(&mut server, "textDocument/definition", at(0, 11));
+        assert_eq!(response["result"]["range"], range(0, 4, 5));
+        let notifications = server.handle(&json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didClose",
+            "params": {"textDocument": {"uri": URI}},
+        }));
+        assert_eq!(notifications[0]["params"]["diagnostics"], json!([]));
+        let response = request(&mut server, "textDocument/definition", at(0, 11));
+        assert_eq!(response["error"]["code"], -32602);
+    }
+
+    #[test]
+    fn test_definition() {
+        let mut server = Server::new();
+        open(&mut server, SOURCE);
+        struct Test {
+            position: Value,
+            expected: Value,
+        }
+        let tests = vec![
+            Test {
+                position: at(2, 18),
+                expected: json!({"uri": URI, "range": range(0, 4, 9)}),
+            },
+            Test {
+                position: at(1, 21),
+                expected: json!({"uri": URI, "range": range(1, 13, 14)}),
+            },
+            Test {
+                position: at(3, 9),
+                expected: json!({"uri": URI, "range": range(2, 4, 9)}),
+            },
+            Test {
+                position: at(3, 1),
+                expected: Value::Null,
+            },
+        ];
+        for test in tests {
+            let response = request(
+                &mut server,
+                "textDocument/definition",
+                test.position.clone(),
+            );
+            assert_eq!(response["result"], test.expected, "{}", test.position);
+        }
+    }
+
+    #[test]
+    fn test_definition_counts_utf16_units() {
+        let mut server = Server::new();
+        open(&mut server, "let s = \"😀\"; s");
+        let response = request(&mut server, "textDocument/definition", at(0, 14));
+        assert_eq!(response["result"]["range"], range(0, 4, 5));
+    }
+
+    #[test]
+    fn test_references() {
+        let mut server = Server::new();
+        open(&mut server, SOURCE);
+        let mut params = at(1, 5);
+        params["context"] = json!({"includeDeclaration": true});
+        let response = request(&mut server, "textDocument/references", params.clone());
+        assert_eq!(
+            response["result"],
+            json!([
+                {"uri": URI, "range": range(1, 4, 7)},
+                {"uri": URI, "range": range(2, 12, 15)},
+            ])
+        );
+        params["context"] = json!({"includeDeclaration": false});
+        let response = request(&mut server, "textDocument/references", params);
+        assert_eq!(
+            response["result"],
+            json!([{"uri": URI, "range": range(2, 12, 15)}])
+        );
+    }
+
+    #[test]
+    fn test_references_skip_catch_parameters() {
+        let mut server = Server::new();
+        open(&mut server, "let e = 1;\ntry { e } catch (e) { e };\ne");
+        let mut params = at(0, 4);
+        params["context"] = json!({"includeDeclaration": true});
+        let response = request(&mut server, "textDocument/references", params);
+        assert_eq!(
+            response["result"],
+            json!([
+                {"uri": URI, "range": range(0, 4, 5)},
+                {"uri": URI, "range": range(1, 6, 7)},
+                {"uri": URI, "range": range(2, 0, 1)},
+            ])
+        );
+    }
+
+    #[test]
+    fn test_hover() {
+        let mut server = Server::new();
+        open(&mut server, SOURCE);
+        struct Test {
+            position: Value,
+            expected: Value,
+        }
+        let tests = vec![
+            Test {
+                position: at(2, 17),
+                expected: json!("let limit: INTEGER"),
+            },
+            Test {
+                position: at(2, 13),
+                expected: json!("let add: fn(a, b)"),
+            },
+            Test {
+                position: at(1, 25),
+                expected: json!("parameter b"),
+            },
+            Test {
+                position: at(3, 2),
+                expected: json!("builtin puts(any number of arguments)"),
+            },
+            Test {
+                position: at(0, 13),
+                expected: Value::Null,
+            },
+        ];
+        for test in tests {
+            let response = request(&mut server, "textDocument/hover", test.position.clone());
+            assert_eq!(
+                response["result"]["contents"]["value"], test.expected,
+                "{}",
+                test.position
+            );
+        }
+        let response = request(&mut server, "textDocument/hover", at(2, 17));
+        assert_eq!(response["result"]["range"], range(2, 16, 21));
+    }
+
+    #[test]
+    fn test_completion() {
+        let mut server = Server::new();
+        open(&mut server, SOURCE);
+        let labels = |response: Value| -> Vec<String> {
+            response["result"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|item| item["label"].as_str().unwrap().to_string())
+                .collect()
+        };
+        let response = request(&mut server, "textDocument/completion", at(1, 22));
+        assert_eq!(
+            labels(response),
+            vec![
+                "limit", "add", "a", "b", "total", "error", "first", "last", "len", "push", "puts",
+                "rest"
+            ]
+        );
+        let response = request(&mut server, "textDocument/completion", at(3, 0));
+        let items = response["result"].as_array().unwrap().clone();
+        assert_eq!(
+            labels(response),
+            vec!["limit", "add", "total", "error", "first", "last", "len", "push", "puts", "rest"]
+        );
+        assert_eq!(items[1]["kind"], 3);
+        assert_eq!(items[1]["detail"], "let add: fn(a, b)");
+        assert_eq!(items[6]["detail"], "builtin len(1 argument)");
+
+        open(&mut server, "let count = 1;\nlet f = fn(n) { co");
+        let response = request(&mut server, "textDocument/completion", at(1, 18));
+        assert_eq!(labels(response)[..3], ["count", "f", "n"]);
+    }
+
+    #[test]
+    fn test_formatting() {
+        let mut server = Server::new();
+        open(&mut server, "let  x=1\nputs( x )");
+        let params =
+            json!({"textDocument": {"uri": URI}, "options": {"tabSize": 4, "insertSpaces": true}});
+        let response = request(&mut server, "textDocument/formatting", params.clone());
+        assert_eq!(
+            response["result"],
+            json!([{
+                "range": {"start": {"line": 0, "character": 0}, "end": {"line": 1, "character": 9}},
+                "newText": "let x = 1;\nputs(x);\n",
+            }])
+        );
+        open(&mut server, "let x = 1;\nputs(x);\n");
+        let response = request(&mut server, "textDocument/formatting", params.clone());
+        assert_eq!(response["result"], json!([]));
+        for text in ["let x = ", "fn (", "macro ( }"] {
+            open(&mut server, text);
+            let response = request(&mut server, "textDocument/formatting", params.clone());
+            assert_eq!(response["result"], Value::Null, "{}", text);
+        }
+    }
+}
@@ -0,0 +1,102 @@
+#[cfg(test)]
+mod tests {
+    use rost_interpreter::{
+        ast::Program,
+        evaluator::EvaluationError,
+        lexer::Lexer,
+        macro_expansion::{define_macros, expand_macros},
+        object::{Environment, Object},
+        parser::Parser,
+    };
+
+    fn test_parse_program(input: &str) -> Program {
+        let lexer = Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        parser.parse_program().unwrap()
+    }
+
+    #[test]
+    fn test_define_macros() {
+        let input = "
+            let number = 1;
+            let function = fn(x, y) { x + y };
+            let mymacro = macro(x, y) { x + y; };
+        ";
+        let mut env = Environment::new(None);
+        let mut program = test_parse_program(input);
+        define_macros(&mut program, &mut env);
+        assert_eq!(program.statements.len(), 2);
+        assert!(env.get("number").is_err());
+        assert!(env.get("function").is_err());
+        let r#macro = match env.get("mymacro").unwrap() {
+            Object::Macro(m) => m,
+            e => panic!("Expected Object::Macro\nGot: {:?}", e),
+        };
+        assert_eq!(r#macro.parameters.len(), 2);
+        assert_eq!(r#macro.parameters[0].value, "x");
+        assert_eq!(r#macro.parameters[1].value, "y");
+        assert_eq!(r#macro.body.to_string(), "(x + y)");
+    }
+
+    #[test]
+    fn test_expand_macros() {
+        struct Test {
+            input: String,
+            expected: String,
+        }
+        let tests = vec![
+            Test {
+                input: String::from(
+                    "let infixExpression = macro() { quote(1 + 2); }; infixExpression();",
+                ),
+                expected: String::from("(1 + 2)"),
+            },
+            Test {
+                input: String::from(
+                    "let reverse = macro(a, b) { quote(unquote(b) - unquote(a)); }; reverse(2 + 2, 10 - 5);",
+                ),
+                expected: String::from("(10 - 5) - (2 + 2)"),
+            },
+            Test {
+                input: String::from(
+                    r#"
+                    let unless = macro(condition, consequence, alternative) {
+                        quote(if (!(unquote(condition))) {
+                            unquote(consequence);
+                        } else {
+                            unquote(alternative);
+                        });
+                    };
+                    unless(10 > 5, puts("not greater"), puts("greater"));
+                    "#,
+                ),
+                expected: String::from(
+                    r#"if (!(10 > 5)) { puts("not greater") } else { puts("greater") }"#,
+                ),
+            },
+        ];
+        for test in tests {
+            let mut env = Environment::new(None);
+            let mut program = test_parse_program(&test.input);
+            define_macros(&mut program, &mut env);
+            let expanded = expand_macros(program, &mut env).unwrap();
+            assert_eq!(
+                expanded.to_string(),
+                test_parse_program(&test.expected).to_string()
+            );
+        }
+    }
+
+    #[test]
+    fn test_macro_must_return_quote() {
+        let mut env = Environment::new(None);
+        let mut program = test_parse_program("let m = macro() { 1 }; m();");
+        define_macros(&mut program, &mut env);
+        assert_eq!(
+            expand_macros(program, &mut env).unwrap_err(),
+            EvaluationError::MacroError(String::from(
+                "Macros must return a quoted AST node\nGot: 1"
+            ))
+        );
+    }
+}
@@ -2,8 +2,9 @@
 mod tests {
     use rost_interpreter::{
         ast::{Expression, NodeTrait, Program, Statement},
-        lexer::Lexer,
-        parser::Parser,
+        lexer::{Lexer, LexerError},
+        parser::{Parser, ParserError},
+        token::Position,
     };
 
     #[test]
@@ -972,4 +973,138 @@ mod tests {
         assert_eq!(index.operator, "+");
         assert!(test_integer_literal(*index.right.clone(), 1));
     }
+
+    #[test]
+    fn test_try_expression() {
+        struct Test {
+            input: String,
+            expected: String,
+        }
+        let tests = vec![
+            Test {
+                input: String::from("try { risky(); } catch (e) { e; }"),
+                expected: String::from("try risky() catch (e) e"),
+            },
+            Test {
+                input: String::from("try { 1 } finally { cleanup() }"),
+                expected: String::from("try 1 finally cleanup()"),
+            },
+            Test {
+                input: String::from("try { 1 } catch (e) { 2 } finally { 3 }"),
+                expected: String::from("try 1 catch (e) 2 finally 3"),
+            },
+            Test {
+                input: String::from("throw error(kind, message);"),
+                expected: String::from("throw error(kind, message);"),
+            },
+        ];
+        for test in tests {
+            let lexer = Lexer::new(&test.input);
+            let mut parser = Parser::new(lexer);
+            let program = parser.parse_program().unwrap();
+            assert_eq!(program.to_string(), test.expected);
+        }
+        let lexer = Lexer::new("try { 1 }");
+        let mut parser = Parser::new(lexer);
+        assert!(parser.parse_program().is_err());
+    }
+
+    #[test]
+    fn test_macro_literal() {
+        let input = "macro(x, y) { x + y; }";
+        let lexer = Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_program().unwrap();
+        assert_eq!(program.statements.len(), 1);
+        let statement = match &program.statements[0] {
+            Statement::Expression(es) => es,
+            e => panic!(
+                "Not the right kind of Statement. Expected: Statement::Expression\nGot: {}",
+                e
+            ),
+        };
+        let r#macro = match &statement.expression {
+            Expression::MacroLiteral(ml) => ml,
+            e => panic!(
+                "Not the right kind of Expression. Expected: Expression::MacroLiteral\nGot: {}",
+                e
+            ),
+        };
+        assert_eq!(r#macro.parameters.len(), 2);
+        assert_eq!(r#macro.parameters[0].value, "x");
+        assert_eq!(r#macro.parameters[1].value, "y");
+        assert_eq!(r#macro.body.to_string(), "(x + y)");
+    }
+
+    #[test]
+    fn test_integer_literal_forms() {
+        let tests = vec![
+            ("0xFF", 255),
+            ("0o755", 493),
+            ("0b1010", 10),
+            ("1_000_000", 1_000_000),
+        ];
+        for (input, expected) in tests {
+            let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+            let Statement::Expression(statement) = &program.statements[0] else {
+                panic!("Expected Statement::Expression\nGot: {}", program);
+            };
+            let Expression::IntegerLiteral(int) = &statement.expression else {
+                panic!("Expected Expression::IntegerLiteral\nGot: {}", program);
+            };
+            assert_eq!(int.value, expected);
+            assert_eq!(program.to_string(), input);
+        }
+    }
+
+    #[test]
+    fn test_unclosed_parameter_lists() {
+        let tests = vec![
+            ("fn (", "FunctionLiteral"),
+            ("fn (a, b {", "FunctionLiteral"),
+            ("macro ( }", "MacroLiteral"),
+        ];
+        for (input, expected) in tests {
+            match Parser::new(Lexer::new(input)).parse_program() {
+                Err(ParserError::FunctionLiteral(_)) => assert_eq!(expected, "FunctionLiteral"),
+                Err(ParserError::MacroLiteral(_)) => assert_eq!(expected, "MacroLiteral"),
+                Err(e) => panic!("Expected ParserError::{}\nGot: {}", expected, e),
+                Ok(program) => panic!("Expected an error\nGot: {}", program),
+            }
+        }
+    }
+
+    #[test]
+    fn test_lexer_errors() {
+        let tests = vec![
+            (
+                "let x = 1 +\n  0x;",
+                "missing digits after `0x`",
+                Position { line: 2, column: 3 },
+            ),
+            (
+                "1__0",
+                "`_` in `1__0` must be between two digits",
+                Position { line: 1, column: 1 },
+            ),
+            (
+                "let y = @;",
+                "unexpected character `@`",
+                Position { line: 1, column: 9 },
+            ),
+        ];
+        for (input, message, position) in tests {
+            match Parser::new(Lexer::new(input)).parse_program() {
+                Err(ParserError::Lexer(error)) => assert_eq!(
+                    error,
+                    LexerError {
+                        message: String::from(message),
+                        position,
+                    }
+                ),
+                Err(e) => panic!("Expected ParserError::Lexer\nGot: {}", e),
+                Ok(program) => panic!("Expected an error\nGot: {}", program),
+            }
+        }
+    }
 }
@@ -0,0 +1,118 @@
+#[cfg(test)]
+mod tests {
+    use rost_interpreter::{interpreter::Interpreter, profiler::Profiler};
+    use std::{io, rc::Rc};
+
+    const SOURCE: &str = "let fib = fn(n) { if (n < 2) { n } else { fib(n - 1) + fib(n - 2) } };
+let words = fn(n) { if (n == 0) { [] } else { push(words(n - 1), \"word\") } };
+let run = fn() { fib(5); words(3) };
+run();
+let id = fn(x) { x };
+id(1);
+";
+
+    fn profile(input: &str) -> Rc<Profiler> {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_output(io::sink());
+        let profiler = Rc::new(Profiler::new());
+        interpreter.set_tracer(Some(profiler.clone()));
+        interpreter.eval_str(input).unwrap();
+        profiler
+    }
+
+    #[test]
+    fn test_call_counts_and_allocations() {
+        let profiler = profile(SOURCE);
+        let mut functions = profiler.functions();
+        functions.sort_by_key(|f| f.position);
+        let summary: Vec<(String, u64, bool)> = functions
+            .iter()
+            .map(|f| (f.label(), f.calls, f.self_allocated > 0))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (String::from("fib (line 1)"), 15, false),
+                (String::from("words (line 2)"), 4, true),
+                (String::from("run (line 3)"), 1, false),
+                (String::from("id (line 5)"), 1, false),
+            ]
+        );
+        let words = &functions[1];
+        assert_eq!(words.total_allocated, words.self_allocated);
+        let run = &functions[2];
+        assert_eq!(run.total_allocated, words.total_allocated);
+        for function in &functions {
+            assert!(function.self_time <= function.total_time, "{:?}", function);
+        }
+        assert!(run.total_time >= functions[0].total_time + words.total_time);
+
+        let self_times: Vec<_> = profiler.functions().iter().map(|f| f.self_time).collect();
+        assert!(self_times.windows(2).all(|pair| pair[0] >= pair[1]));
+    }
+
+    #[test]
+    fn test_anonymous_functions_are_told_apart() {
+        let profiler =
+            profile("let a = fn() { 1 };\nlet b = fn() { 2 };\n[fn() { a() }, fn() { b() }][0]();");
+        let mut labels: Vec<String> = profiler.functions().iter().map(|f| f.label()).collect();
+        labels.sort();
+        assert_eq!(labels, vec!["<anonymous> (line 3)", "a (line 1)"]);
+    }
+
+    #[test]
+    fn test_collapsed_stacks() {
+        let profiler = profile(SOURCE);
+        let mut output = Vec::new();
+        profiler.write_collapsed_stacks(&mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let stacks: Vec<&str> = output
+            .lines()
+            .map(|line| {
+                let (stack, nanos) = line.rsplit_once(' ').unwrap();
+                nanos.parse::<u128>().unwrap();
+                stack
+            })
+            .collect();
+        assert_eq!(
+            stacks,
+            vec![
+                "id (line 5)",
+                "run (line 3)",
+                "run (line 3);fib (line 1)",
+                "run (line 3);fib (line 1);fib (line 1)",
+                "run (line 3);fib (line 1);fib (line 1);fib (line 1)",
+                "run (line 3);fib (line 1);fib (line 1);fib (line 1);fib (line 1)",
+                "run (line 3);fib (line 1);fib (line 1);fib (line 1);fib (line 1);fib (line 1)",
+                "run (line 3);words (line 2)",
+                "run (line 3);words (line 2);words (line 2)",
+                "run (line 3);words (line 2);words (line 2);words (line 2)",
+                "run (line 3);words (line 2);words (line 2);words (line 2);words (line 2)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_report() {
+        let profiler = profile(SOURCE);
+        let report = profiler.report();
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(
+            lines[0].split_whitespace().collect::<Vec<_>>(),
+            vec!["function", "calls", "total", "self", "allocated"]
+        );
+        let allocated = profiler
+            .functions()
+            .iter()
+            .find(|f| f.name == "words")
+            .unwrap()
+            .self_allocated;
+        let words = lines
+            .iter()
+            .find(|l| l.starts_with("words (line 2)"))
+            .unwrap();
+        assert!(words.ends_with(&format!(" {} B", allocated)), "{}", words);
+        assert_eq!(words.split_whitespace().nth(3), Some("4"));
+    }
+}
@@ -0,0 +1,211 @@
+#[cfg(test)]
+mod tests {
+    use rost_interpreter::{
+        ast::{Identifier, Program, Visitor},
+        evaluator::{eval, EvaluationError},
+        lexer::Lexer,
+        object::{Environment, Integer, Object},
+        parser::Parser,
+        resolver::{resolve, Resolution, ResolveError},
+        token::Position,
+    };
+
+    fn test_parse_program(input: &str) -> Program {
+        let lexer = Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        parser.parse_program().unwrap()
+    }
+
+    struct Resolutions(Vec<(String, Option<Resolution>)>);
+
+    impl Visitor for Resolutions {
+        fn visit_identifier(&mut self, identifier: &Identifier) {
+            self.0
+                .push((identifier.value.clone(), identifier.resolution.get()));
+        }
+    }
+
+    fn at(depth: usize, slot: usize) -> Option<Resolution> {
+        Some(Resolution { depth, slot })
+    }
+
+    #[test]
+    fn test_resolve_slots() {
+        let program = test_parse_program(
+            "let a = 1;
+            let f = fn(x, y) {
+                let z = x;
+                fn() { z + a + len(y) };
+            };",
+        );
+        let env = Environment::new(None);
+        assert_eq!(resolve(&program, &env), Ok(()));
+        let mut resolutions = Resolutions(Vec::new());
+        resolutions.visit_program(&program);
+        assert_eq!(
+            resolutions.0,
+            vec![
+                (String::from("a"), at(0, 0)),
+                (String::from("f"), at(0, 1)),
+                (String::from("x"), at(0, 0)),
+                (String::from("y"), at(0, 1)),
+                (String::from("z"), at(0, 2)),
+                (String::from("x"), at(0, 0)),
+                (String::from("z"), at(1, 2)),
+                (String::from("a"), at(2, 0)),
+                (String::from("len"), None),
+                (String::from("y"), at(1, 1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_undefined_variables() {
+        struct Test {
+            input: String,
+            expected: Result<(), Vec<ResolveError>>,
+        }
+        let tests = vec![
+            Test {
+                input: String::from("let x = 1; x + y;"),
+                expected: Err(vec![ResolveError::UndefinedVariable(
+                    String::from("y"),
+                    Position {
+                        line: 1,
+                        column: 16,
+                    },
+                )]),
+            },
+            Test {
+                input: String::from("fn(a) { b }; fn() { a };"),
+                expected: Err(vec![
+                    ResolveError::UndefinedVariable(
+                        String::from("b"),
+                        Position { line: 1, column: 9 },
+                    ),
+                    ResolveError::UndefinedVariable(
+                        String::from("a"),
+                        Position {
+                            line: 1,
+                            column: 21,
+                        },
+                    ),
+                ]),
+            },
+            Test {
+                input: String::from("let f = fn() { g() }; let g = fn() { f };"),
+                expected: Ok(()),
+            },
+            Test {
+                input: String::from("try { throw 1 } catch (e) { let x = e; x }; e;"),
+                expected: Err(vec![ResolveError::UndefinedVariable(
+                    String::from("e"),
+                    Position {
+                        line: 1,
+                        column: 45,
+                    },
+                )]),
+            },
+            Test {
+                input: String::from("let e = 1; try { throw 1 } catch (e) { e }; e;"),
+                expected: Ok(()),
+            },
+            Test {
+                input: String::from("quote(a + unquote(1 + 2) + b);"),
+                expected: Ok(()),
+            },
+            Test {
+                input: String::from("quote(unquote(a));"),
+                expected: Err(vec![ResolveError::UndefinedVariable(
+                    String::from("a"),
+                    Position {
+                        line: 1,
+                        column: 15,
+                    },
+                )]),
+            },
+            Test {
+                input: String::from("puts(len([]), global);"),
+                expected: Ok(()),
+            },
+        ];
+        for test in tests {
+            let program = test_parse_program(&test.input);
+            let mut env = Environment::new(None);
+            env.set("global", Object::Integer(Integer { value: 1 }));
+            assert_eq!(resolve(&program, &env), test.expected, "{}", test.input);
+        }
+    }
+
+    #[test]
+    fn test_eval_resolved() {
+        struct Test {
+            input: String,
+            expected: Result<Object, EvaluationError>,
+        }
+        let tests = vec![
+            Test {
+                input: String::from(
+                    "let even = fn(n) { if (n == 0) { true } else { odd(n - 1) } };
+                    let odd = fn(n) { if (n == 0) { false } else { even(n - 1) } };
+                    odd(7);",
+                ),
+                expected: Ok(Object::Boolean(rost_interpreter::object::Boolean {
+                    value: true,
+                })),
+            },
+            Test {
+                input: String::from(
+                    "let x = 1; let f = fn() { let y = x; let x = 2; y + x }; f();",
+                ),
+                expected: Ok(Object::Integer(Integer { value: 3 })),
+            },
+            Test {
+                input: String::from("let f = fn() { if (false) { let x = 1; } x }; f();"),
+                expected: Err(EvaluationError::IdentError(String::from("x"))),
+            },
+        ];
+        for test in tests {
+            let mut env = Environment::new(None);
+            assert_eq!(
+                eval(&test_parse_program(&test.input), &mut env),
+                test.expected,
+                "{}",
+                test.input
+            );
+        }
+    }
+
+    #[test]
+    fn test_undefined_variables_are_reported_before_evaluation() {
+        let mut env = Environment::new(None);
+        assert_eq!(
+            eval(
+                &test_parse_program("let x = 1; puts(x); missing;"),
+                &mut env
+            ),
+            Err(EvaluationError::IdentError(String::from(
+                "missing at line 1, column 21"
+            )))
+        );
+        assert!(env.get("x").is_err());
+        let error = eval(
+            &test_parse_program("let f = fn() { a };\nb + f()"),
+            &mut env,
+        );
+        assert_eq!(
+            error,
+            Err(EvaluationError::IdentError(String::from(
+                "a at line 1, column 16, b at line 2, column 1"
+            )))
+        );
+        assert_eq!(
+            error.unwrap_err().to_string(),
+            "Identifier not found: a at line 1, column 16, b at line 2, column 1"
+        );
+        assert_eq!(
+            eval(&test_parse_program("let x = 2; x"), &mut env),
+            Ok(Object::Integer(Integer { value: 2 }))
+        );
+    }
+}
@@ -0,0 +1,248 @@
+#[cfg(test)]
+mod tests {
+    use rost_interpreter::{
+        convert::IntoObject,
+        evaluator::{self, EvaluationError},
+        interpreter::{Interpreter, InterpreterError},
+        lexer::Lexer,
+        object::Object,
+        parser::Parser,
+        runtime::Limits,
+    };
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
+
+    const COUNT_DOWN: &str =
+        "let countDown = fn(n) { if (n == 0) { 0 } else { countDown(n - 1) } };";
+
+    fn evaluation_error(result: Result<Object, InterpreterError>) -> EvaluationError {
+        match result {
+            Err(InterpreterError::Evaluation(e, _)) => e,
+            e => panic!("Expected InterpreterError::Evaluation\nGot: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_step_limit() {
+        let mut interpreter = Interpreter::new();
+        interpreter.eval_str(COUNT_DOWN).unwrap();
+        interpreter.set_limits(Limits {
+            max_steps: Some(100),
+            ..Limits::default()
+        });
+        assert!(interpreter.eval_str("countDown(2)").is_ok());
+        assert_eq!(
+            evaluation_error(interpreter.eval_str("countDown(20)")),
+            EvaluationError::StepLimitExceeded(100)
+        );
+        // The budget applies to each run, not to the interpreter's lifetime.
+        for _ in 0..10 {
+            assert!(interpreter.eval_str("countDown(2)").is_ok());
+        }
+    }
+
+    #[test]
+    fn test_step_limit_covers_reentered_code() {
+        let mut interpreter = Interpreter::new();
+        interpreter.eval_str(COUNT_DOWN).unwrap();
+        let env = interpreter.environment().clone();
+        interpreter.register_fn("reenter", move || {
+            let program = Parser::new(Lexer::new("countDown(1)"))
+                .parse_program()
+                .unwrap();
+            evaluator::eval(&program, &mut env.clone()).is_ok()
+        });
+        interpreter.set_limits(Limits {
+            max_steps: Some(100),
+            ..Limits::default()
+        });
+        assert_eq!(
+            evaluation_error(
+                interpreter.eval_str(
+                    "let loop = fn(n) { if (n > 0) { reenter(); loop(n - 1) } }; loop(50)"
+                )
+            ),
+            EvaluationError::StepLimitExceeded(100)
+        );
+    }
+
+    #[test]
+    fn test_recursion_limit() {
+        let mut interpreter = Interpreter::new();
+        interpreter.eval_str(COUNT_DOWN).unwrap();
+        interpreter.set_limits(Limits {
+            max_depth: Some(10),
+            ..Limits::default()
+        });
+        assert!(interpreter.eval_str("countDown(9)").is_ok());
+        assert_eq!(
+            evaluation_error(interpreter.eval_str("countDown(10)")),
+            EvaluationError::RecursionLimitExceeded(10)
+        );
+        assert_eq!(
+            evaluation_error(interpreter.eval_str("let loop = fn() { loop() }; loop()")),
+            EvaluationError::RecursionLimitExceeded(10)
+        );
+        assert!(interpreter.eval_str("countDown(9)").is_ok());
+    }
+
+    #[test]
+    fn test_deep_recursion_does_not_overflow_the_stack() {
+        let mut interpreter = Interpreter::new();
+        interpreter.eval_str(COUNT_DOWN).unwrap();
+        match evaluation_error(interpreter.eval_str("countDown(1000000)")) {
+            EvaluationError::RecursionLimitExceeded(_) => {}
+            e => panic!(
+                "Expected EvaluationError::RecursionLimitExceeded\nGot: {:?}",
+                e
+            ),
+        }
+    }
+
+    #[test]
+    fn test_timeout() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_limits(Limits {
+            timeout: Some(Duration::from_millis(50)),
+            ..Limits::default()
+        });
+        let started = Instant::now();
+        let result = interpreter.eval_str(
+            "let fill = fn(n) { if (n == 0) { 0 } else { fill(n - 1) + fill(n - 1) + fill(n - 1) } }; fill(14)",
+        );
+        assert_eq!(
+            evaluation_error(result),
+            EvaluationError::Timeout(Duration::from_millis(50))
+        );
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_cancellation() {
+        let mut interpreter = Interpreter::new();
+        interpreter.eval_str(COUNT_DOWN).unwrap();
+        interpreter
+            .eval_str("let fill = fn(n) { if (n == 0) { 0 } else { fill(n - 1) + fill(n - 1) + fill(n - 1) } };")
+            .unwrap();
+        let token = interpreter.cancellation_token();
+        let canceller = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            token.cancel();
+        });
+        let started = Instant::now();
+        assert_eq!(
+            evaluation_error(interpreter.eval_str("let before = 1; fill(14); let after = 2;")),
+            EvaluationError::Cancelled
+        );
+        assert!(started.elapsed() < Duration::from_secs(5));
+        canceller.join().unwrap();
+
+        let token = interpreter.cancellation_token();
+        assert_eq!(
+            evaluation_error(interpreter.eval_str("1")),
+            EvaluationError::Cancelled
+        );
+        token.reset();
+
+        // Bindings made before the cancellation survive and the call depth unwound.
+        assert_eq!(interpreter.get_global("before"), Some(1.into_object()));
+        assert_eq!(interpreter.get_global("after"), None);
+        interpreter.set_limits(Limits {
+            max_depth: Some(10),
+            ..Limits::default()
+        });
+        assert!(interpreter.eval_str("countDown(9)").is_ok());
+    }
+
+    #[test]
+    fn test_allocation_limit() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_limits(Limits {
+            max_allocated: Some(1024 * 1024),
+            ..Limits::default()
+        });
+        interpreter
+            .eval_str("let grow = fn(s, n) { if (n == 0) { s } else { grow(s + s, n - 1) } };")
+            .unwrap();
+        assert!(interpreter.eval_str(r#"len(grow("ab", 10))"#).is_ok());
+        assert_eq!(
+            evaluation_error(interpreter.eval_str(r#"grow("ab", 30)"#)),
+            EvaluationError::OutOfMemory(1024 * 1024)
+        );
+        // The string that would go over the budget is never built.
+        assert!(interpreter.allocated_bytes() <= 1024 * 1024);
+
+        interpreter
+            .eval_str(
+                "let fill = fn(arr, n) { if (n == 0) { arr } else { fill(push(arr, arr), n - 1) } };",
+            )
+            .unwrap();
+        assert_eq!(
+            evaluation_error(interpreter.eval_str("fill([], 100000)")),
+            EvaluationError::OutOfMemory(1024 * 1024)
+        );
+
+        let long = format!("let s = \"{}\"; [s, s, s]", "x".repeat(400 * 1024));
+        assert_eq!(
+            evaluation_error(interpreter.eval_str(&long)),
+            EvaluationError::OutOfMemory(1024 * 1024)
+        );
+        assert!(interpreter.allocated_bytes() <= 1024 * 1024);
+    }
+
+    #[test]
+    fn test_allocation_limit_counts_values_in_use() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_limits(Limits {
+            max_allocated: Some(1024 * 1024),
+            ..Limits::default()
+        });
+        let big = format!("let big = \"{}\";", "x".repeat(100 * 1024));
+        interpreter.eval_str(&big).unwrap();
+        // Creates 4 MB of strings in all, but only holds on to one at a time.
+        interpreter
+            .eval_str(
+                "let churn = fn(n) { if (n > 0) { len(big + big); churn(n - 1) } }; churn(20);",
+            )
+            .unwrap();
+        assert!(interpreter.allocated_bytes() < 200 * 1024);
+
+        // Values bound by earlier runs still count.
+        interpreter
+            .eval_str("let kept = big + big + big + big + big + big + big + big;")
+            .unwrap();
+        assert_eq!(
+            evaluation_error(interpreter.eval_str("let more = kept + big;")),
+            EvaluationError::OutOfMemory(1024 * 1024)
+        );
+        interpreter.eval_str("let kept = 0;").unwrap();
+        assert!(interpreter.eval_str("let more = big + big;").is_ok());
+    }
+
+    #[test]
+    fn test_allocated_bytes() {
+        let mut interpreter = Interpreter::new();
+        interpreter.eval_str("1 + 2").unwrap();
+        assert_eq!(interpreter.allocated_bytes(), 0);
+
+        interpreter
+            .eval_str(r#"let greeting = "Hello" + " World";"#)
+            .unwrap();
+        let strings = interpreter.allocated_bytes();
+        assert!(strings >= "Hello World".len());
+
+        interpreter
+            .eval_str(r#"let words = ["Hello", "World", "!"]; let f = fn(x) { x };"#)
+            .unwrap();
+        let all = interpreter.allocated_bytes();
+        assert!(all > strings);
+
+        // Values that are no longer bound stop counting.
+        interpreter
+            .eval_str("let greeting = 0; let words = 0; let f = 0;")
+            .unwrap();
+        assert!(interpreter.allocated_bytes() < all);
+    }
+}
@@ -0,0 +1,226 @@
+#[cfg(test)]
+mod tests {
+    use rost_interpreter::{
+        ast::Statement,
+        evaluator::EvaluationError,
+        interpreter::Interpreter,
+        object::{BuiltIn, Function, Object},
+        token::Position,
+        tracer::{CallTracer, JsonTracer, Tracer},
+    };
+    use serde_json::{json, Value};
+    use std::{
+        cell::RefCell,
+        io::{self, Write},
+        rc::Rc,
+    };
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedBuffer {
+        fn contents(&self) -> String {
+            String::from_utf8(self.0.borrow().clone()).unwrap()
+        }
+    }
+
+    #[derive(Default)]
+    struct Events(RefCell<Vec<String>>);
+
+    impl Events {
+        fn push(&self, event: String) {
+            self.0.borrow_mut().push(event);
+        }
+    }
+
+    impl Tracer for Events {
+        fn enter_statement(&self, statement: &Statement) {
+            self.push(format!("enter {}", statement.position().line));
+        }
+
+        fn leave_statement(&self, statement: &Statement) {
+            self.push(format!("leave {}", statement.position().line));
+        }
+
+        fn enter_function(&self, function: &Function, args: &[Object]) {
+            self.push(format!("call {} {}", function.display_name(), args.len()));
+        }
+
+        fn leave_function(&self, function: &Function, result: Option<&Object>) {
+            let result = result.map_or(String::from("error"), |r| r.to_string());
+            self.push(format!("return {} {}", function.display_name(), result));
+        }
+
+        fn call_builtin(&self, builtin: &BuiltIn, args: &[Object]) {
+            self.push(format!("builtin {} {}", builtin.name, args.len()));
+        }
+
+        fn error(&self, error: &EvaluationError, position: Position) {
+            self.push(format!("error {} {}", error.kind(), position.line));
+        }
+    }
+
+    fn trace(input: &str) -> Vec<String> {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_output(SharedBuffer::default());
+        let events = Rc::new(Events::default());
+        interpreter.set_tracer(Some(events.clone()));
+        let _ = interpreter.eval_str(input);
+        let events = events.0.borrow().clone();
+        events
+    }
+
+    #[test]
+    fn test_callbacks() {
+        struct Test {
+            input: String,
+            expected: Vec<&'static str>,
+        }
+        let tests = vec![
+            Test {
+                input: String::from("let f = fn(x) {\n  len(x)\n};\nf(\"ab\");"),
+                expected: vec![
+                    "enter 1",
+                    "leave 1",
+                    "enter 4",
+                    "call f 1",
+                    "enter 2",
+                    "builtin len 1",
+                    "leave 2",
+                    "return f 2",
+                    "leave 4",
+                ],
+            },
+            Test {
+                input: String::from("let f = fn() {\n  throw \"no\";\n};\nf();"),
+                expected: vec![
+                    "enter 1",
+                    "leave 1",
+                    "enter 4",
+                    "call f 0",
+                    "enter 2",
+                    "error Error 2",
+                    "leave 2",
+                    "return f error",
+                    "leave 4",
+                ],
+            },
+            Test {
+                input: String::from(
+                    "try {\n  throw \"a\";\n} catch (e) {\n  throw \"b\";\n} finally {\n  1;\n}",
+                ),
+                expected: vec![
+                    "enter 1",
+                    "enter 2",
+                    "error Error 2",
+                    "leave 2",
+                    "enter 4",
+                    "error Error 4",
+                    "leave 4",
+                    "enter 6",
+                    "leave 6",
+                    "leave 1",
+                ],
+            },
+            Test {
+                input: String::from("let x = try { throw \"a\" } catch (e) { } + true;"),
+                expected: vec![
+                    "enter 1",
+                    "enter 1",
+                    "error Error 1",
+                    "leave 1",
+                    "error TypeError 1",
+                    "leave 1",
+                ],
+            },
+        ];
+        for test in tests {
+            assert_eq!(trace(&test.input), test.expected, "{}", test.input);
+        }
+    }
+
+    #[test]
+    fn test_call_tracer() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_output(SharedBuffer::default());
+        let output = SharedBuffer::default();
+        interpreter.set_tracer(Some(Rc::new(CallTracer::new(output.clone()))));
+        let result = interpreter.eval_str(
+            "let fib = fn(n) { if (n < 2) { n } else { fib(n - 1) + fib(n - 2) } };
+puts(fib(2));
+let fail = fn(s) { len(s) + 1 };
+fail(\"text\" + \"text\");
+fail(1);",
+        );
+        assert!(result.is_err());
+        assert_eq!(
+            output.contents(),
+            r#"-> fib(2)
+  -> fib(1)
+  <- fib = 1
+  -> fib(0)
+  <- fib = 0
+<- fib = 1
+builtin puts(1)
+-> fail("texttext")
+  builtin len("texttext")
+<- fail = 9
+-> fail(1)
+  builtin len(1)
+  !! line 3, column 20: BuiltInError: Wrong kind of argument. Expected: String or Array Got: INTEGER
+<- fail failed
+"#
+        );
+    }
+
+    #[test]
+    fn test_json_tracer() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_output(SharedBuffer::default());
+        let output = SharedBuffer::default();
+        interpreter.set_tracer(Some(Rc::new(JsonTracer::new(output.clone()))));
+        interpreter
+            .eval_str("let id = fn(x) { x };\nid(\"a\");")
+            .unwrap();
+        let events: Vec<Value> = output
+            .contents()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                json!({"event": "enter_statement", "line": 1, "column": 1}),
+                json!({"event": "leave_statement", "line": 1, "column": 1}),
+                json!({"event": "enter_statement", "line": 2, "column": 1}),
+                json!({"event": "enter_function", "function": "id", "arguments": ["\"a\""], "depth": 1}),
+                json!({"event": "enter_statement", "line": 1, "column": 18}),
+                json!({"event": "leave_statement", "line": 1, "column": 18}),
+                json!({"event": "leave_function", "function": "id", "result": "\"a\"", "depth": 1}),
+                json!({"event": "leave_statement", "line": 2, "column": 1}),
+            ]
+        );
+
+        let output = SharedBuffer::default();
+        interpreter.set_tracer(Some(Rc::new(JsonTracer::new(output.clone()))));
+        assert!(interpreter.eval_str("1 + true").is_err());
+        let error: Value = serde_json::from_str(output.contents().lines().nth(1).unwrap()).unwrap();
+        assert_eq!(error["event"], "error");
+        assert_eq!(error["kind"], "TypeError");
+        assert_eq!(error["line"], 1);
+
+        interpreter.set_tracer(None);
+        interpreter.eval_str("id(1)").unwrap();
+        assert_eq!(output.contents().lines().count(), 3);
+    }
+}